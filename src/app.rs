@@ -1,15 +1,22 @@
 //! Application state
 
+use std::collections::{BTreeMap, HashSet};
 use std::path::PathBuf;
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use iroh_blobs::ticket::BlobTicket;
+use iroh_blobs::Hash;
 use serde::{Deserialize, Serialize};
 
 use crate::config::Config;
 use crate::input::KeyPreset;
 use crate::theme::ThemeKind;
-use crate::transfer::ConflictResolution;
+use crate::transfer::{
+    list_selected_files, summarize_selection, ConflictResolution, ConflictResolutions, EntryNamer,
+    EntryNaming, EntryRename, ExcludeOptions, FileConflict, HookRun, HookStatus, ManifestMode,
+    NameStrictness, ReceiveOptions, ReceivePolicy, ReceiveSubfolder, Router, SelectedFile,
+    SelectionSummary, SendOptions, ShareUpdate, SkippedEntry, SymlinkMode, VerifyReport,
+};
 use crate::tree_browser::TreeBrowser;
 
 /// Actions requiring async handling
@@ -18,19 +25,37 @@ pub enum AppAction {
     StartSend {
         id: String,
         paths: Vec<PathBuf>,
-        follow_symlinks: bool,
+        options: SendOptions,
+    },
+    StartSendText {
+        id: String,
+        text: String,
+        options: SendOptions,
     },
     StartReceive {
         id: String,
         ticket: BlobTicket,
         output_dir: PathBuf,
+        options: ReceiveOptions,
     },
     CancelTransfer {
         id: String,
     },
     ResolveConflict {
         id: String,
-        resolution: ConflictResolution,
+        resolutions: ConflictResolutions,
+    },
+    ApproveReceive {
+        id: String,
+        approved: bool,
+    },
+    UpdateShare {
+        id: String,
+        update: ShareUpdate,
+    },
+    /// Hash a local file for the verify popup
+    HashFile {
+        path: PathBuf,
     },
 }
 
@@ -158,6 +183,7 @@ const MAX_STORED_FILES: usize = 15;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransferFile {
+    /// Full entry name for receives, file name for sends
     pub name: String,
     pub size: u64,
 }
@@ -174,7 +200,9 @@ pub struct Transfer {
     pub ticket: Option<String>,
     pub connection: ConnectionStatus,
     pub error_message: Option<String>,
-    pub conflict_resolution: Option<ConflictResolution>,
+    pub conflict_resolution: Option<ConflictResolution>, // Set when every conflict got the same choice
+    #[serde(default)]
+    pub conflict_resolutions: Vec<(String, ConflictResolution)>,
     pub duration_secs: Option<f64>,
     #[serde(default)]
     pub files: Vec<TransferFile>,
@@ -182,10 +210,76 @@ pub struct Transfer {
     pub additional_file_count: usize,
     #[serde(skip)]
     pub source_paths: Option<Vec<PathBuf>>,
+    /// Snippet of a text share; never persisted to history
+    #[serde(skip)]
+    pub text: Option<String>,
+    /// Hashing progress while a send is preparing
+    #[serde(skip)]
+    pub import: Option<ImportStatus>,
+    /// Entries a send left out, with reasons
+    #[serde(skip)]
+    pub skipped: Vec<SkippedEntry>,
+    /// Received entries saved under cleaned-up names
+    #[serde(default)]
+    pub renamed: Vec<EntryRename>,
+    /// Entry names a running share serves, for taking entries out
+    #[serde(skip)]
+    pub share_entries: Vec<String>,
+    /// Title the sender gave the share
+    #[serde(skip)]
+    pub title: Option<String>,
+    /// Message the sender attached to the share
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Folder a receive saves into
+    #[serde(default)]
+    pub saved_to: Option<PathBuf>,
+    /// Folders routing rules sent received files to, with a file count each
+    #[serde(default)]
+    pub routed: Vec<(PathBuf, usize)>,
+    /// Received files hashed again once in place
+    #[serde(default)]
+    pub verify: Option<VerifyReport>,
+    /// Collection hash and entry hashes, once the collection is known
+    #[serde(default)]
+    pub hashes: Option<ShareHashes>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportStatus {
+    pub hashed_bytes: u64,
+    pub total_bytes: u64,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub current: String,
+}
+
+impl ImportStatus {
+    pub fn percent(&self) -> f64 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            (self.hashed_bytes as f64 / self.total_bytes as f64) * 100.0
+        }
+    }
 }
 
 impl Transfer {
+    /// A file send whose ticket is out, so entries can still be added or taken out
+    pub fn is_live_share(&self) -> bool {
+        self.direction == TransferDirection::Upload
+            && self.source_paths.is_some()
+            && self.ticket.is_some()
+            && !matches!(
+                self.status,
+                TransferStatus::Failed | TransferStatus::Complete
+            )
+    }
+
     pub fn progress_percent(&self) -> f64 {
+        if let Some(import) = &self.import {
+            return import.percent();
+        }
         if self.total_bytes == 0 {
             0.0
         } else {
@@ -219,6 +313,31 @@ impl Transfer {
     pub fn total_file_count(&self) -> usize {
         self.files.len() + self.additional_file_count
     }
+
+    /// Record per-file conflict choices, collapsing to a single resolution when uniform
+    pub fn set_conflict_resolutions(&mut self, resolutions: &ConflictResolutions) {
+        let mut entries: Vec<(String, ConflictResolution)> = resolutions
+            .iter()
+            .map(|(name, r)| (name.clone(), *r))
+            .collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let first = entries.first().map(|(_, r)| *r);
+        self.conflict_resolution = first.filter(|f| entries.iter().all(|(_, r)| r == f));
+        self.conflict_resolutions = entries;
+    }
+
+    /// Number of conflicts per resolution, in first-seen order
+    pub fn conflict_summary(&self) -> Vec<(ConflictResolution, usize)> {
+        let mut summary: Vec<(ConflictResolution, usize)> = Vec::new();
+        for (_, resolution) in &self.conflict_resolutions {
+            match summary.iter_mut().find(|(r, _)| r == resolution) {
+                Some((_, count)) => *count += 1,
+                None => summary.push((*resolution, 1)),
+            }
+        }
+        summary
+    }
 }
 
 #[derive(Debug)]
@@ -231,19 +350,52 @@ pub struct App {
     pub should_quit: bool,
 
     pub tree_browser: TreeBrowser,
-    pub follow_symlinks: bool,
+    pub symlink_mode: SymlinkMode,
+    pub archive_sends: bool,
+    pub in_place_sends: bool,
+    pub exclude: ExcludeOptions,
+    pub skip_unreadable: bool,
+    pub retire_old_tickets: bool,
+    /// Running share the Send tab currently adds to, instead of starting a send
+    pub adding_to_share: Option<String>,
+    /// Title and note for the next send, cleared once it starts
+    pub share_title: Option<String>,
+    pub share_note: Option<String>,
+    send_summary: Option<(SummaryKey, SelectionSummary)>,
     pub ticket_input: String,
     pub receive_dir: PathBuf,
+    pub auto_extract: bool,
+    pub low_footprint_receive: bool,
+    pub name_strictness: NameStrictness,
+    pub receive_policy: ReceivePolicy,
+    pub receive_subfolder: ReceiveSubfolder,
+    pub receive_routes: Router,
+    pub verify_receives: bool,
+    pub checksum_manifest: ManifestMode,
+    /// Most recent first, at most `RECENT_RECEIVE_DIRS`
+    pub recent_receive_dirs: Vec<PathBuf>,
     pub input_active: bool,
     pub transfers: Vec<Transfer>,
     pub transfer_cursor: usize,
     pub session_history: Vec<Transfer>, // Completed this session
     pub history: Vec<Transfer>,         // Persisted
+    /// Hook runs this session by transfer id, oldest first, at most `RECENT_HOOK_RUNS`
+    pub hook_runs: Vec<(String, HookRun)>,
     pub history_cursor: usize,
     pub show_ticket_popup: Option<String>, // For SSH clipboard issues
     pub conflict_popup: Option<ConflictPopup>,
+    pub approval_popup: Option<ApprovalPopup>,
+    pub destination_popup: Option<DestinationPopup>,
     pub theme_popup: Option<ThemePopup>,
     pub key_preset_popup: Option<KeyPresetPopup>,
+    pub text_compose: Option<TextComposePopup>,
+    pub text_view: Option<TextViewPopup>,
+    pub hash_popup: Option<HashPopup>,
+    pub verify_file_popup: Option<VerifyFilePopup>,
+    pub exclude_popup: Option<ExcludePopup>,
+    pub share_edit_popup: Option<ShareEditPopup>,
+    pub share_label_popup: Option<ShareLabelPopup>,
+    pub send_review: Option<SendReviewPopup>,
     history_path: Option<PathBuf>,
     pub incognito: bool,
 }
@@ -251,9 +403,130 @@ pub struct App {
 #[derive(Debug, Clone)]
 pub struct ConflictPopup {
     pub transfer_id: String,
-    pub conflicts: Vec<(String, PathBuf)>, // (name, existing_path)
+    pub conflicts: Vec<FileConflict>, // Only files whose content differs
+    pub choices: Vec<ConflictResolution>, // Parallel to conflicts
+    pub identical: Vec<String>,       // Skipped automatically
+    pub total_bytes: u64,
+    pub selected: usize, // Cursor into conflicts
+    /// What the sender called the share and said about it
+    pub title: Option<String>,
+    pub note: Option<String>,
+}
+
+impl ConflictPopup {
+    /// Choices a user can cycle through per file (Cancel applies to the whole transfer)
+    pub const CHOICES: [ConflictResolution; 4] = [
+        ConflictResolution::Rename,
+        ConflictResolution::Overwrite,
+        ConflictResolution::Skip,
+        ConflictResolution::KeepNewer,
+    ];
+
+    /// Splits identical files out of the list; returns None if nothing needs a decision
+    pub fn new(
+        transfer_id: String,
+        conflicts: Vec<FileConflict>,
+        total_bytes: u64,
+    ) -> Option<Self> {
+        let (identical, conflicts): (Vec<_>, Vec<_>) =
+            conflicts.into_iter().partition(|c| c.identical);
+        if conflicts.is_empty() {
+            return None;
+        }
+
+        let choices = vec![ConflictResolution::Rename; conflicts.len()];
+        Some(Self {
+            transfer_id,
+            conflicts,
+            choices,
+            identical: identical.into_iter().map(|c| c.name).collect(),
+            total_bytes,
+            selected: 0,
+            title: None,
+            note: None,
+        })
+    }
+
+    pub fn set_selected(&mut self, resolution: ConflictResolution) {
+        if let Some(choice) = self.choices.get_mut(self.selected) {
+            *choice = resolution;
+        }
+    }
+
+    pub fn set_all(&mut self, resolution: ConflictResolution) {
+        self.choices.iter_mut().for_each(|c| *c = resolution);
+    }
+
+    /// Step the current file's choice forward or backward through `CHOICES`
+    pub fn cycle_selected(&mut self, forward: bool) {
+        let Some(choice) = self.choices.get_mut(self.selected) else {
+            return;
+        };
+        let len = Self::CHOICES.len();
+        let pos = Self::CHOICES.iter().position(|c| c == choice).unwrap_or(0);
+        let next = if forward {
+            (pos + 1) % len
+        } else {
+            (pos + len - 1) % len
+        };
+        *choice = Self::CHOICES[next];
+    }
+
+    pub fn resolutions(&self) -> ConflictResolutions {
+        self.conflicts
+            .iter()
+            .map(|c| c.name.clone())
+            .zip(self.choices.iter().copied())
+            .chain(
+                self.identical
+                    .iter()
+                    .map(|name| (name.clone(), ConflictResolution::SkipIdentical)),
+            )
+            .collect()
+    }
+}
+
+/// Destinations remembered for quick reuse
+pub const RECENT_RECEIVE_DIRS: usize = 5;
+
+/// Picker for where the next receive saves to
+#[derive(Debug)]
+pub struct DestinationPopup {
+    pub browser: TreeBrowser,
+}
+
+impl DestinationPopup {
+    pub fn new(current: &std::path::Path) -> Self {
+        let root = if current.is_dir() {
+            current.to_path_buf()
+        } else {
+            std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."))
+        };
+        Self {
+            browser: TreeBrowser::dirs_only(root),
+        }
+    }
+
+    /// Highlighted directory, or the one being browsed if none is
+    pub fn highlighted(&self) -> PathBuf {
+        self.browser
+            .state
+            .selected()
+            .last()
+            .map(|id| id.0.clone())
+            .unwrap_or_else(|| self.browser.root_dir.clone())
+    }
+}
+
+/// A large incoming share waiting for a yes or no before it downloads
+#[derive(Debug, Clone)]
+pub struct ApprovalPopup {
+    pub transfer_id: String,
+    pub files: usize,
     pub total_bytes: u64,
-    pub selected: usize, // 0=Rename, 1=Overwrite, 2=Skip, 3=Cancel
+    pub largest: Vec<(String, u64)>,
+    pub title: Option<String>,
+    pub note: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -266,6 +539,355 @@ pub struct KeyPresetPopup {
     pub selected: usize,
 }
 
+/// Entries walked before the pre-send summary gives up and shows lower bounds
+const SUMMARY_ENTRY_LIMIT: usize = 50_000;
+
+/// Inputs the cached pre-send summary was computed from
+#[derive(Debug, Clone, PartialEq)]
+struct SummaryKey {
+    selected: Vec<PathBuf>,
+    exclude: ExcludeOptions,
+    symlinks: SymlinkMode,
+}
+
+/// Editor for the exclude patterns of the next sends
+#[derive(Debug, Clone, Default)]
+pub struct ExcludePopup {
+    pub input: String,
+}
+
+/// Picker for entries to take out of a running share
+#[derive(Debug, Clone)]
+pub struct ShareEditPopup {
+    pub transfer_id: String,
+    pub entries: Vec<String>,
+    pub remove: Vec<bool>, // Parallel to entries
+    pub selected: usize,
+}
+
+impl ShareEditPopup {
+    pub fn new(transfer_id: String, entries: Vec<String>) -> Self {
+        let remove = vec![false; entries.len()];
+        Self {
+            transfer_id,
+            entries,
+            remove,
+            selected: 0,
+        }
+    }
+
+    pub fn toggle_selected(&mut self) {
+        if let Some(remove) = self.remove.get_mut(self.selected) {
+            *remove = !*remove;
+        }
+    }
+
+    pub fn removed(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .zip(&self.remove)
+            .filter(|(_, remove)| **remove)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+/// Editor for the title and note of the next send
+#[derive(Debug, Clone, Default)]
+pub struct ShareLabelPopup {
+    pub title: String,
+    pub note: String,
+    pub editing_note: bool,
+}
+
+impl ShareLabelPopup {
+    fn field(&mut self) -> &mut String {
+        if self.editing_note {
+            &mut self.note
+        } else {
+            &mut self.title
+        }
+    }
+}
+
+/// Files listed on the review screen; the sender still checks the rest
+const REVIEW_ENTRY_LIMIT: usize = 5_000;
+
+/// What is being typed on the review screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewField {
+    Prefix,
+    Rename,
+}
+
+/// Pre-send review of the entry names, with layout, prefix and renames
+#[derive(Debug, Clone)]
+pub struct SendReviewPopup {
+    pub files: Vec<SelectedFile>,
+    pub roots: Vec<PathBuf>,
+    pub naming: EntryNaming,
+    /// Parallel to files; an error explains why the name can't be used
+    pub names: Vec<Result<String, String>>,
+    pub duplicates: HashSet<String>,
+    pub selected: usize,
+    pub editing: Option<(ReviewField, String)>,
+    pub truncated: bool,
+}
+
+impl SendReviewPopup {
+    pub fn new(paths: &[PathBuf], follow_links: bool, exclude: &ExcludeOptions) -> Self {
+        let files = list_selected_files(paths, follow_links, exclude, REVIEW_ENTRY_LIMIT + 1);
+        let truncated = files.len() > REVIEW_ENTRY_LIMIT;
+        let mut popup = Self {
+            files: files.into_iter().take(REVIEW_ENTRY_LIMIT).collect(),
+            roots: paths.iter().filter_map(|p| p.canonicalize().ok()).collect(),
+            naming: EntryNaming::default(),
+            names: Vec::new(),
+            duplicates: HashSet::new(),
+            selected: 0,
+            editing: None,
+            truncated,
+        };
+        popup.refresh();
+        popup
+    }
+
+    /// Recomputes every name and which of them collide
+    pub fn refresh(&mut self) {
+        let namer = EntryNamer::new(&self.naming, &self.roots);
+        self.names = self
+            .files
+            .iter()
+            .map(|file| match namer.name(&file.root, &file.path, false) {
+                Ok(name) => Ok(name.unwrap_or_default()),
+                Err(e) => Err(e.to_string()),
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        self.duplicates = self
+            .names
+            .iter()
+            .flatten()
+            .filter(|name| !seen.insert(name.as_str()))
+            .cloned()
+            .collect();
+    }
+
+    /// Names are unique and valid, so the collection will be usable
+    pub fn is_sendable(&self) -> bool {
+        self.duplicates.is_empty() && self.names.iter().all(|n| n.is_ok())
+    }
+
+    pub fn start_edit(&mut self, field: ReviewField) {
+        let input = match field {
+            ReviewField::Prefix => self.naming.prefix.clone().unwrap_or_default(),
+            ReviewField::Rename => {
+                let Some(file) = self.files.get(self.selected) else {
+                    return;
+                };
+                match self.naming.renames.get(&file.path) {
+                    Some(name) => name.clone(),
+                    None => EntryNamer::new(&self.naming, &self.roots)
+                        .unrenamed(&file.root, &file.path)
+                        .unwrap_or_default(),
+                }
+            }
+        };
+        self.editing = Some((field, input));
+    }
+
+    pub fn apply_edit(&mut self) {
+        let Some((field, input)) = self.editing.take() else {
+            return;
+        };
+        let input = input.trim().trim_matches('/').to_string();
+        match field {
+            ReviewField::Prefix => {
+                self.naming.prefix = Some(input).filter(|p| !p.is_empty());
+            }
+            ReviewField::Rename => {
+                let Some(file) = self.files.get(self.selected) else {
+                    return;
+                };
+                let unrenamed = EntryNamer::new(&self.naming, &self.roots)
+                    .unrenamed(&file.root, &file.path)
+                    .unwrap_or_default();
+                if input.is_empty() || input == unrenamed {
+                    self.naming.renames.remove(&file.path);
+                } else {
+                    self.naming.renames.insert(file.path.clone(), input);
+                }
+            }
+        }
+        self.refresh();
+    }
+
+    pub fn cycle_layout(&mut self) {
+        self.naming.layout = self.naming.layout.next();
+        self.refresh();
+    }
+}
+
+/// Compose box for sharing a text snippet
+#[derive(Debug, Clone, Default)]
+pub struct TextComposePopup {
+    pub text: String,
+}
+
+/// Viewer for a received text snippet
+#[derive(Debug, Clone)]
+pub struct TextViewPopup {
+    pub text: String,
+    pub scroll: u16,
+    pub copied: bool,
+}
+
+impl TextViewPopup {
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            scroll: 0,
+            copied: false,
+        }
+    }
+
+    pub fn scroll_down(&mut self) {
+        let max = self.text.lines().count().saturating_sub(1) as u16;
+        self.scroll = (self.scroll + 1).min(max);
+    }
+}
+
+/// Entry hashes kept per transfer, so big shares don't bloat history
+const MAX_STORED_HASHES: usize = 1_000;
+
+/// Collection hash of a share and the BLAKE3 hash of each entry, as hex
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareHashes {
+    pub root: String,
+    pub entries: Vec<(String, String)>,
+    /// Entries past `MAX_STORED_HASHES`, not kept
+    #[serde(default)]
+    pub omitted: usize,
+    /// Where a receive saved each entry, after renames and routing
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub saved: BTreeMap<String, PathBuf>,
+}
+
+impl ShareHashes {
+    pub fn new(root: Hash, entries: Vec<(String, Hash)>) -> Self {
+        let omitted = entries.len().saturating_sub(MAX_STORED_HASHES);
+        Self {
+            root: root.to_hex().to_string(),
+            entries: entries
+                .into_iter()
+                .take(MAX_STORED_HASHES)
+                .map(|(name, hash)| (name, hash.to_hex().to_string()))
+                .collect(),
+            omitted,
+            saved: BTreeMap::new(),
+        }
+    }
+
+    /// Keeps the final paths of the entries whose hashes are kept
+    pub fn set_saved(&mut self, saved: Vec<(String, PathBuf)>) {
+        let kept: HashSet<&str> = self.entries.iter().map(|(name, _)| name.as_str()).collect();
+        self.saved = saved
+            .into_iter()
+            .filter(|(name, _)| kept.contains(name.as_str()))
+            .collect();
+    }
+
+    /// One `<hash>  <name>` line per entry, as `b3sum --check` reads them
+    pub fn manifest(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(name, hash)| format!("{}  {}\n", hash, name))
+            .collect()
+    }
+}
+
+/// Hashes of one transfer, browsable and copyable
+#[derive(Debug, Clone)]
+pub struct HashPopup {
+    pub name: String,
+    pub hashes: ShareHashes,
+    /// 0 is the collection hash, entries follow
+    pub selected: usize,
+    pub copied: bool,
+}
+
+impl HashPopup {
+    pub fn new(transfer: &Transfer) -> Option<Self> {
+        Some(Self {
+            name: transfer.name.clone(),
+            hashes: transfer.hashes.clone()?,
+            selected: 0,
+            copied: false,
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.hashes.entries.len() + 1
+    }
+
+    pub fn selected_hash(&self) -> &str {
+        match self.selected.checked_sub(1) {
+            Some(i) => &self.hashes.entries[i].1,
+            None => &self.hashes.root,
+        }
+    }
+
+    /// Verify popup for the highlighted entry, pointed at the file it was
+    /// saved as when known. None on the collection hash, which no file has.
+    fn verify_popup(&self) -> Option<VerifyFilePopup> {
+        let (name, hash) = self.hashes.entries.get(self.selected.checked_sub(1)?)?;
+        let path = self
+            .hashes
+            .saved
+            .get(name)
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        Some(VerifyFilePopup {
+            path,
+            expected: hash.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Checks a local file against a hash given out of band
+#[derive(Debug, Clone, Default)]
+pub struct VerifyFilePopup {
+    pub path: String,
+    pub expected: String,
+    pub editing_hash: bool,
+    pub check: Option<FileCheck>,
+}
+
+impl VerifyFilePopup {
+    fn field(&mut self) -> &mut String {
+        if self.editing_hash {
+            &mut self.expected
+        } else {
+            &mut self.path
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileCheck {
+    /// Waiting for the file to be hashed
+    Hashing {
+        path: PathBuf,
+        expected: Hash,
+    },
+    Matches,
+    /// The file hashes to this instead
+    Differs(String),
+    Failed(String),
+}
+
 impl ThemePopup {
     pub fn new(current: ThemeKind) -> Self {
         let selected = ThemeKind::ALL
@@ -293,6 +915,8 @@ impl Default for App {
 }
 
 const MAX_HISTORY_ENTRIES: usize = 100;
+/// Hook runs kept for the Active tab
+const RECENT_HOOK_RUNS: usize = 10;
 
 impl App {
     pub fn new() -> Self {
@@ -305,24 +929,53 @@ impl App {
             should_quit: false,
 
             tree_browser: TreeBrowser::new(),
-            follow_symlinks: false,
+            symlink_mode: SymlinkMode::default(),
+            archive_sends: false,
+            in_place_sends: false,
+            exclude: ExcludeOptions::default(),
+            skip_unreadable: false,
+            retire_old_tickets: false,
+            adding_to_share: None,
+            share_title: None,
+            share_note: None,
+            send_summary: None,
 
             ticket_input: String::new(),
             receive_dir: dirs::download_dir().unwrap_or_else(|| PathBuf::from(".")),
+            auto_extract: true,
+            low_footprint_receive: false,
+            name_strictness: NameStrictness::default(),
+            receive_policy: ReceivePolicy::default(),
+            receive_subfolder: ReceiveSubfolder::default(),
+            receive_routes: Router::default(),
+            verify_receives: true,
+            checksum_manifest: ManifestMode::default(),
+            recent_receive_dirs: Vec::new(),
             input_active: false,
 
             transfers: Vec::new(),
             transfer_cursor: 0,
 
             session_history: Vec::new(),
+            hook_runs: Vec::new(),
 
             history: Vec::new(),
             history_cursor: 0,
 
             show_ticket_popup: None,
             conflict_popup: None,
+            approval_popup: None,
+            destination_popup: None,
             theme_popup: None,
             key_preset_popup: None,
+            text_compose: None,
+            text_view: None,
+            hash_popup: None,
+            verify_file_popup: None,
+            exclude_popup: None,
+            share_edit_popup: None,
+            share_label_popup: None,
+            send_review: None,
 
             history_path: None,
             incognito: false,
@@ -353,6 +1006,91 @@ impl App {
         self
     }
 
+    /// Builder method: Set whether sends default to a single archive
+    pub fn with_archive_sends(mut self, archive_sends: bool) -> Self {
+        self.archive_sends = archive_sends;
+        self
+    }
+
+    /// Builder method: Set whether sends reference files instead of copying them
+    pub fn with_in_place_sends(mut self, in_place_sends: bool) -> Self {
+        self.in_place_sends = in_place_sends;
+        self
+    }
+
+    /// Builder method: Set the exclude rules for directory sends
+    pub fn with_exclude(mut self, exclude: ExcludeOptions) -> Self {
+        self.exclude = exclude;
+        self
+    }
+
+    /// Builder method: Set whether unreadable entries are skipped rather than fatal
+    pub fn with_skip_unreadable(mut self, skip_unreadable: bool) -> Self {
+        self.skip_unreadable = skip_unreadable;
+        self
+    }
+
+    /// Builder method: Set whether earlier tickets stop working once a share changes
+    pub fn with_retire_old_tickets(mut self, retire: bool) -> Self {
+        self.retire_old_tickets = retire;
+        self
+    }
+
+    /// Builder method: Set whether received archives are unpacked
+    pub fn with_auto_extract(mut self, auto_extract: bool) -> Self {
+        self.auto_extract = auto_extract;
+        self
+    }
+
+    /// Builder method: Set whether receives stream past the blob store
+    pub fn with_low_footprint_receive(mut self, low_footprint: bool) -> Self {
+        self.low_footprint_receive = low_footprint;
+        self
+    }
+
+    /// Builder method: Set how far received names are changed to stay portable
+    pub fn with_name_strictness(mut self, strictness: NameStrictness) -> Self {
+        self.name_strictness = strictness;
+        self
+    }
+
+    /// Builder method: Set whether each receive gets a folder of its own
+    pub fn with_receive_subfolder(mut self, subfolder: ReceiveSubfolder) -> Self {
+        self.receive_subfolder = subfolder;
+        self
+    }
+
+    /// Builder method: Set rules that send matching received files elsewhere
+    pub fn with_receive_routes(mut self, routes: Router) -> Self {
+        self.receive_routes = routes;
+        self
+    }
+
+    /// Builder method: Set whether received files are hashed again once in place
+    pub fn with_verify_receives(mut self, verify: bool) -> Self {
+        self.verify_receives = verify;
+        self
+    }
+
+    /// Builder method: Set which checksum manifests receives write
+    pub fn with_checksum_manifest(mut self, manifest: ManifestMode) -> Self {
+        self.checksum_manifest = manifest;
+        self
+    }
+
+    /// Builder method: Set destinations offered for quick reuse
+    pub fn with_recent_receive_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.recent_receive_dirs = dirs;
+        self.recent_receive_dirs.truncate(RECENT_RECEIVE_DIRS);
+        self
+    }
+
+    /// Builder method: Set size, file-count and file-type limits for receives
+    pub fn with_receive_policy(mut self, policy: ReceivePolicy) -> Self {
+        self.receive_policy = policy;
+        self
+    }
+
     /// Builder method: Set history path (if Some, loads history)
     pub fn with_history_path_opt(mut self, path: Option<PathBuf>) -> Self {
         self.history_path = path;
@@ -440,6 +1178,34 @@ impl App {
         self.save_history();
     }
 
+    /// A finished run replaces the entry its start added
+    pub fn record_hook_run(&mut self, id: &str, run: HookRun) {
+        let started = self.hook_runs.iter_mut().rev().find(|(run_id, existing)| {
+            run_id == id && existing.event == run.event && existing.status == HookStatus::Running
+        });
+        match started {
+            Some((_, existing)) => *existing = run,
+            None => {
+                self.hook_runs.push((id.to_string(), run));
+                if self.hook_runs.len() > RECENT_HOOK_RUNS {
+                    self.hook_runs.remove(0);
+                }
+            }
+        }
+    }
+
+    /// Moves the receive directory to the front of the recent destinations
+    fn remember_receive_dir(&mut self) {
+        if self.recent_receive_dirs.first() == Some(&self.receive_dir) {
+            return;
+        }
+        self.recent_receive_dirs
+            .retain(|dir| dir != &self.receive_dir);
+        self.recent_receive_dirs.insert(0, self.receive_dir.clone());
+        self.recent_receive_dirs.truncate(RECENT_RECEIVE_DIRS);
+        self.save_config();
+    }
+
     /// Save current preferences to config file
     fn save_config(&self) {
         // Don't save in incognito mode
@@ -451,6 +1217,10 @@ impl App {
         config.preferences.theme = self.theme.name().to_string();
         config.preferences.key_preset = self.key_preset.name().to_string();
         config.preferences.receive_dir = Some(self.receive_dir.clone());
+        config
+            .preferences
+            .recent_receive_dirs
+            .clone_from(&self.recent_receive_dirs);
 
         if let Err(e) = config.save() {
             tracing::warn!("Failed to save config: {}", e);
@@ -474,9 +1244,54 @@ impl App {
             return self.handle_conflict_popup_key(key);
         }
 
-        if self.theme_popup.is_some() {
-            self.handle_theme_popup_key(key);
-            return None;
+        if self.approval_popup.is_some() {
+            return self.handle_approval_popup_key(key);
+        }
+
+        if self.destination_popup.is_some() {
+            self.handle_destination_popup_key(key);
+            return None;
+        }
+
+        if self.text_compose.is_some() {
+            return self.handle_text_compose_key(key);
+        }
+
+        if self.text_view.is_some() {
+            self.handle_text_view_key(key);
+            return None;
+        }
+
+        if self.verify_file_popup.is_some() {
+            return self.handle_verify_file_popup_key(key);
+        }
+
+        if self.hash_popup.is_some() {
+            self.handle_hash_popup_key(key);
+            return None;
+        }
+
+        if self.exclude_popup.is_some() {
+            self.handle_exclude_popup_key(key);
+            return None;
+        }
+
+        if self.share_edit_popup.is_some() {
+            return self.handle_share_edit_popup_key(key);
+        }
+
+        if self.share_label_popup.is_some() {
+            self.handle_share_label_popup_key(key);
+            return None;
+        }
+
+        if self.send_review.is_some() {
+            return self.handle_send_review_key(key);
+        }
+
+        if self.theme_popup.is_some() {
+            self.handle_theme_popup_key(key);
+            return None;
         }
 
         if self.key_preset_popup.is_some() {
@@ -597,11 +1412,44 @@ impl App {
                 KeyCode::Char('/') => self.tree_browser.start_search(),
                 KeyCode::Char('g') => self.tree_browser.move_to_first(),
                 KeyCode::Char('G') => self.tree_browser.move_to_last(),
-                KeyCode::Char('S') => self.follow_symlinks = !self.follow_symlinks,
-                KeyCode::Char('s') | KeyCode::Enter => {
-                    if !self.tree_browser.selected.is_empty() {
-                        return self.start_send_action();
+                KeyCode::Char('S') => self.symlink_mode = self.symlink_mode.next(),
+                KeyCode::Char('Z') => self.archive_sends = !self.archive_sends,
+                KeyCode::Char('R') => self.in_place_sends = !self.in_place_sends,
+                KeyCode::Char('H') => self.exclude.include_hidden = !self.exclude.include_hidden,
+                KeyCode::Char('I') => {
+                    self.exclude.respect_gitignore = !self.exclude.respect_gitignore;
+                }
+                KeyCode::Char('E') => {
+                    self.exclude_popup = Some(ExcludePopup {
+                        input: self.exclude.patterns.join(", "),
+                    });
+                }
+                KeyCode::Char('T') => self.text_compose = Some(TextComposePopup::default()),
+                KeyCode::Char('N') if self.adding_to_share.is_none() => {
+                    self.share_label_popup = Some(ShareLabelPopup {
+                        title: self.share_title.clone().unwrap_or_default(),
+                        note: self.share_note.clone().unwrap_or_default(),
+                        editing_note: false,
+                    });
+                }
+                KeyCode::Char('V')
+                    if self.adding_to_share.is_none() && !self.tree_browser.selected.is_empty() =>
+                {
+                    self.send_review = Some(SendReviewPopup::new(
+                        &self.tree_browser.selected,
+                        self.symlink_mode == SymlinkMode::Follow,
+                        &self.exclude,
+                    ));
+                }
+                KeyCode::Esc if self.adding_to_share.is_some() => {
+                    self.adding_to_share = None;
+                    self.mode = Mode::Active;
+                }
+                KeyCode::Char('s') | KeyCode::Enter if !self.tree_browser.selected.is_empty() => {
+                    if self.adding_to_share.is_some() {
+                        return self.add_to_share_action();
                     }
+                    return self.start_send_action(EntryNaming::default());
                 }
                 _ => {}
             }
@@ -631,10 +1479,8 @@ impl App {
                 KeyCode::Backspace => {
                     self.ticket_input.pop();
                 }
-                KeyCode::Enter => {
-                    if !self.ticket_input.is_empty() {
-                        return self.start_receive_action();
-                    }
+                KeyCode::Enter if !self.ticket_input.is_empty() => {
+                    return self.start_receive_action();
                 }
                 KeyCode::Esc => self.input_active = false,
                 _ => {}
@@ -642,6 +1488,17 @@ impl App {
         } else {
             match key.code {
                 KeyCode::Enter | KeyCode::Char('i') => self.input_active = true,
+                KeyCode::Char('x') => self.auto_extract = !self.auto_extract,
+                KeyCode::Char('L') => self.low_footprint_receive = !self.low_footprint_receive,
+                KeyCode::Char('d') => {
+                    self.destination_popup = Some(DestinationPopup::new(&self.receive_dir));
+                }
+                KeyCode::Char('f') => self.receive_subfolder = self.receive_subfolder.next(),
+                KeyCode::Char('v') if key.modifiers.is_empty() => {
+                    self.verify_receives = !self.verify_receives;
+                }
+                KeyCode::Char('m') => self.checksum_manifest = self.checksum_manifest.next(),
+                KeyCode::Char('#') => self.verify_file_popup = Some(VerifyFilePopup::default()),
                 KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                     self.input_active = true;
                     if let Ok(mut clipboard) = arboard::Clipboard::new() {
@@ -679,6 +1536,15 @@ impl App {
                     }
                 }
             }
+            KeyCode::Char('v') => {
+                if let Some(text) = self
+                    .transfers
+                    .get(self.transfer_cursor)
+                    .and_then(|t| t.text.clone())
+                {
+                    self.text_view = Some(TextViewPopup::new(text));
+                }
+            }
             KeyCode::Char('p') => {
                 if let Some(transfer) = self.transfers.get_mut(self.transfer_cursor) {
                     match transfer.status {
@@ -702,11 +1568,86 @@ impl App {
                     }
                 }
             }
+            KeyCode::Char('+') => {
+                if let Some(transfer) = self.transfers.get(self.transfer_cursor) {
+                    if transfer.is_live_share() {
+                        // Pick the entries in the Send tab; `s` adds them to this share
+                        self.adding_to_share = Some(transfer.id.clone());
+                        self.mode = Mode::Send;
+                    }
+                }
+            }
+            KeyCode::Char('#') => {
+                self.hash_popup = self
+                    .transfers
+                    .get(self.transfer_cursor)
+                    .and_then(HashPopup::new);
+            }
+            KeyCode::Char('-') => {
+                if let Some(transfer) = self.transfers.get(self.transfer_cursor) {
+                    if transfer.is_live_share() && !transfer.share_entries.is_empty() {
+                        self.share_edit_popup = Some(ShareEditPopup::new(
+                            transfer.id.clone(),
+                            transfer.share_entries.clone(),
+                        ));
+                    }
+                }
+            }
             _ => {}
         }
         None
     }
 
+    fn handle_share_edit_popup_key(&mut self, key: KeyEvent) -> Option<AppAction> {
+        let popup = self.share_edit_popup.as_mut()?;
+
+        if self.key_preset.is_up(&key) {
+            popup.selected = popup.selected.saturating_sub(1);
+            return None;
+        } else if self.key_preset.is_down(&key) {
+            if popup.selected + 1 < popup.entries.len() {
+                popup.selected += 1;
+            }
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Char(' ') => popup.toggle_selected(),
+            KeyCode::Enter => {
+                let id = popup.transfer_id.clone();
+                let remove = popup.removed();
+                self.share_edit_popup = None;
+                if !remove.is_empty() {
+                    return Some(AppAction::UpdateShare {
+                        id,
+                        update: ShareUpdate {
+                            remove,
+                            ..Default::default()
+                        },
+                    });
+                }
+            }
+            KeyCode::Esc => self.share_edit_popup = None,
+            _ => {}
+        }
+        None
+    }
+
+    fn add_to_share_action(&mut self) -> Option<AppAction> {
+        let id = self.adding_to_share.take()?;
+        let add = self.tree_browser.selected.clone();
+        self.tree_browser.clear_selection();
+        self.mode = Mode::Active;
+
+        Some(AppAction::UpdateShare {
+            id,
+            update: ShareUpdate {
+                add,
+                ..Default::default()
+            },
+        })
+    }
+
     fn handle_history_key_with_action(&mut self, key: KeyEvent) -> Option<AppAction> {
         if self.key_preset.is_down(&key) {
             if !self.history.is_empty() {
@@ -748,14 +1689,18 @@ impl App {
                     }
                 }
             }
-            KeyCode::Char('d') => {
-                if !self.history.is_empty() {
-                    self.history.remove(self.history_cursor);
-                    if self.history_cursor > 0 && self.history_cursor >= self.history.len() {
-                        self.history_cursor = self.history.len().saturating_sub(1);
-                    }
-                    self.save_history();
+            KeyCode::Char('#') => {
+                self.hash_popup = self
+                    .history
+                    .get(self.history_cursor)
+                    .and_then(HashPopup::new);
+            }
+            KeyCode::Char('d') if !self.history.is_empty() => {
+                self.history.remove(self.history_cursor);
+                if self.history_cursor > 0 && self.history_cursor >= self.history.len() {
+                    self.history_cursor = self.history.len().saturating_sub(1);
                 }
+                self.save_history();
             }
             _ => {}
         }
@@ -797,10 +1742,22 @@ impl App {
             connection: ConnectionStatus::Connecting,
             error_message: None,
             conflict_resolution: None,
+            conflict_resolutions: Vec::new(),
             duration_secs: None,
             files: Vec::new(),
             additional_file_count: 0,
             source_paths: Some(paths.clone()),
+            text: None,
+            import: None,
+            skipped: Vec::new(),
+            renamed: Vec::new(),
+            share_entries: Vec::new(),
+            title: None,
+            note: None,
+            saved_to: None,
+            routed: Vec::new(),
+            verify: None,
+            hashes: None,
         };
         transfer.set_files(files);
 
@@ -810,11 +1767,83 @@ impl App {
         Some(AppAction::StartSend {
             id,
             paths,
-            follow_symlinks: self.follow_symlinks,
+            options: self.send_options(),
         })
     }
 
-    fn start_send_action(&mut self) -> Option<AppAction> {
+    fn start_send_text_action(&mut self, text: String) -> Option<AppAction> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let options = SendOptions {
+            title: self.share_title.take(),
+            note: self.share_note.take(),
+            ..self.send_options()
+        };
+        self.transfers.push(Transfer {
+            id: id.clone(),
+            direction: TransferDirection::Upload,
+            name: options
+                .title
+                .clone()
+                .unwrap_or_else(|| "Text snippet".to_string()),
+            total_bytes: text.len() as u64,
+            transferred_bytes: 0,
+            speed_bps: 0,
+            status: TransferStatus::Active,
+            ticket: None,
+            connection: ConnectionStatus::Connecting,
+            error_message: None,
+            conflict_resolution: None,
+            conflict_resolutions: Vec::new(),
+            duration_secs: None,
+            files: Vec::new(),
+            additional_file_count: 0,
+            source_paths: None,
+            text: Some(text.clone()),
+            import: None,
+            skipped: Vec::new(),
+            renamed: Vec::new(),
+            share_entries: Vec::new(),
+            title: options.title.clone(),
+            note: options.note.clone(),
+            saved_to: None,
+            routed: Vec::new(),
+            verify: None,
+            hashes: None,
+        });
+        self.mode = Mode::Active;
+
+        Some(AppAction::StartSendText { id, text, options })
+    }
+
+    fn send_options(&self) -> SendOptions {
+        SendOptions {
+            symlinks: self.symlink_mode,
+            archive: self.archive_sends,
+            in_place: self.in_place_sends,
+            exclude: self.exclude.clone(),
+            skip_unreadable: self.skip_unreadable,
+            retire_old_tickets: self.retire_old_tickets,
+            title: None,
+            note: None,
+            naming: EntryNaming::default(),
+        }
+    }
+
+    fn receive_options(&self) -> ReceiveOptions {
+        ReceiveOptions {
+            auto_extract: self.auto_extract,
+            low_footprint: self.low_footprint_receive,
+            sanitize: self.name_strictness,
+            policy: self.receive_policy.clone(),
+            subfolder: self.receive_subfolder,
+            routes: self.receive_routes.clone(),
+            verify: self.verify_receives,
+            manifest: self.checksum_manifest,
+            to_stdout: false,
+        }
+    }
+
+    fn start_send_action(&mut self, naming: EntryNaming) -> Option<AppAction> {
         let paths = self.tree_browser.selected.clone();
         if paths.is_empty() {
             return None;
@@ -842,10 +1871,17 @@ impl App {
             })
             .collect();
 
+        let options = SendOptions {
+            title: self.share_title.take(),
+            note: self.share_note.take(),
+            naming,
+            ..self.send_options()
+        };
+
         let mut transfer = Transfer {
             id: id.clone(),
             direction: TransferDirection::Upload,
-            name,
+            name: options.title.clone().unwrap_or(name),
             total_bytes: 0,
             transferred_bytes: 0,
             speed_bps: 0,
@@ -854,10 +1890,22 @@ impl App {
             connection: ConnectionStatus::Connecting,
             error_message: None,
             conflict_resolution: None,
+            conflict_resolutions: Vec::new(),
             duration_secs: None,
             files: Vec::new(),
             additional_file_count: 0,
             source_paths: Some(paths.clone()),
+            text: None,
+            import: None,
+            skipped: Vec::new(),
+            renamed: Vec::new(),
+            share_entries: Vec::new(),
+            title: options.title.clone(),
+            note: options.note.clone(),
+            saved_to: None,
+            routed: Vec::new(),
+            verify: None,
+            hashes: None,
         };
         transfer.set_files(files);
 
@@ -865,11 +1913,7 @@ impl App {
         self.tree_browser.clear_selection();
         self.mode = Mode::Active;
 
-        Some(AppAction::StartSend {
-            id,
-            paths,
-            follow_symlinks: self.follow_symlinks,
-        })
+        Some(AppAction::StartSend { id, paths, options })
     }
 
     fn start_receive_action(&mut self) -> Option<AppAction> {
@@ -883,143 +1927,499 @@ impl App {
             return None;
         }
 
-        tracing::info!("Parsing ticket...");
-        let ticket = match ticket_str.parse::<BlobTicket>() {
-            Ok(t) => {
-                tracing::info!("Ticket parsed OK, hash: {}", t.hash());
-                t
-            }
-            Err(e) => {
-                tracing::error!("Ticket parse failed: {}", e);
-                let id = uuid::Uuid::new_v4().to_string();
-                let transfer = Transfer {
-                    id,
-                    direction: TransferDirection::Download,
-                    name: "Invalid ticket".to_string(),
-                    total_bytes: 0,
-                    transferred_bytes: 0,
-                    speed_bps: 0,
-                    status: TransferStatus::Failed,
-                    ticket: Some(ticket_str),
-                    connection: ConnectionStatus::Ready,
-                    error_message: Some(format!("Invalid ticket: {}", e)),
-                    conflict_resolution: None,
-                    duration_secs: None,
-                    files: Vec::new(),
-                    additional_file_count: 0,
-                    source_paths: None,
-                };
-                self.transfers.push(transfer);
-                self.ticket_input.clear();
-                self.input_active = false;
-                self.mode = Mode::Active;
-                return None;
-            }
+        tracing::info!("Parsing ticket...");
+        let ticket = match ticket_str.parse::<BlobTicket>() {
+            Ok(t) => {
+                tracing::info!("Ticket parsed OK, hash: {}", t.hash());
+                t
+            }
+            Err(e) => {
+                tracing::error!("Ticket parse failed: {}", e);
+                let id = uuid::Uuid::new_v4().to_string();
+                let transfer = Transfer {
+                    id,
+                    direction: TransferDirection::Download,
+                    name: "Invalid ticket".to_string(),
+                    total_bytes: 0,
+                    transferred_bytes: 0,
+                    speed_bps: 0,
+                    status: TransferStatus::Failed,
+                    ticket: Some(ticket_str),
+                    connection: ConnectionStatus::Ready,
+                    error_message: Some(format!("Invalid ticket: {}", e)),
+                    conflict_resolution: None,
+                    conflict_resolutions: Vec::new(),
+                    duration_secs: None,
+                    files: Vec::new(),
+                    additional_file_count: 0,
+                    source_paths: None,
+                    text: None,
+                    import: None,
+                    skipped: Vec::new(),
+                    renamed: Vec::new(),
+                    share_entries: Vec::new(),
+                    title: None,
+                    note: None,
+                    saved_to: None,
+                    routed: Vec::new(),
+                    verify: None,
+                    hashes: None,
+                };
+                self.transfers.push(transfer);
+                self.ticket_input.clear();
+                self.input_active = false;
+                self.mode = Mode::Active;
+                return None;
+            }
+        };
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let transfer = Transfer {
+            id: id.clone(),
+            direction: TransferDirection::Download,
+            name: "Connecting...".to_string(),
+            total_bytes: 0,
+            transferred_bytes: 0,
+            speed_bps: 0,
+            status: TransferStatus::Active,
+            ticket: Some(ticket_str),
+            connection: ConnectionStatus::Connecting,
+            error_message: None,
+            conflict_resolution: None,
+            conflict_resolutions: Vec::new(),
+            duration_secs: None,
+            files: Vec::new(),
+            additional_file_count: 0,
+            source_paths: None,
+            text: None,
+            import: None,
+            skipped: Vec::new(),
+            renamed: Vec::new(),
+            share_entries: Vec::new(),
+            title: None,
+            note: None,
+            saved_to: Some(self.receive_dir.clone()),
+            routed: Vec::new(),
+            verify: None,
+            hashes: None,
+        };
+
+        self.remember_receive_dir();
+        self.transfers.push(transfer);
+        self.ticket_input.clear();
+        self.input_active = false;
+        self.mode = Mode::Active;
+
+        Some(AppAction::StartReceive {
+            id,
+            ticket,
+            output_dir: self.receive_dir.clone(),
+            options: self.receive_options(),
+        })
+    }
+
+    fn handle_destination_popup_key(&mut self, key: KeyEvent) {
+        let Some(popup) = self.destination_popup.as_mut() else {
+            return;
+        };
+
+        if self.key_preset.is_up(&key) {
+            popup.browser.move_up();
+            return;
+        } else if self.key_preset.is_down(&key) {
+            popup.browser.move_down();
+            return;
+        } else if self.key_preset.is_right(&key) {
+            popup.browser.enter();
+            return;
+        } else if self.key_preset.is_left(&key) {
+            popup.browser.collapse_selected();
+            return;
+        }
+
+        let chosen = match key.code {
+            KeyCode::Enter => {
+                popup.browser.enter();
+                None
+            }
+            KeyCode::Backspace => {
+                popup.browser.go_up();
+                None
+            }
+            KeyCode::Char(' ') | KeyCode::Char('s') => Some(popup.highlighted()),
+            KeyCode::Char('.') => Some(popup.browser.root_dir.clone()),
+            KeyCode::Char(c @ '1'..='9') => c
+                .to_digit(10)
+                .and_then(|n| self.recent_receive_dirs.get(n as usize - 1))
+                .cloned(),
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.destination_popup = None;
+                None
+            }
+            _ => None,
+        };
+
+        if let Some(dir) = chosen {
+            self.receive_dir = dir;
+            self.destination_popup = None;
+            self.remember_receive_dir();
+        }
+    }
+
+    fn handle_approval_popup_key(&mut self, key: KeyEvent) -> Option<AppAction> {
+        let approved = match key.code {
+            KeyCode::Enter | KeyCode::Char('y') => true,
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('q') => false,
+            _ => return None,
+        };
+        let popup = self.approval_popup.take()?;
+        Some(AppAction::ApproveReceive {
+            id: popup.transfer_id,
+            approved,
+        })
+    }
+
+    fn handle_conflict_popup_key(&mut self, key: KeyEvent) -> Option<AppAction> {
+        let popup = self.conflict_popup.as_mut()?;
+
+        if self.key_preset.is_up(&key) {
+            popup.selected = popup.selected.saturating_sub(1);
+            return None;
+        } else if self.key_preset.is_down(&key) {
+            if popup.selected + 1 < popup.conflicts.len() {
+                popup.selected += 1;
+            }
+            return None;
+        } else if self.key_preset.is_right(&key) {
+            popup.cycle_selected(true);
+            return None;
+        } else if self.key_preset.is_left(&key) {
+            popup.cycle_selected(false);
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Enter => {
+                let id = popup.transfer_id.clone();
+                let resolutions = popup.resolutions();
+                self.conflict_popup = None;
+                return Some(AppAction::ResolveConflict { id, resolutions });
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                popup.set_all(ConflictResolution::Cancel);
+                let id = popup.transfer_id.clone();
+                let resolutions = popup.resolutions();
+                self.conflict_popup = None;
+                return Some(AppAction::ResolveConflict { id, resolutions });
+            }
+            KeyCode::Char(' ') => popup.cycle_selected(true),
+            // Lowercase: current file, uppercase: apply to all
+            KeyCode::Char('1') | KeyCode::Char('r') => {
+                popup.set_selected(ConflictResolution::Rename)
+            }
+            KeyCode::Char('2') | KeyCode::Char('o') => {
+                popup.set_selected(ConflictResolution::Overwrite)
+            }
+            KeyCode::Char('3') | KeyCode::Char('s') => popup.set_selected(ConflictResolution::Skip),
+            KeyCode::Char('4') | KeyCode::Char('n') => {
+                popup.set_selected(ConflictResolution::KeepNewer)
+            }
+            KeyCode::Char('R') => popup.set_all(ConflictResolution::Rename),
+            KeyCode::Char('O') => popup.set_all(ConflictResolution::Overwrite),
+            KeyCode::Char('S') => popup.set_all(ConflictResolution::Skip),
+            KeyCode::Char('N') => popup.set_all(ConflictResolution::KeepNewer),
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_text_compose_key(&mut self, key: KeyEvent) -> Option<AppAction> {
+        let popup = self.text_compose.as_mut()?;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Char('v') if ctrl => {
+                if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                    if let Ok(text) = clipboard.get_text() {
+                        popup.text.push_str(&text);
+                    }
+                }
+            }
+            KeyCode::Char('u') if ctrl => popup.text.clear(),
+            KeyCode::Char('j') if ctrl => popup.text.push('\n'),
+            KeyCode::Char(c) => popup.text.push(c),
+            KeyCode::Backspace => {
+                popup.text.pop();
+            }
+            KeyCode::Enter if !popup.text.trim().is_empty() => {
+                let text = std::mem::take(&mut popup.text);
+                self.text_compose = None;
+                return self.start_send_text_action(text);
+            }
+            KeyCode::Esc => self.text_compose = None,
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_send_review_key(&mut self, key: KeyEvent) -> Option<AppAction> {
+        let popup = self.send_review.as_mut()?;
+
+        if popup.editing.is_some() {
+            let (_, input) = popup.editing.as_mut()?;
+            match key.code {
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    input.clear();
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => popup.apply_edit(),
+                KeyCode::Esc => popup.editing = None,
+                _ => {}
+            }
+            return None;
+        }
+
+        if self.key_preset.is_up(&key) {
+            popup.selected = popup.selected.saturating_sub(1);
+            return None;
+        } else if self.key_preset.is_down(&key) {
+            if popup.selected + 1 < popup.files.len() {
+                popup.selected += 1;
+            }
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Char('f') => popup.cycle_layout(),
+            KeyCode::Char('p') => popup.start_edit(ReviewField::Prefix),
+            KeyCode::Char('r') => popup.start_edit(ReviewField::Rename),
+            KeyCode::Enter if popup.is_sendable() => {
+                let naming = popup.naming.clone();
+                self.send_review = None;
+                return self.start_send_action(naming);
+            }
+            KeyCode::Esc => self.send_review = None,
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_share_label_popup_key(&mut self, key: KeyEvent) {
+        let Some(ref mut popup) = self.share_label_popup else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                popup.field().clear();
+            }
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                popup.editing_note = !popup.editing_note;
+            }
+            KeyCode::Char(c) => popup.field().push(c),
+            KeyCode::Backspace => {
+                popup.field().pop();
+            }
+            KeyCode::Enter => {
+                let non_empty = |s: &str| Some(s.trim().to_string()).filter(|s| !s.is_empty());
+                self.share_title = non_empty(&popup.title);
+                self.share_note = non_empty(&popup.note);
+                self.share_label_popup = None;
+            }
+            KeyCode::Esc => self.share_label_popup = None,
+            _ => {}
+        }
+    }
+
+    fn handle_exclude_popup_key(&mut self, key: KeyEvent) {
+        let Some(ref mut popup) = self.exclude_popup else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                popup.input.clear();
+            }
+            KeyCode::Char(c) => popup.input.push(c),
+            KeyCode::Backspace => {
+                popup.input.pop();
+            }
+            KeyCode::Enter => {
+                self.exclude.patterns = parse_patterns(&popup.input);
+                self.exclude_popup = None;
+            }
+            KeyCode::Esc => self.exclude_popup = None,
+            _ => {}
+        }
+    }
+
+    /// Totals for the current selection under the current rules, recomputed
+    /// only when the selection or rules change
+    pub fn send_summary(&mut self) -> Option<SelectionSummary> {
+        if self.tree_browser.selected.is_empty() {
+            return None;
+        }
+
+        let key = SummaryKey {
+            selected: self.tree_browser.selected.clone(),
+            exclude: self.exclude.clone(),
+            symlinks: self.symlink_mode,
         };
+        if let Some((cached_key, summary)) = &self.send_summary {
+            if *cached_key == key {
+                return Some(*summary);
+            }
+        }
 
-        let id = uuid::Uuid::new_v4().to_string();
-        let transfer = Transfer {
-            id: id.clone(),
-            direction: TransferDirection::Download,
-            name: "Connecting...".to_string(),
-            total_bytes: 0,
-            transferred_bytes: 0,
-            speed_bps: 0,
-            status: TransferStatus::Active,
-            ticket: Some(ticket_str),
-            connection: ConnectionStatus::Connecting,
-            error_message: None,
-            conflict_resolution: None,
-            duration_secs: None,
-            files: Vec::new(),
-            additional_file_count: 0,
-            source_paths: None,
+        let summary = summarize_selection(
+            &key.selected,
+            key.symlinks == SymlinkMode::Follow,
+            &key.exclude,
+            SUMMARY_ENTRY_LIMIT,
+        );
+        self.send_summary = Some((key, summary));
+        Some(summary)
+    }
+
+    fn handle_text_view_key(&mut self, key: KeyEvent) {
+        let Some(ref mut popup) = self.text_view else {
+            return;
         };
 
-        self.transfers.push(transfer);
-        self.ticket_input.clear();
-        self.input_active = false;
-        self.mode = Mode::Active;
+        if self.key_preset.is_down(&key) {
+            popup.scroll_down();
+            return;
+        } else if self.key_preset.is_up(&key) {
+            popup.scroll = popup.scroll.saturating_sub(1);
+            return;
+        }
 
-        Some(AppAction::StartReceive {
-            id,
-            ticket,
-            output_dir: self.receive_dir.clone(),
-        })
+        match key.code {
+            KeyCode::Char('c') | KeyCode::Char('y') => {
+                copy_to_clipboard_osc52(&popup.text); // Works over SSH
+                if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                    let _ = clipboard.set_text(popup.text.clone());
+                }
+                popup.copied = true;
+            }
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => self.text_view = None,
+            _ => {}
+        }
     }
 
-    fn handle_conflict_popup_key(&mut self, key: KeyEvent) -> Option<AppAction> {
-        let popup = self.conflict_popup.as_mut()?;
+    fn handle_hash_popup_key(&mut self, key: KeyEvent) {
+        let Some(ref mut popup) = self.hash_popup else {
+            return;
+        };
 
-        if self.key_preset.is_up(&key) {
-            if popup.selected > 0 {
-                popup.selected -= 1;
+        if self.key_preset.is_down(&key) {
+            popup.selected = (popup.selected + 1).min(popup.rows() - 1);
+            popup.copied = false;
+            return;
+        } else if self.key_preset.is_up(&key) {
+            popup.selected = popup.selected.saturating_sub(1);
+            popup.copied = false;
+            return;
+        }
+
+        let text = match key.code {
+            KeyCode::Char('c') | KeyCode::Char('y') | KeyCode::Enter => {
+                popup.selected_hash().to_string()
             }
-            return None;
-        } else if self.key_preset.is_down(&key) {
-            if popup.selected < 3 {
-                popup.selected += 1;
+            KeyCode::Char('a') => popup.hashes.manifest(),
+            KeyCode::Char('v') => {
+                if let Some(verify) = popup.verify_popup() {
+                    self.verify_file_popup = Some(verify);
+                }
+                return;
             }
-            return None;
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.hash_popup = None;
+                return;
+            }
+            _ => return,
+        };
+        copy_to_clipboard_osc52(&text); // Works over SSH
+        if let Ok(mut clipboard) = arboard::Clipboard::new() {
+            let _ = clipboard.set_text(text);
         }
+        popup.copied = true;
+    }
+
+    fn handle_verify_file_popup_key(&mut self, key: KeyEvent) -> Option<AppAction> {
+        let popup = self.verify_file_popup.as_mut()?;
 
         match key.code {
-            KeyCode::Enter => {
-                let resolution = match popup.selected {
-                    0 => ConflictResolution::Rename,
-                    1 => ConflictResolution::Overwrite,
-                    2 => ConflictResolution::Skip,
-                    _ => ConflictResolution::Cancel,
-                };
-                let id = popup.transfer_id.clone();
-                self.conflict_popup = None;
-                return Some(AppAction::ResolveConflict { id, resolution });
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Ok(mut clipboard) = arboard::Clipboard::new() {
+                    if let Ok(text) = clipboard.get_text() {
+                        popup.field().push_str(text.trim());
+                    }
+                }
             }
-            KeyCode::Esc | KeyCode::Char('q') => {
-                let id = popup.transfer_id.clone();
-                self.conflict_popup = None;
-                return Some(AppAction::ResolveConflict {
-                    id,
-                    resolution: ConflictResolution::Cancel,
-                });
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                popup.field().clear();
             }
-            KeyCode::Char('1') => {
-                let id = popup.transfer_id.clone();
-                self.conflict_popup = None;
-                return Some(AppAction::ResolveConflict {
-                    id,
-                    resolution: ConflictResolution::Rename,
-                });
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                popup.editing_hash = !popup.editing_hash;
             }
-            KeyCode::Char('2') => {
-                let id = popup.transfer_id.clone();
-                self.conflict_popup = None;
-                return Some(AppAction::ResolveConflict {
-                    id,
-                    resolution: ConflictResolution::Overwrite,
-                });
+            KeyCode::Char(c) => {
+                popup.field().push(c);
+                popup.check = None;
             }
-            KeyCode::Char('3') => {
-                let id = popup.transfer_id.clone();
-                self.conflict_popup = None;
-                return Some(AppAction::ResolveConflict {
-                    id,
-                    resolution: ConflictResolution::Skip,
-                });
+            KeyCode::Backspace => {
+                popup.field().pop();
+                popup.check = None;
             }
-            KeyCode::Char('4') => {
-                let id = popup.transfer_id.clone();
-                self.conflict_popup = None;
-                return Some(AppAction::ResolveConflict {
-                    id,
-                    resolution: ConflictResolution::Cancel,
-                });
+            KeyCode::Enter => {
+                let path = popup.path.trim();
+                let path = match (path.strip_prefix("~/"), dirs::home_dir()) {
+                    (Some(rest), Some(home)) => home.join(rest),
+                    _ => PathBuf::from(path),
+                };
+                if path.as_os_str().is_empty() {
+                    popup.check = Some(FileCheck::Failed("no file given".to_string()));
+                } else if let Some(expected) = parse_hex_hash(&popup.expected) {
+                    popup.check = Some(FileCheck::Hashing {
+                        path: path.clone(),
+                        expected,
+                    });
+                    return Some(AppAction::HashFile { path });
+                } else {
+                    popup.check = Some(FileCheck::Failed("not a BLAKE3 hash".to_string()));
+                }
             }
+            KeyCode::Esc => self.verify_file_popup = None,
             _ => {}
         }
         None
     }
 
+    /// Result of a `HashFile` action; ignored if the popup moved on
+    pub fn file_hashed(&mut self, path: &std::path::Path, hash: Result<Hash, String>) {
+        let Some(ref mut popup) = self.verify_file_popup else {
+            return;
+        };
+        let Some(FileCheck::Hashing {
+            path: pending,
+            expected,
+        }) = &popup.check
+        else {
+            return;
+        };
+        if pending != path {
+            return;
+        }
+        popup.check = Some(match hash {
+            Ok(hash) if hash == *expected => FileCheck::Matches,
+            Ok(hash) => FileCheck::Differs(hash.to_hex().to_string()),
+            Err(e) => FileCheck::Failed(e),
+        });
+    }
+
     fn handle_theme_popup_key(&mut self, key: KeyEvent) {
         let Some(ref mut popup) = self.theme_popup else {
             return;
@@ -1135,6 +2535,26 @@ mod dirs {
     }
 }
 
+/// Splits a comma-separated pattern list, dropping blanks
+fn parse_patterns(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// A BLAKE3 hash written as 64 hex digits. Checked up front, as `Hash`
+/// parsing panics on some malformed input.
+fn parse_hex_hash(input: &str) -> Option<Hash> {
+    let input = input.trim();
+    if input.len() != 64 || !input.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    input.to_ascii_lowercase().parse().ok()
+}
+
 /// OSC52 clipboard (works over SSH: VSCode, iTerm2, kitty, etc.)
 fn copy_to_clipboard_osc52(text: &str) {
     use std::io::Write;
@@ -1196,10 +2616,22 @@ mod tests {
             connection: ConnectionStatus::P2P,
             error_message: None,
             conflict_resolution: None,
+            conflict_resolutions: Vec::new(),
             duration_secs: None,
             files: vec![],
             additional_file_count: 0,
             source_paths: None,
+            text: None,
+            import: None,
+            skipped: Vec::new(),
+            renamed: Vec::new(),
+            share_entries: Vec::new(),
+            title: None,
+            note: None,
+            saved_to: None,
+            routed: Vec::new(),
+            verify: None,
+            hashes: None,
         }
     }
 
@@ -1611,6 +3043,479 @@ mod tests {
         assert_eq!(transfer.eta_seconds(), Some(7)); // 7500 / 1000 = 7.5, truncated to 7
     }
 
+    // ConflictPopup tests
+    fn test_file_conflict(name: &str, identical: bool) -> FileConflict {
+        FileConflict {
+            name: name.to_string(),
+            path: PathBuf::from("/tmp").join(name),
+            existing_size: 100,
+            existing_modified: None,
+            incoming_size: 100,
+            incoming_modified: None,
+            identical,
+        }
+    }
+
+    fn test_conflict_popup() -> ConflictPopup {
+        ConflictPopup::new(
+            "test".to_string(),
+            vec![
+                test_file_conflict("a.txt", false),
+                test_file_conflict("b.txt", false),
+                test_file_conflict("c.txt", false),
+            ],
+            300,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_conflict_popup_defaults_to_rename() {
+        let popup = test_conflict_popup();
+        let resolutions = popup.resolutions();
+        assert_eq!(resolutions.len(), 3);
+        assert!(resolutions
+            .values()
+            .all(|r| *r == ConflictResolution::Rename));
+    }
+
+    #[test]
+    fn test_conflict_popup_per_file_choices() {
+        let mut popup = test_conflict_popup();
+        popup.set_selected(ConflictResolution::Overwrite);
+        popup.selected = 1;
+        popup.set_selected(ConflictResolution::Skip);
+
+        let resolutions = popup.resolutions();
+        assert_eq!(resolutions["a.txt"], ConflictResolution::Overwrite);
+        assert_eq!(resolutions["b.txt"], ConflictResolution::Skip);
+        assert_eq!(resolutions["c.txt"], ConflictResolution::Rename);
+    }
+
+    #[test]
+    fn test_conflict_popup_cycle_wraps() {
+        let mut popup = test_conflict_popup();
+        popup.cycle_selected(true);
+        assert_eq!(popup.choices[0], ConflictResolution::Overwrite);
+        popup.cycle_selected(true);
+        popup.cycle_selected(true);
+        assert_eq!(popup.choices[0], ConflictResolution::KeepNewer);
+        popup.cycle_selected(true);
+        assert_eq!(popup.choices[0], ConflictResolution::Rename);
+        popup.cycle_selected(false);
+        assert_eq!(popup.choices[0], ConflictResolution::KeepNewer);
+    }
+
+    #[test]
+    fn test_conflict_popup_set_all() {
+        let mut popup = test_conflict_popup();
+        popup.set_selected(ConflictResolution::Overwrite);
+        popup.set_all(ConflictResolution::Skip);
+        assert!(popup.choices.iter().all(|c| *c == ConflictResolution::Skip));
+    }
+
+    #[test]
+    fn test_conflict_popup_separates_identical() {
+        let popup = ConflictPopup::new(
+            "test".to_string(),
+            vec![
+                test_file_conflict("same.txt", true),
+                test_file_conflict("changed.txt", false),
+            ],
+            200,
+        )
+        .unwrap();
+        assert_eq!(popup.conflicts.len(), 1);
+        assert_eq!(popup.identical, vec!["same.txt".to_string()]);
+
+        let resolutions = popup.resolutions();
+        assert_eq!(resolutions["same.txt"], ConflictResolution::SkipIdentical);
+        assert_eq!(resolutions["changed.txt"], ConflictResolution::Rename);
+    }
+
+    #[test]
+    fn test_conflict_popup_all_identical_needs_no_popup() {
+        let popup = ConflictPopup::new(
+            "test".to_string(),
+            vec![test_file_conflict("same.txt", true)],
+            100,
+        );
+        assert!(popup.is_none());
+    }
+
+    #[test]
+    fn test_transfer_set_conflict_resolutions_uniform() {
+        let mut transfer = test_transfer(0, 0, 0);
+        let resolutions = ConflictResolutions::from([
+            ("a".to_string(), ConflictResolution::Skip),
+            ("b".to_string(), ConflictResolution::Skip),
+        ]);
+        transfer.set_conflict_resolutions(&resolutions);
+        assert_eq!(transfer.conflict_resolution, Some(ConflictResolution::Skip));
+        assert_eq!(
+            transfer.conflict_summary(),
+            vec![(ConflictResolution::Skip, 2)]
+        );
+    }
+
+    #[test]
+    fn test_transfer_set_conflict_resolutions_mixed() {
+        let mut transfer = test_transfer(0, 0, 0);
+        let resolutions = ConflictResolutions::from([
+            ("a".to_string(), ConflictResolution::Rename),
+            ("b".to_string(), ConflictResolution::SkipIdentical),
+            ("c".to_string(), ConflictResolution::SkipIdentical),
+        ]);
+        transfer.set_conflict_resolutions(&resolutions);
+        assert_eq!(transfer.conflict_resolution, None);
+        assert_eq!(transfer.conflict_resolutions.len(), 3);
+        assert_eq!(
+            transfer.conflict_summary(),
+            vec![
+                (ConflictResolution::Rename, 1),
+                (ConflictResolution::SkipIdentical, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_patterns() {
+        assert_eq!(
+            parse_patterns(" target/, *.log ,, .DS_Store"),
+            vec!["target/", "*.log", ".DS_Store"]
+        );
+        assert!(parse_patterns(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_destination_picker_remembers_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let older = dir.path().join("older");
+        // Incognito keeps the test from writing the real config
+        let mut app = App::new()
+            .with_incognito(true)
+            .with_receive_dir(dir.path().to_path_buf())
+            .with_recent_receive_dirs(vec![older.clone()]);
+        app.mode = Mode::Receive;
+
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('d')));
+        assert!(app.destination_popup.is_some());
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('1')));
+        assert!(app.destination_popup.is_none());
+        assert_eq!(app.receive_dir, older);
+
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('d')));
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('.')));
+        assert_eq!(app.receive_dir, std::env::current_dir().unwrap());
+        assert_eq!(app.recent_receive_dirs[1], older);
+    }
+
+    #[test]
+    fn test_receive_verify_keys() {
+        let mut app = App::new();
+        app.mode = Mode::Receive;
+        assert!(app.receive_options().verify);
+
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('v')));
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('m')));
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('m')));
+        let options = app.receive_options();
+        assert!(!options.verify);
+        assert_eq!(options.manifest, ManifestMode::Sha256);
+    }
+
+    #[test]
+    fn test_text_snippet_keeps_title_and_note() {
+        let mut app = App::new();
+        app.share_title = Some("Wifi password".to_string());
+        app.share_note = Some("guest network".to_string());
+        app.text_compose = Some(TextComposePopup {
+            text: "hunter2".to_string(),
+        });
+
+        let Some(AppAction::StartSendText { options, .. }) =
+            app.handle_key_with_action(KeyEvent::from(KeyCode::Enter))
+        else {
+            panic!("expected a StartSendText action");
+        };
+        assert_eq!(options.title.as_deref(), Some("Wifi password"));
+        assert_eq!(options.note.as_deref(), Some("guest network"));
+        assert_eq!(app.transfers[0].name, "Wifi password");
+        assert!(app.share_title.is_none());
+    }
+
+    #[test]
+    fn test_hash_popup_copies_and_verifies_entries() {
+        let mut transfer = test_transfer(10, 10, 0);
+        let mut hashes = ShareHashes::new(
+            Hash::new(b"collection"),
+            vec![("docs/a.txt".to_string(), Hash::new(b"a"))],
+        );
+        // Renamed on a conflict; the popup points at where it really went
+        hashes.set_saved(vec![(
+            "docs/a.txt".to_string(),
+            PathBuf::from("/downloads/docs/a (1).txt"),
+        )]);
+        transfer.hashes = Some(hashes);
+        let mut app = App::new();
+        app.mode = Mode::History;
+        app.history = vec![transfer];
+
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('#')));
+        let popup = app.hash_popup.as_ref().unwrap();
+        assert_eq!(
+            popup.selected_hash(),
+            Hash::new(b"collection").to_hex().as_str()
+        );
+        assert_eq!(
+            popup.hashes.manifest(),
+            format!("{}  docs/a.txt\n", Hash::new(b"a").to_hex())
+        );
+
+        // No file has the collection hash
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('v')));
+        assert!(app.verify_file_popup.is_none());
+
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Down));
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('v')));
+        let verify = app.verify_file_popup.as_ref().unwrap();
+        assert_eq!(verify.path, "/downloads/docs/a (1).txt");
+
+        let action = app.handle_key_with_action(KeyEvent::from(KeyCode::Enter));
+        let Some(AppAction::HashFile { path }) = action else {
+            panic!("expected a HashFile action");
+        };
+        app.file_hashed(&path, Ok(Hash::new(b"a")));
+        assert_eq!(
+            app.verify_file_popup.as_ref().unwrap().check,
+            Some(FileCheck::Matches)
+        );
+
+        // Esc goes back to the hash list
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Esc));
+        assert!(app.verify_file_popup.is_none());
+        assert!(app.hash_popup.is_some());
+    }
+
+    #[test]
+    fn test_verify_file_popup_rejects_bad_hashes() {
+        let mut app = App::new();
+        app.mode = Mode::Receive;
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('#')));
+        for c in "notes.txt".chars() {
+            app.handle_key_with_action(KeyEvent::from(KeyCode::Char(c)));
+        }
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Tab));
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('z')));
+
+        assert!(app
+            .handle_key_with_action(KeyEvent::from(KeyCode::Enter))
+            .is_none());
+        assert_eq!(
+            app.verify_file_popup.as_ref().unwrap().check,
+            Some(FileCheck::Failed("not a BLAKE3 hash".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_approval_popup_answers_once() {
+        let mut app = App::new();
+        app.approval_popup = Some(ApprovalPopup {
+            transfer_id: "rx".to_string(),
+            files: 3,
+            total_bytes: 1 << 30,
+            largest: vec![],
+            title: None,
+            note: None,
+        });
+
+        // Other keys leave the popup up
+        assert!(app
+            .handle_key_with_action(KeyEvent::from(KeyCode::Char('x')))
+            .is_none());
+        assert!(app.approval_popup.is_some());
+
+        let action = app.handle_key_with_action(KeyEvent::from(KeyCode::Char('n')));
+        assert!(matches!(
+            action,
+            Some(AppAction::ApproveReceive { ref id, approved: false }) if id == "rx"
+        ));
+        assert!(app.approval_popup.is_none());
+    }
+
+    #[test]
+    fn test_exclude_popup_updates_patterns() {
+        let mut app = App::new().with_exclude(ExcludeOptions {
+            patterns: vec![".git/".to_string()],
+            ..Default::default()
+        });
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('E')));
+        assert_eq!(app.exclude_popup.as_ref().unwrap().input, ".git/");
+
+        for c in ", node_modules/".chars() {
+            app.handle_key_with_action(KeyEvent::from(KeyCode::Char(c)));
+        }
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Enter));
+        assert!(app.exclude_popup.is_none());
+        assert_eq!(app.exclude.patterns, vec![".git/", "node_modules/"]);
+        assert_eq!(app.send_options().exclude.patterns.len(), 2);
+    }
+
+    #[test]
+    fn test_send_summary_tracks_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "aaaa").unwrap();
+        std::fs::write(dir.path().join(".hidden"), "hh").unwrap();
+
+        let mut app = App::new();
+        assert!(app.send_summary().is_none());
+
+        app.tree_browser.selected = vec![dir.path().to_path_buf()];
+        let summary = app.send_summary().unwrap();
+        assert_eq!((summary.files, summary.bytes), (2, 6));
+
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('H')));
+        let summary = app.send_summary().unwrap();
+        assert_eq!((summary.files, summary.bytes), (1, 4));
+        assert_eq!(summary.excluded.entries, 1);
+        assert_eq!(summary.excluded.bytes, 2);
+    }
+
+    #[test]
+    fn test_live_share_add_and_remove() {
+        let mut app = App::new();
+        let mut transfer = test_transfer(100, 0, 0);
+        transfer.source_paths = Some(vec![PathBuf::from("/tmp/a.txt")]);
+        transfer.share_entries = vec!["a.txt".to_string(), "b.txt".to_string()];
+        app.transfers.push(transfer);
+        app.mode = Mode::Active;
+
+        // No ticket yet, so nothing to change
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('+')));
+        assert_eq!(app.mode, Mode::Active);
+        app.transfers[0].ticket = Some("ticket".to_string());
+
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('+')));
+        assert_eq!(app.mode, Mode::Send);
+        app.tree_browser.selected = vec![PathBuf::from("/tmp/c.txt")];
+        let action = app.handle_key_with_action(KeyEvent::from(KeyCode::Char('s')));
+        match action {
+            Some(AppAction::UpdateShare { id, update }) => {
+                assert_eq!(id, "test");
+                assert_eq!(update.add, vec![PathBuf::from("/tmp/c.txt")]);
+            }
+            other => panic!("expected UpdateShare, got {:?}", other),
+        }
+        assert_eq!(app.mode, Mode::Active);
+        assert!(app.adding_to_share.is_none());
+        assert_eq!(app.transfers.len(), 1);
+
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('-')));
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Down));
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char(' ')));
+        let action = app.handle_key_with_action(KeyEvent::from(KeyCode::Enter));
+        match action {
+            Some(AppAction::UpdateShare { update, .. }) => {
+                assert_eq!(update.remove, vec!["b.txt"]);
+                assert!(update.add.is_empty());
+            }
+            other => panic!("expected UpdateShare, got {:?}", other),
+        }
+        assert!(app.share_edit_popup.is_none());
+    }
+
+    #[test]
+    fn test_share_label_applies_to_next_send_only() {
+        let mut app = App::new();
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('N')));
+        for c in "Q3 logs".chars() {
+            app.handle_key_with_action(KeyEvent::from(KeyCode::Char(c)));
+        }
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Tab));
+        for c in " skip tmp ".chars() {
+            app.handle_key_with_action(KeyEvent::from(KeyCode::Char(c)));
+        }
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(app.share_title.as_deref(), Some("Q3 logs"));
+        assert_eq!(app.share_note.as_deref(), Some("skip tmp"));
+
+        app.tree_browser.selected = vec![PathBuf::from("/tmp/logs")];
+        match app.handle_key_with_action(KeyEvent::from(KeyCode::Char('s'))) {
+            Some(AppAction::StartSend { options, .. }) => {
+                assert_eq!(options.title.as_deref(), Some("Q3 logs"));
+                assert_eq!(options.note.as_deref(), Some("skip tmp"));
+            }
+            other => panic!("expected StartSend, got {:?}", other),
+        }
+        assert_eq!(app.transfers[0].name, "Q3 logs");
+        assert!(app.share_title.is_none() && app.share_note.is_none());
+        assert!(app.send_options().title.is_none());
+    }
+
+    #[test]
+    fn test_send_review_blocks_colliding_names() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a")).unwrap();
+        std::fs::create_dir_all(dir.path().join("b")).unwrap();
+        std::fs::write(dir.path().join("a/notes.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("b/notes.txt"), "b").unwrap();
+
+        let mut app = App::new();
+        app.tree_browser.selected = vec![dir.path().join("a"), dir.path().join("b")];
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('V')));
+        let popup = app.send_review.as_mut().unwrap();
+        assert!(popup.is_sendable());
+
+        // Flattened, both files are named notes.txt
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('f')));
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('f')));
+        let popup = app.send_review.as_ref().unwrap();
+        assert!(popup.duplicates.contains("notes.txt"));
+        assert!(app
+            .handle_key_with_action(KeyEvent::from(KeyCode::Enter))
+            .is_none());
+
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Down));
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Char('r')));
+        app.handle_key_with_action(KeyEvent::new(KeyCode::Char('u'), KeyModifiers::CONTROL));
+        for c in "notes-2.txt".chars() {
+            app.handle_key_with_action(KeyEvent::from(KeyCode::Char(c)));
+        }
+        app.handle_key_with_action(KeyEvent::from(KeyCode::Enter));
+        assert!(app.send_review.as_ref().unwrap().is_sendable());
+
+        match app.handle_key_with_action(KeyEvent::from(KeyCode::Enter)) {
+            Some(AppAction::StartSend { options, .. }) => {
+                assert_eq!(options.naming.renames.len(), 1);
+            }
+            other => panic!("expected StartSend, got {:?}", other),
+        }
+        assert!(app.send_review.is_none());
+    }
+
+    #[test]
+    fn test_progress_percent_uses_import_while_preparing() {
+        let mut transfer = test_transfer(1000, 0, 0);
+        transfer.import = Some(ImportStatus {
+            hashed_bytes: 250,
+            total_bytes: 1000,
+            ..Default::default()
+        });
+        assert_eq!(transfer.progress_percent(), 25.0);
+
+        transfer.import = None;
+        assert_eq!(transfer.progress_percent(), 0.0);
+    }
+
+    #[test]
+    fn test_text_view_popup_scroll_clamps_to_last_line() {
+        let mut popup = TextViewPopup::new("one\ntwo\nthree".to_string());
+        for _ in 0..5 {
+            popup.scroll_down();
+        }
+        assert_eq!(popup.scroll, 2);
+    }
+
     #[test]
     fn test_mode_enum_default() {
         assert_eq!(Mode::default(), Mode::Send);
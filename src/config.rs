@@ -16,6 +16,7 @@ pub struct Config {
     pub persistence: PersistenceConfig,
     pub preferences: PreferencesConfig,
     pub transfer: TransferConfig,
+    pub hooks: HooksConfig,
 }
 
 /// Configuration for data persistence features.
@@ -39,6 +40,9 @@ pub struct PreferencesConfig {
     /// Directory to save received files.
     /// If None, defaults to ~/Downloads at runtime.
     pub receive_dir: Option<PathBuf>,
+
+    /// Receive directories used last, most recent first.
+    pub recent_receive_dirs: Vec<PathBuf>,
 }
 
 /// Transfer-related configuration options.
@@ -50,6 +54,96 @@ pub struct TransferConfig {
 
     /// Maximum number of concurrent receive operations.
     pub max_concurrent_receives: usize,
+
+    /// Send selections as a single tar+zstd archive by default.
+    pub archive_sends: bool,
+
+    /// Share files from where they are instead of copying them into the store.
+    /// Saves disk space for very large files, but they must not change while shared.
+    pub in_place_sends: bool,
+
+    /// Gitignore-style patterns left out of directory sends.
+    pub exclude: Vec<String>,
+
+    /// Send dotfiles and dot-directories.
+    pub send_hidden: bool,
+
+    /// Honour `.gitignore` and `.ignore` files inside sent directories.
+    pub respect_gitignore: bool,
+
+    /// Leave out unreadable files and report them instead of failing the send.
+    pub skip_unreadable: bool,
+
+    /// Stop serving earlier tickets when entries are added to or taken out of a share.
+    pub retire_old_tickets: bool,
+
+    /// Unpack received tuit archives instead of saving the `.tar.zst`.
+    pub auto_extract: bool,
+
+    /// Stream received files straight to the receive directory instead of
+    /// keeping a copy in the blob store, roughly halving the disk space needed.
+    pub low_footprint_receive: bool,
+
+    /// How far received names are changed so other systems can store them:
+    /// "off", "portable" or "strict".
+    pub sanitize_names: String,
+
+    /// Give each receive a folder of its own: "off", "share" or "sender".
+    pub receive_subfolder: String,
+
+    /// Refuse shares larger than this, e.g. "20 GiB".
+    pub max_receive_size: Option<String>,
+
+    /// Refuse shares with more files than this.
+    pub max_receive_files: Option<usize>,
+
+    /// Refuse shares containing files with these extensions, e.g. ["exe", "bat"].
+    pub blocked_extensions: Vec<String>,
+
+    /// Ask before downloading shares larger than this, e.g. "2 GiB".
+    pub confirm_receive_size: Option<String>,
+
+    /// Folders other than the receive directory for matching files.
+    pub routes: Vec<RouteConfig>,
+
+    /// Hash received files again once they are in place.
+    pub verify_receives: bool,
+
+    /// Checksum manifests written next to received files: "off", "blake3" or "sha256".
+    pub checksum_manifest: String,
+}
+
+/// A `[[transfer.routes]]` entry: files matching every condition given are
+/// saved under `to` instead of the receive directory.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouteConfig {
+    /// Gitignore-style pattern for entry names, e.g. "*.pdf".
+    pub pattern: Option<String>,
+
+    /// Sender endpoint id, or the start of one.
+    pub sender: Option<String>,
+
+    /// Destination folder; a leading `~` is the home directory.
+    pub to: PathBuf,
+}
+
+/// Commands run when transfers finish. They get the transfer's details in
+/// `TUIT_*` environment variables and as JSON on stdin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Run after a receive has saved every file.
+    pub on_receive_complete: Option<String>,
+
+    /// Run after a peer has fetched a whole share.
+    pub on_send_complete: Option<String>,
+
+    /// Run when a send or receive fails.
+    pub on_failure: Option<String>,
+
+    /// Seconds before a running hook is killed.
+    pub timeout_secs: u64,
 }
 
 impl Default for PersistenceConfig {
@@ -64,6 +158,7 @@ impl Default for PreferencesConfig {
             theme: "default".to_string(),
             key_preset: "arrows".to_string(),
             receive_dir: None,
+            recent_receive_dirs: Vec::new(),
         }
     }
 }
@@ -73,6 +168,39 @@ impl Default for TransferConfig {
         Self {
             max_concurrent_sends: 50,
             max_concurrent_receives: 50,
+            archive_sends: false,
+            in_place_sends: false,
+            exclude: vec![
+                ".git/".to_string(),
+                ".DS_Store".to_string(),
+                "Thumbs.db".to_string(),
+            ],
+            send_hidden: true,
+            respect_gitignore: false,
+            skip_unreadable: false,
+            retire_old_tickets: false,
+            auto_extract: true,
+            low_footprint_receive: false,
+            sanitize_names: "portable".to_string(),
+            receive_subfolder: "off".to_string(),
+            max_receive_size: None,
+            max_receive_files: None,
+            blocked_extensions: Vec::new(),
+            confirm_receive_size: None,
+            routes: Vec::new(),
+            verify_receives: true,
+            checksum_manifest: "off".to_string(),
+        }
+    }
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            on_receive_complete: None,
+            on_send_complete: None,
+            on_failure: None,
+            timeout_secs: 60,
         }
     }
 }
@@ -178,8 +306,31 @@ mod tests {
         assert_eq!(config.preferences.theme, "default");
         assert_eq!(config.preferences.key_preset, "arrows");
         assert_eq!(config.preferences.receive_dir, None);
+        assert!(config.preferences.recent_receive_dirs.is_empty());
         assert_eq!(config.transfer.max_concurrent_sends, 50);
         assert_eq!(config.transfer.max_concurrent_receives, 50);
+        assert!(!config.transfer.archive_sends);
+        assert!(!config.transfer.in_place_sends);
+        assert!(config.transfer.exclude.contains(&".git/".to_string()));
+        assert!(config.transfer.send_hidden);
+        assert!(!config.transfer.respect_gitignore);
+        assert!(!config.transfer.skip_unreadable);
+        assert!(!config.transfer.retire_old_tickets);
+        assert!(config.transfer.auto_extract);
+        assert!(!config.transfer.low_footprint_receive);
+        assert_eq!(config.transfer.sanitize_names, "portable");
+        assert_eq!(config.transfer.receive_subfolder, "off");
+        assert_eq!(config.transfer.max_receive_size, None);
+        assert_eq!(config.transfer.max_receive_files, None);
+        assert!(config.transfer.blocked_extensions.is_empty());
+        assert_eq!(config.transfer.confirm_receive_size, None);
+        assert!(config.transfer.routes.is_empty());
+        assert!(config.transfer.verify_receives);
+        assert_eq!(config.transfer.checksum_manifest, "off");
+        assert_eq!(config.hooks.on_receive_complete, None);
+        assert_eq!(config.hooks.on_send_complete, None);
+        assert_eq!(config.hooks.on_failure, None);
+        assert_eq!(config.hooks.timeout_secs, 60);
     }
 
     #[test]
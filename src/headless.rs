@@ -0,0 +1,229 @@
+//! Headless pipe mode: `tuit send -` and `tuit receive <ticket> [--stdout]`
+//!
+//! Drives the same transfer manager as the TUI, but reports to stderr so
+//! stdout stays free for piped data.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use iroh_blobs::ticket::BlobTicket;
+
+use crate::transfer::{
+    ConflictResolutions, HookStatus, ReceiveOptions, SendOptions, SendSource, TransferCommand,
+    TransferManager, TransferProgress,
+};
+
+const TRANSFER_ID: &str = "headless";
+
+/// Imports stdin as a single entry and serves it until a peer fetches it
+pub async fn send_stdin(
+    manager: &mut TransferManager,
+    name: String,
+    options: SendOptions,
+) -> Result<()> {
+    manager
+        .send_command(TransferCommand::Send {
+            id: TRANSFER_ID.to_string(),
+            source: SendSource::Stdin { name },
+            options,
+        })
+        .await?;
+    drive(manager).await
+}
+
+/// Receives into `output_dir`, or to stdout when `options.to_stdout` is set.
+/// Conflicts are auto-renamed and shares needing confirmation are declined,
+/// as there is nobody to ask.
+pub async fn receive(
+    manager: &mut TransferManager,
+    ticket: &str,
+    output_dir: PathBuf,
+    options: ReceiveOptions,
+) -> Result<()> {
+    let ticket: BlobTicket = ticket.trim().parse().context("invalid ticket")?;
+    manager
+        .send_command(TransferCommand::Receive {
+            id: TRANSFER_ID.to_string(),
+            ticket,
+            output_dir,
+            options,
+        })
+        .await?;
+    drive(manager).await
+}
+
+/// Returns once the transfer has ended and any hooks it triggered have finished
+async fn drive(manager: &mut TransferManager) -> Result<()> {
+    let mut total_bytes = 0u64;
+    let mut hooks_running = 0usize;
+    let mut outcome: Option<Result<()>> = None;
+    let mut verify_failed = false;
+
+    loop {
+        if hooks_running == 0 {
+            if let Some(result) = outcome.take() {
+                return result;
+            }
+        }
+
+        let progress = tokio::select! {
+            progress = manager.recv_progress() => progress,
+            _ = tokio::signal::ctrl_c() => {
+                manager
+                    .send_command(TransferCommand::Cancel { id: TRANSFER_ID.to_string() })
+                    .await?;
+                continue;
+            }
+        };
+        let Some(progress) = progress else {
+            anyhow::bail!("transfer manager stopped unexpectedly");
+        };
+
+        match progress {
+            TransferProgress::Preparing { status, .. } => eprintln!("{}", status),
+            TransferProgress::TicketReady { ticket, .. } => {
+                eprintln!("Ticket (receive with `tuit receive <ticket>`):");
+                eprintln!("{}", ticket);
+            }
+            TransferProgress::Started {
+                name,
+                total_bytes: total,
+                ..
+            } => {
+                total_bytes = total;
+                eprintln!("{} ({})", name, format_size(total));
+            }
+            TransferProgress::ShareInfo { title, note, .. } => {
+                if let Some(title) = title {
+                    eprintln!("Share: {}", title);
+                }
+                if let Some(note) = note {
+                    eprintln!("Note: {}", note);
+                }
+            }
+            TransferProgress::Skipped { entries, .. } => {
+                for entry in entries {
+                    eprintln!("Skipped {}: {}", entry.path, entry.reason);
+                }
+            }
+            TransferProgress::Renamed { renames, .. } => {
+                for rename in renames {
+                    eprintln!("Renamed {} -> {}", rename.from, rename.to);
+                }
+            }
+            TransferProgress::Connecting { .. } => eprintln!("Waiting for peer..."),
+            TransferProgress::Connected { is_relay, .. } => {
+                eprintln!("Connected ({})", if is_relay { "relay" } else { "direct" });
+            }
+            TransferProgress::Progress {
+                transferred_bytes,
+                speed_bps,
+                ..
+            } => {
+                // The sender also counts collection metadata; cap at the payload
+                let transferred_bytes = transferred_bytes.min(total_bytes);
+                let percent = (transferred_bytes * 100)
+                    .checked_div(total_bytes)
+                    .unwrap_or(100);
+                eprint!(
+                    "\r{:>3}%  {} / {}  {}/s   ",
+                    percent,
+                    format_size(transferred_bytes),
+                    format_size(total_bytes),
+                    format_size(speed_bps)
+                );
+                let _ = std::io::stderr().flush();
+            }
+            TransferProgress::FileConflicts { .. } => {
+                manager
+                    .send_command(TransferCommand::ResolveConflict {
+                        id: TRANSFER_ID.to_string(),
+                        resolutions: ConflictResolutions::new(),
+                    })
+                    .await?;
+            }
+            TransferProgress::Destination { dir, .. } => {
+                eprintln!("Saving to {}", dir.display());
+            }
+            TransferProgress::Hashes { root, .. } => eprintln!("Collection hash: {}", root),
+            TransferProgress::Routed { dirs, .. } => {
+                for (dir, files) in dirs {
+                    eprintln!("Routed {} file(s) to {}", files, dir.display());
+                }
+            }
+            TransferProgress::ApprovalNeeded { total_bytes, .. } => {
+                eprintln!(
+                    "Share is {}, over confirm_receive_size; declining",
+                    format_size(total_bytes)
+                );
+                manager
+                    .send_command(TransferCommand::ApproveReceive {
+                        id: TRANSFER_ID.to_string(),
+                        approved: false,
+                    })
+                    .await?;
+            }
+            TransferProgress::Completed {
+                total_bytes,
+                duration_secs,
+                ..
+            } => {
+                eprintln!(
+                    "\nDone: {} in {:.1}s",
+                    format_size(total_bytes),
+                    duration_secs
+                );
+                outcome = Some(if verify_failed {
+                    Err(anyhow::anyhow!("some received files failed verification"))
+                } else {
+                    Ok(())
+                });
+            }
+            TransferProgress::Failed { error, .. } => outcome = Some(Err(anyhow::anyhow!(error))),
+            TransferProgress::Hook { run, .. } => match &run.status {
+                HookStatus::Running => {
+                    hooks_running += 1;
+                    eprintln!("Running {} hook: {}", run.event.label(), run.command);
+                }
+                status => {
+                    hooks_running = hooks_running.saturating_sub(1);
+                    for line in run.output.lines() {
+                        eprintln!("  {}", line);
+                    }
+                    if let HookStatus::Failed(reason) = status {
+                        eprintln!("{} hook failed: {}", run.event.label(), reason);
+                    }
+                }
+            },
+            TransferProgress::Cancelled { .. } => anyhow::bail!("transfer cancelled"),
+            TransferProgress::TextReceived { text, .. } => println!("{}", text),
+            TransferProgress::ShareUpdateFailed { error, .. } => {
+                eprintln!("Share not updated: {}", error);
+            }
+            TransferProgress::Verified { report, .. } => {
+                eprintln!("\nVerified {} of {} files", report.passed(), report.checked);
+                for (name, reason) in &report.failed {
+                    eprintln!("  FAILED {}: {}", name, reason);
+                }
+                for manifest in &report.manifests {
+                    eprintln!("Wrote {}", manifest.display());
+                }
+                if let Some(error) = &report.manifest_error {
+                    eprintln!("Manifest not written: {}", error);
+                }
+                verify_failed = !report.failed.is_empty();
+            }
+            TransferProgress::Queued { .. }
+            | TransferProgress::Saved { .. }
+            | TransferProgress::FileList { .. }
+            | TransferProgress::ImportProgress { .. }
+            | TransferProgress::ShareContents { .. }
+            | TransferProgress::FileHashed { .. } => {}
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    humansize::format_size(bytes, humansize::BINARY)
+}
@@ -2,6 +2,7 @@
 
 mod app;
 mod config;
+mod headless;
 mod input;
 mod theme;
 mod transfer;
@@ -12,7 +13,7 @@ use std::io;
 use std::time::Duration;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind},
     execute,
@@ -22,7 +23,11 @@ use ratatui::{backend::CrosstermBackend, Terminal};
 
 use app::App;
 use config::Config;
-use transfer::{TransferCommand, TransferLimits, TransferManager, TransferProgress};
+use transfer::{
+    ExcludeOptions, Hooks, ManifestMode, NameStrictness, ReceivePolicy, ReceiveSubfolder,
+    RouteRule, Router, SendOptions, SendSource, TransferCommand, TransferLimits, TransferManager,
+    TransferProgress,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "tuit", about = "P2P file transfers via iroh", version)]
@@ -38,6 +43,40 @@ struct Args {
     /// Override receive directory
     #[arg(long, value_name = "PATH")]
     receive_dir: Option<std::path::PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Headless commands for pipelines; without one, the TUI starts
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Send stdin without the TUI, e.g. `pg_dump | tuit send - --name db.sql`
+    Send {
+        /// `-` to read from stdin
+        #[arg(value_name = "SOURCE")]
+        source: String,
+
+        /// File name the receiver sees
+        #[arg(long, default_value = "stdin")]
+        name: String,
+
+        /// Share title shown to the receiver
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Short message shown to the receiver
+        #[arg(long)]
+        note: Option<String>,
+    },
+    /// Receive without the TUI; progress goes to stderr
+    Receive {
+        ticket: String,
+
+        /// Write a single-file share to stdout instead of the receive directory
+        #[arg(long)]
+        stdout: bool,
+    },
 }
 
 fn main() -> Result<()> {
@@ -65,12 +104,19 @@ async fn async_main() -> Result<()> {
     } else {
         Config::load_from(args.config.clone())
     };
+    // Built before the terminal is set up, so config warnings aren't drawn over
+    let policy = receive_policy(&config.transfer);
 
     // Override receive_dir: CLI > config > current directory
     let receive_dir = args
         .receive_dir
         .or(config.preferences.receive_dir.clone())
         .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+    // Staging areas of receives that didn't get to clean up after themselves
+    transfer::remove_stale_staging(&receive_dir);
+    for dir in &config.preferences.recent_receive_dirs {
+        transfer::remove_stale_staging(dir);
+    }
 
     // Per-instance data directory to avoid conflicts
     let instance_id = std::process::id();
@@ -93,7 +139,65 @@ async fn async_main() -> Result<()> {
         max_concurrent_receives: config.transfer.max_concurrent_receives,
     };
 
-    let mut transfer_manager = TransferManager::with_limits(data_dir.clone(), limits).await?;
+    // Incognito sessions never run hooks, whatever the config says
+    let hooks = if args.incognito {
+        Hooks::default()
+    } else {
+        Hooks {
+            on_receive_complete: config.hooks.on_receive_complete.clone(),
+            on_send_complete: config.hooks.on_send_complete.clone(),
+            on_failure: config.hooks.on_failure.clone(),
+            timeout: Duration::from_secs(config.hooks.timeout_secs),
+        }
+    };
+
+    let mut transfer_manager =
+        TransferManager::with_limits(data_dir.clone(), limits, hooks).await?;
+
+    // Headless pipe mode skips the TUI entirely
+    if let Some(command) = args.command {
+        let result = match command {
+            Command::Send {
+                source,
+                name,
+                title,
+                note,
+            } => {
+                anyhow::ensure!(
+                    source == "-",
+                    "only `-` (stdin) can be sent from the command line; use the TUI for files"
+                );
+                let options = SendOptions {
+                    title,
+                    note,
+                    ..Default::default()
+                };
+                headless::send_stdin(&mut transfer_manager, name, options).await
+            }
+            Command::Receive { ticket, stdout } => {
+                let options = transfer::ReceiveOptions {
+                    auto_extract: config.transfer.auto_extract,
+                    low_footprint: config.transfer.low_footprint_receive,
+                    sanitize: NameStrictness::from_name(&config.transfer.sanitize_names),
+                    policy,
+                    subfolder: ReceiveSubfolder::from_name(&config.transfer.receive_subfolder),
+                    routes: receive_routes(&config.transfer),
+                    verify: config.transfer.verify_receives,
+                    manifest: ManifestMode::from_name(&config.transfer.checksum_manifest),
+                    to_stdout: stdout,
+                };
+                headless::receive(&mut transfer_manager, &ticket, receive_dir, options).await
+            }
+        };
+
+        let _ = transfer_manager
+            .send_command(TransferCommand::Shutdown)
+            .await;
+        if args.incognito {
+            remove_incognito_data(&data_dir);
+        }
+        return result;
+    }
 
     // Terminal setup
     enable_raw_mode()?;
@@ -107,6 +211,26 @@ async fn async_main() -> Result<()> {
         .with_theme_name(&config.preferences.theme)
         .with_key_preset_name(&config.preferences.key_preset)
         .with_receive_dir(receive_dir)
+        .with_archive_sends(config.transfer.archive_sends)
+        .with_in_place_sends(config.transfer.in_place_sends)
+        .with_skip_unreadable(config.transfer.skip_unreadable)
+        .with_retire_old_tickets(config.transfer.retire_old_tickets)
+        .with_exclude(ExcludeOptions {
+            patterns: config.transfer.exclude.clone(),
+            include_hidden: config.transfer.send_hidden,
+            respect_gitignore: config.transfer.respect_gitignore,
+        })
+        .with_auto_extract(config.transfer.auto_extract)
+        .with_low_footprint_receive(config.transfer.low_footprint_receive)
+        .with_name_strictness(NameStrictness::from_name(&config.transfer.sanitize_names))
+        .with_receive_policy(policy)
+        .with_receive_subfolder(ReceiveSubfolder::from_name(
+            &config.transfer.receive_subfolder,
+        ))
+        .with_recent_receive_dirs(config.preferences.recent_receive_dirs.clone())
+        .with_receive_routes(receive_routes(&config.transfer))
+        .with_verify_receives(config.transfer.verify_receives)
+        .with_checksum_manifest(ManifestMode::from_name(&config.transfer.checksum_manifest))
         .with_history_path_opt(history_path);
     let result = run(&mut terminal, &mut app, &mut transfer_manager).await;
 
@@ -117,10 +241,7 @@ async fn async_main() -> Result<()> {
 
     // Clean up blob store if running in incognito mode
     if args.incognito {
-        tracing::info!("Incognito mode: cleaning up data directory");
-        if let Err(e) = std::fs::remove_dir_all(&data_dir) {
-            tracing::warn!("Failed to remove incognito data directory: {}", e);
-        }
+        remove_incognito_data(&data_dir);
     }
 
     disable_raw_mode()?;
@@ -138,6 +259,47 @@ async fn async_main() -> Result<()> {
     Ok(())
 }
 
+fn remove_incognito_data(data_dir: &std::path::Path) {
+    tracing::info!("Incognito mode: cleaning up data directory");
+    if let Err(e) = std::fs::remove_dir_all(data_dir) {
+        tracing::warn!("Failed to remove incognito data directory: {}", e);
+    }
+}
+
+fn receive_routes(config: &config::TransferConfig) -> Router {
+    let rules: Vec<RouteRule> = config
+        .routes
+        .iter()
+        .map(|route| RouteRule {
+            pattern: route.pattern.clone(),
+            sender: route.sender.clone(),
+            to: route.to.clone(),
+        })
+        .collect();
+    Router::new(&rules)
+}
+
+/// Receive limits from config; unreadable sizes are ignored with a warning
+fn receive_policy(config: &config::TransferConfig) -> ReceivePolicy {
+    let size = |setting: &str, value: &Option<String>| {
+        let value = value.as_deref()?;
+        match transfer::parse_size(value) {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                tracing::warn!("Ignoring {} = {:?}: {}", setting, value, e);
+                None
+            }
+        }
+    };
+    ReceivePolicy {
+        max_total_bytes: size("max_receive_size", &config.max_receive_size),
+        max_files: config.max_receive_files,
+        confirm_above_bytes: size("confirm_receive_size", &config.confirm_receive_size),
+        ..Default::default()
+    }
+    .with_blocked_extensions(&config.blocked_extensions)
+}
+
 async fn run<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
@@ -165,16 +327,21 @@ async fn run<B: ratatui::backend::Backend>(
 
                 if let Some(action) = action {
                     match action {
-                        app::AppAction::StartSend {
-                            id,
-                            paths,
-                            follow_symlinks,
-                        } => {
+                        app::AppAction::StartSend { id, paths, options } => {
                             transfer_manager
                                 .send_command(TransferCommand::Send {
                                     id,
-                                    paths,
-                                    follow_symlinks,
+                                    source: SendSource::Paths(paths),
+                                    options,
+                                })
+                                .await?;
+                        }
+                        app::AppAction::StartSendText { id, text, options } => {
+                            transfer_manager
+                                .send_command(TransferCommand::Send {
+                                    id,
+                                    source: SendSource::Text(text),
+                                    options,
                                 })
                                 .await?;
                         }
@@ -182,6 +349,7 @@ async fn run<B: ratatui::backend::Backend>(
                             id,
                             ticket,
                             output_dir,
+                            options,
                         } => {
                             tracing::info!(
                                 "Sending Receive command to transfer manager, id: {}",
@@ -192,6 +360,7 @@ async fn run<B: ratatui::backend::Backend>(
                                     id: id.clone(),
                                     ticket,
                                     output_dir,
+                                    options,
                                 })
                                 .await?;
                             tracing::info!("Receive command sent for id: {}", id);
@@ -201,18 +370,33 @@ async fn run<B: ratatui::backend::Backend>(
                                 .send_command(TransferCommand::Cancel { id })
                                 .await?;
                         }
-                        app::AppAction::ResolveConflict { id, resolution } => {
+                        app::AppAction::UpdateShare { id, update } => {
+                            transfer_manager
+                                .send_command(TransferCommand::UpdateShare { id, update })
+                                .await?;
+                        }
+                        app::AppAction::HashFile { path } => {
+                            transfer_manager
+                                .send_command(TransferCommand::HashFile { path })
+                                .await?;
+                        }
+                        app::AppAction::ApproveReceive { id, approved } => {
+                            transfer_manager
+                                .send_command(TransferCommand::ApproveReceive { id, approved })
+                                .await?;
+                        }
+                        app::AppAction::ResolveConflict { id, resolutions } => {
                             tracing::info!(
-                                "Resolving conflict for id: {}, resolution: {:?}",
+                                "Resolving conflicts for id: {}, resolutions: {:?}",
                                 id,
-                                resolution
+                                resolutions
                             );
-                            // Store the resolution in the transfer for display
+                            // Store the resolutions in the transfer for display
                             if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id) {
-                                transfer.conflict_resolution = Some(resolution.clone());
+                                transfer.set_conflict_resolutions(&resolutions);
                             }
                             transfer_manager
-                                .send_command(TransferCommand::ResolveConflict { id, resolution })
+                                .send_command(TransferCommand::ResolveConflict { id, resolutions })
                                 .await?;
                         }
                     }
@@ -232,6 +416,53 @@ fn handle_transfer_progress(app: &mut App, progress: TransferProgress) {
             if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
                 transfer.status = app::TransferStatus::Preparing;
                 transfer.name = status;
+                transfer.import = None;
+            }
+        }
+        TransferProgress::ImportProgress {
+            id,
+            hashed_bytes,
+            total_bytes,
+            files_done,
+            files_total,
+            current,
+        } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.import = Some(app::ImportStatus {
+                    hashed_bytes,
+                    total_bytes,
+                    files_done,
+                    files_total,
+                    current,
+                });
+            }
+        }
+        TransferProgress::Skipped { id, entries } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                // Entries added to a running share report what they left out separately
+                transfer.skipped.extend(entries);
+            }
+        }
+        TransferProgress::Renamed { id, renames } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                // Archive contents are reported separately as they are unpacked
+                transfer.renamed.extend(renames);
+            }
+        }
+        TransferProgress::ShareInfo { id, title, note } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.title = title;
+                transfer.note = note;
+            }
+        }
+        TransferProgress::ShareContents { id, entries } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.share_entries = entries;
+            }
+        }
+        TransferProgress::ShareUpdateFailed { id, error } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.error_message = Some(format!("Share not updated: {}", error));
             }
         }
         TransferProgress::Connecting { id } => {
@@ -262,6 +493,7 @@ fn handle_transfer_progress(app: &mut App, progress: TransferProgress) {
             if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
                 transfer.name = name;
                 transfer.total_bytes = total_bytes;
+                transfer.import = None;
                 // Don't override status here - let Connecting/Connected handle it
             }
         }
@@ -282,6 +514,7 @@ fn handle_transfer_progress(app: &mut App, progress: TransferProgress) {
         TransferProgress::TicketReady { id, ticket } => {
             if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
                 transfer.ticket = Some(ticket.clone());
+                transfer.error_message = None;
                 if let Ok(mut clipboard) = arboard::Clipboard::new() {
                     let _ = clipboard.set_text(&ticket);
                 }
@@ -322,11 +555,64 @@ fn handle_transfer_progress(app: &mut App, progress: TransferProgress) {
             conflicts,
             total_bytes,
         } => {
-            app.conflict_popup = Some(app::ConflictPopup {
+            // Identical files are skipped by the receiver without asking
+            let identical: transfer::ConflictResolutions = conflicts
+                .iter()
+                .filter(|c| c.identical)
+                .map(|c| (c.name.clone(), transfer::ConflictResolution::SkipIdentical))
+                .collect();
+            app.conflict_popup = app::ConflictPopup::new(id.to_string(), conflicts, total_bytes);
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.set_conflict_resolutions(&identical);
+                if let Some(popup) = app.conflict_popup.as_mut() {
+                    popup.title.clone_from(&transfer.title);
+                    popup.note.clone_from(&transfer.note);
+                }
+            }
+        }
+        TransferProgress::Destination { id, dir } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.saved_to = Some(dir);
+            }
+        }
+        TransferProgress::Saved { id, entries, .. } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                if let Some(hashes) = transfer.hashes.as_mut() {
+                    hashes.set_saved(entries);
+                }
+            }
+        }
+        TransferProgress::Verified { id, report } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.verify = Some(report);
+            }
+        }
+        TransferProgress::Hook { id, run } => app.record_hook_run(&id, run),
+        TransferProgress::Hashes { id, root, entries } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.hashes = Some(app::ShareHashes::new(root, entries));
+            }
+        }
+        TransferProgress::FileHashed { path, hash } => app.file_hashed(&path, hash),
+        TransferProgress::Routed { id, dirs } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.routed = dirs;
+            }
+        }
+        TransferProgress::ApprovalNeeded {
+            id,
+            files,
+            total_bytes,
+            largest,
+        } => {
+            let transfer = app.transfers.iter().find(|t| t.id == id.as_ref());
+            app.approval_popup = Some(app::ApprovalPopup {
                 transfer_id: id.to_string(),
-                conflicts,
+                files,
                 total_bytes,
-                selected: 0,
+                largest,
+                title: transfer.and_then(|t| t.title.clone()),
+                note: transfer.and_then(|t| t.note.clone()),
             });
         }
         TransferProgress::Queued { id, position } => {
@@ -337,6 +623,12 @@ fn handle_transfer_progress(app: &mut App, progress: TransferProgress) {
                 }
             }
         }
+        TransferProgress::TextReceived { id, text } => {
+            if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
+                transfer.text = Some(text.clone());
+            }
+            app.text_view = Some(app::TextViewPopup::new(text));
+        }
         TransferProgress::FileList { id, files } => {
             if let Some(transfer) = app.transfers.iter_mut().find(|t| t.id == id.as_ref()) {
                 let file_list: Vec<app::TransferFile> = files
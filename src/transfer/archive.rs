@@ -0,0 +1,567 @@
+//! Single-blob tar+zstd archive sends
+//!
+//! Many tiny files import and transfer far faster as one compressed blob. The
+//! receiver either keeps the `.tar.zst` or unpacks it with the same path checks
+//! used for regular exports.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::filter::{self, Excluded, SkippedEntry};
+use super::meta::check_symlink_target;
+use super::naming::{EntryNamer, NameCheck};
+use super::rawname;
+use super::{SendOptions, SymlinkMode};
+use anyhow::{Context, Result};
+use tar::EntryType;
+use tokio_util::sync::CancellationToken;
+
+pub const ARCHIVE_EXT: &str = ".tar.zst";
+
+/// zstd's default level; a good speed/ratio balance for mixed content
+const ZSTD_LEVEL: i32 = 3;
+
+/// Reader that publishes how many bytes have been consumed, for progress
+pub struct CountingReader<R> {
+    inner: R,
+    read: Arc<AtomicU64>,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R, read: Arc<AtomicU64>) -> Self {
+        Self { inner, read }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// Archives each path under its own name, honouring the symlink mode, excludes
+/// and unreadable-entry policy. Entries left out are recorded in `skipped`,
+/// and names escaped from non-UTF-8 paths, with their prefixes, in `escaped`
+/// for the receiver to restore.
+pub fn write_archive<W: Write>(
+    paths: &[PathBuf],
+    options: &SendOptions,
+    skipped: &mut Vec<SkippedEntry>,
+    escaped: &mut BTreeSet<String>,
+    out: W,
+) -> Result<W> {
+    let symlinks = options.symlinks;
+    let skip = options.skip_unreadable;
+    let follow_links = symlinks == SymlinkMode::Follow;
+    let mut builder = tar::Builder::new(zstd::Encoder::new(out, ZSTD_LEVEL)?);
+    builder.follow_symlinks(follow_links);
+    let mut excluded = Excluded::default();
+    let mut names = NameCheck::default();
+
+    let roots = paths
+        .iter()
+        .map(|path| path.canonicalize())
+        .collect::<std::io::Result<Vec<_>>>()?;
+    let namer = EntryNamer::new(&options.naming, &roots);
+    for path in &roots {
+        for entry in filter::walk(path, follow_links, &options.exclude, &mut excluded) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    filter::skip_or_fail(skip, skipped, SkippedEntry::from_walk_error(&e))?;
+                    continue;
+                }
+            };
+            let file_type = entry.file_type();
+            let Some(name) = namer.name(path, entry.path(), file_type.is_dir())? else {
+                continue;
+            };
+            if !file_type.is_dir() {
+                names.insert(&name, entry.path())?;
+            }
+            escaped.extend(rawname::escaped_prefixes(&name, entry.path()));
+
+            if file_type.is_symlink() && symlinks == SymlinkMode::Skip {
+                tracing::warn!("skipping symlink: {}", entry.path().display());
+            } else if file_type.is_dir() {
+                builder.append_dir(&name, entry.path())?;
+            } else if file_type.is_file() || file_type.is_symlink() {
+                if file_type.is_file() {
+                    if let Err(e) = filter::check_readable(entry.path()) {
+                        filter::skip_or_fail(skip, skipped, SkippedEntry::new(entry.path(), e))?;
+                        continue;
+                    }
+                }
+                builder.append_path_with_name(entry.path(), &name)?;
+            } else {
+                let reason = filter::special_file_reason(&file_type);
+                tracing::warn!("Skipping {}: {}", entry.path().display(), reason);
+                skipped.push(SkippedEntry::new(entry.path(), reason));
+            }
+        }
+    }
+
+    if excluded.entries > 0 {
+        tracing::info!(
+            "Excluded {} entries ({} bytes) from archive",
+            excluded.entries,
+            excluded.bytes
+        );
+    }
+
+    let encoder = builder.into_inner()?;
+    Ok(encoder.finish()?)
+}
+
+/// Security: archive paths must be plain relative components, like collection names
+fn entry_name(path: &Path) -> Result<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => {
+                parts.push(part.to_str().context("non-UTF8 path in archive")?);
+            }
+            Component::CurDir => {}
+            _ => anyhow::bail!("unsafe path in archive: {}", path.display()),
+        }
+    }
+    anyhow::ensure!(!parts.is_empty(), "empty path in archive");
+    Ok(parts.join("/"))
+}
+
+/// Unpacks a tar+zstd stream. `export_path` maps an entry name to its checked
+/// target, `admit` is asked about every file and symlink before it is written
+/// and aborts the extraction with its error, and `available_path` picks a
+/// free name when a file already exists.
+/// Created files are recorded in `created` so a cancelled or failed extraction
+/// can be cleaned up, and their archived permission bits in `modes`, for the
+/// caller to apply once the files are in place. Returns false if cancelled.
+///
+/// Symlinks are created only once every file and directory is unpacked, so
+/// no entry can be written through a link planted earlier in the archive.
+pub fn extract_archive<R: Read>(
+    reader: R,
+    export_path: impl Fn(&str) -> Result<PathBuf>,
+    mut admit: impl FnMut(&str) -> Result<()>,
+    available_path: impl Fn(&Path) -> PathBuf,
+    cancel_token: &CancellationToken,
+    created: &mut Vec<PathBuf>,
+    modes: &mut Vec<(PathBuf, u32)>,
+) -> Result<bool> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(reader)?);
+    archive.set_preserve_mtime(true);
+    // Entry name to link target, whether it is escaped, and checked path,
+    // created last
+    let mut links = BTreeMap::new();
+
+    for entry in archive.entries()? {
+        if cancel_token.is_cancelled() {
+            return Ok(false);
+        }
+
+        let mut entry = entry?;
+        let name = entry_name(&entry.path()?)?;
+        let target = export_path(&name)?;
+
+        match entry.header().entry_type() {
+            EntryType::Directory => {
+                check_no_link_parents(&name, &target, true)?;
+                std::fs::create_dir_all(&target)?;
+            }
+            EntryType::Regular | EntryType::Continuous => {
+                admit(&name)?;
+                check_no_link_parents(&name, &target, false)?;
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let target = available_path(&target);
+                created.push(target.clone());
+                let mode = entry.header().mode()?;
+                entry.unpack(&target)?;
+                keep_owner_access(&target)?;
+                modes.push((target, mode));
+            }
+            EntryType::Symlink => {
+                let link = entry
+                    .link_name()?
+                    .context("symlink without target in archive")?;
+                // Non-UTF-8 targets are checked in escaped form, like in
+                // regular sends, and restored when the link is made
+                let (link, escaped) = match rawname::encode(link.as_os_str()) {
+                    Some(escaped) => (escaped, true),
+                    None => (link.to_string_lossy().into_owned(), false),
+                };
+                admit(&name)?;
+                links.insert(name, (link, escaped, target));
+            }
+            other => {
+                tracing::warn!("Skipping unsupported archive entry {} ({:?})", name, other);
+            }
+        }
+    }
+
+    for (name, (link, escaped, target)) in &links {
+        if let Err(e) = check_symlink_target(name, link, |n| links.contains_key(n)) {
+            tracing::warn!("Rejecting symlink {}: {}", name, e);
+            continue;
+        }
+        let link = if *escaped {
+            match rawname::decode_target(link) {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    tracing::warn!("Rejecting symlink {}: {}", name, e);
+                    continue;
+                }
+            }
+        } else {
+            link.into()
+        };
+        check_no_link_parents(name, target, false)?;
+        if std::fs::symlink_metadata(target).is_ok() {
+            tracing::warn!("Skipping symlink, path exists: {}", target.display());
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        #[cfg(unix)]
+        let result = std::os::unix::fs::symlink(&link, target);
+        #[cfg(not(unix))]
+        let result: io::Result<()> = {
+            let _ = link;
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "symlinks are only recreated on unix",
+            ))
+        };
+        match result {
+            Ok(()) => created.push(target.clone()),
+            Err(e) => tracing::warn!("Failed to create symlink {}: {}", target.display(), e),
+        }
+    }
+
+    Ok(true)
+}
+
+/// Security: refuses an entry below a symlink already on disk, such as one
+/// left by an earlier archive of the same share. Entry names map to targets
+/// component for component, so the parents are the target's last ancestors.
+/// `itself` checks the target too, for directories created through a link.
+fn check_no_link_parents(name: &str, target: &Path, itself: bool) -> Result<()> {
+    let parents = name.split('/').count() - 1;
+    let first = if itself {
+        Some(target)
+    } else {
+        target.parent()
+    };
+    for path in first
+        .into_iter()
+        .flat_map(Path::ancestors)
+        .take(parents + itself as usize)
+    {
+        let is_link = std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+        anyhow::ensure!(!is_link, "archive entry {} is below a symlink", name);
+    }
+    Ok(())
+}
+
+/// Staged files must stay readable and writable until they are moved into
+/// place, whatever mode the archive gave them
+fn keep_owner_access(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path)?.permissions().mode();
+        if mode & 0o600 != 0o600 {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode | 0o600))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export_under(root: &Path) -> impl Fn(&str) -> Result<PathBuf> + '_ {
+        move |name| Ok(root.join(name))
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let src = tempfile::tempdir().unwrap();
+        let project = src.path().join("project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::create_dir_all(project.join("empty")).unwrap();
+        std::fs::write(project.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let archive = write_archive(
+            &[project],
+            &SendOptions::default(),
+            &mut Vec::new(),
+            &mut BTreeSet::new(),
+            Vec::new(),
+        )
+        .unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut created = Vec::new();
+        let completed = extract_archive(
+            archive.as_slice(),
+            export_under(dest.path()),
+            |_| Ok(()),
+            Path::to_path_buf,
+            &CancellationToken::new(),
+            &mut created,
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert!(completed);
+        assert_eq!(
+            std::fs::read_to_string(dest.path().join("project/src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+        assert!(dest.path().join("project/empty").is_dir());
+        assert_eq!(created, vec![dest.path().join("project/src/main.rs")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_archive_rejects_escaping_symlink() {
+        let src = tempfile::tempdir().unwrap();
+        let project = src.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join("a.txt"), "a").unwrap();
+        std::os::unix::fs::symlink("a.txt", project.join("ok")).unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", project.join("bad")).unwrap();
+
+        let options = SendOptions {
+            symlinks: SymlinkMode::Preserve,
+            ..Default::default()
+        };
+        let archive = write_archive(
+            &[project],
+            &options,
+            &mut Vec::new(),
+            &mut BTreeSet::new(),
+            Vec::new(),
+        )
+        .unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        extract_archive(
+            archive.as_slice(),
+            export_under(dest.path()),
+            |_| Ok(()),
+            Path::to_path_buf,
+            &CancellationToken::new(),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        let ok = dest.path().join("project/ok");
+        assert_eq!(std::fs::read_link(ok).unwrap(), PathBuf::from("a.txt"));
+        assert!(std::fs::symlink_metadata(dest.path().join("project/bad")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_archive_entries_never_pass_through_links() {
+        // A link planted first, then a file meant to be written through it
+        let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, "a/up", "..").unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "a/up/file", &b"x"[..])
+            .unwrap();
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        let root = tempfile::tempdir().unwrap();
+        let dest = root.path().join("dest");
+        std::fs::create_dir(&dest).unwrap();
+        let result = extract_archive(
+            archive.as_slice(),
+            export_under(&dest),
+            |_| Ok(()),
+            Path::to_path_buf,
+            &CancellationToken::new(),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        // The file went into a real directory, and the link came after it
+        assert!(result);
+        assert!(dest.join("a/up").is_dir());
+        assert!(!root.path().join("file").exists());
+        assert!(!std::fs::symlink_metadata(dest.join("a/up"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_archive_refuses_entries_below_existing_links() {
+        let root = tempfile::tempdir().unwrap();
+        let dest = root.path().join("dest");
+        std::fs::create_dir(&dest).unwrap();
+        std::os::unix::fs::symlink("..", dest.join("up")).unwrap();
+
+        let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "up/file", &b"x"[..])
+            .unwrap();
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        assert!(extract_archive(
+            archive.as_slice(),
+            export_under(&dest),
+            |_| Ok(()),
+            Path::to_path_buf,
+            &CancellationToken::new(),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        )
+        .is_err());
+        assert!(!root.path().join("file").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_archive_restores_non_utf8_symlink_targets() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let src = tempfile::tempdir().unwrap();
+        let project = src.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        let target = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        std::fs::write(project.join(target), "menu").unwrap();
+        std::os::unix::fs::symlink(target, project.join("menu")).unwrap();
+        std::fs::write(project.join("z.txt"), "z").unwrap();
+
+        let options = SendOptions {
+            symlinks: SymlinkMode::Preserve,
+            ..Default::default()
+        };
+        let archive = write_archive(
+            &[project],
+            &options,
+            &mut Vec::new(),
+            &mut BTreeSet::new(),
+            Vec::new(),
+        )
+        .unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        let completed = extract_archive(
+            archive.as_slice(),
+            export_under(dest.path()),
+            |_| Ok(()),
+            Path::to_path_buf,
+            &CancellationToken::new(),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert!(completed);
+        assert!(dest.path().join("project/z.txt").exists());
+        assert_eq!(
+            std::fs::read_link(dest.path().join("project/menu")).unwrap(),
+            PathBuf::from(target)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_archive_reports_modes_to_apply_later() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_mode(0o444);
+        builder
+            .append_data(&mut header, "readonly.txt", &b"x"[..])
+            .unwrap();
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut modes = Vec::new();
+        extract_archive(
+            archive.as_slice(),
+            export_under(dest.path()),
+            |_| Ok(()),
+            Path::to_path_buf,
+            &CancellationToken::new(),
+            &mut Vec::new(),
+            &mut modes,
+        )
+        .unwrap();
+
+        // Staged writable, with the archived mode kept for after the commit
+        let path = dest.path().join("readonly.txt");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o600, 0o600);
+        assert_eq!(modes, vec![(path, 0o444)]);
+    }
+
+    #[test]
+    fn test_archive_stops_at_refused_entry() {
+        let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0).unwrap());
+        for name in ["a.txt", "setup.exe", "z.txt"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, &b"x"[..]).unwrap();
+        }
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut seen = Vec::new();
+        let result = extract_archive(
+            archive.as_slice(),
+            export_under(dest.path()),
+            |name| {
+                seen.push(name.to_string());
+                anyhow::ensure!(!name.ends_with(".exe"), "blocked: {}", name);
+                Ok(())
+            },
+            Path::to_path_buf,
+            &CancellationToken::new(),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        );
+
+        assert!(result.unwrap_err().to_string().contains("setup.exe"));
+        assert_eq!(seen, ["a.txt", "setup.exe"]);
+        assert!(!dest.path().join("setup.exe").exists());
+        assert!(!dest.path().join("z.txt").exists());
+    }
+
+    #[test]
+    fn test_entry_name_rejects_traversal() {
+        assert_eq!(entry_name(Path::new("./a/b")).unwrap(), "a/b");
+        assert!(entry_name(Path::new("../a")).is_err());
+        assert!(entry_name(Path::new("/etc/passwd")).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+//! Exclude rules applied while walking a send selection
+//!
+//! Patterns use gitignore syntax and are anchored at each selected directory,
+//! so `target/` drops every `target` directory and `/dist` only the top one.
+//! Explicitly selected paths are never excluded.
+
+use std::fs::FileType;
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use walkdir::{DirEntry, WalkDir};
+
+/// Per-directory ignore files honoured when `respect_gitignore` is set
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExcludeOptions {
+    /// Gitignore-style patterns to leave out
+    pub patterns: Vec<String>,
+    /// Send dotfiles and dot-directories
+    pub include_hidden: bool,
+    /// Honour `.gitignore` and `.ignore` files inside the selection
+    pub respect_gitignore: bool,
+}
+
+impl Default for ExcludeOptions {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            include_hidden: true,
+            respect_gitignore: false,
+        }
+    }
+}
+
+/// What the rules left out: pruned entries and the bytes beneath them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Excluded {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+impl Excluded {
+    fn record(&mut self, entry: &DirEntry) {
+        self.entries += 1;
+        self.bytes += tree_size(entry.path());
+    }
+}
+
+/// Walks `root` like `WalkDir`, pruning excluded entries and tallying them
+pub fn walk<'a>(
+    root: &Path,
+    follow_links: bool,
+    options: &'a ExcludeOptions,
+    excluded: &'a mut Excluded,
+) -> impl Iterator<Item = walkdir::Result<DirEntry>> + 'a {
+    let patterns = build_patterns(root, &options.patterns);
+    // Ignore files of the directories above the current entry, shallowest first
+    let mut ignore_stack: Vec<(usize, Gitignore)> = Vec::new();
+
+    WalkDir::new(root)
+        .follow_links(follow_links)
+        .into_iter()
+        .filter_entry(move |entry| {
+            let depth = entry.depth();
+            while ignore_stack.last().is_some_and(|(d, _)| *d >= depth) {
+                ignore_stack.pop();
+            }
+
+            if depth > 0 && is_excluded(entry, options, &patterns, &ignore_stack) {
+                excluded.record(entry);
+                return false;
+            }
+
+            if options.respect_gitignore && entry.file_type().is_dir() {
+                if let Some(gitignore) = load_ignore_files(entry.path()) {
+                    ignore_stack.push((depth, gitignore));
+                }
+            }
+            true
+        })
+}
+
+/// An entry left out of a send because it could not or should not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+    pub path: String,
+    pub reason: String,
+}
+
+impl SkippedEntry {
+    pub fn new(path: &Path, reason: impl ToString) -> Self {
+        Self {
+            path: path.display().to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn from_walk_error(error: &walkdir::Error) -> Self {
+        let reason = match error.io_error() {
+            Some(io) => io.to_string(),
+            None => error.to_string(),
+        };
+        Self {
+            path: error
+                .path()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            reason,
+        }
+    }
+}
+
+/// Applies the unreadable-entry policy: record and carry on, or fail the send
+pub fn skip_or_fail(
+    skip_unreadable: bool,
+    skipped: &mut Vec<SkippedEntry>,
+    entry: SkippedEntry,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        skip_unreadable,
+        "cannot read {}: {} (set skip_unreadable = true to send the rest)",
+        entry.path,
+        entry.reason
+    );
+    tracing::warn!("Skipping {}: {}", entry.path, entry.reason);
+    skipped.push(entry);
+    Ok(())
+}
+
+/// Opening a FIFO or device would block or never end, so these are never read
+pub fn special_file_reason(file_type: &FileType) -> &'static str {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return "special file (FIFO)";
+        } else if file_type.is_socket() {
+            return "special file (socket)";
+        } else if file_type.is_block_device() {
+            return "special file (block device)";
+        } else if file_type.is_char_device() {
+            return "special file (character device)";
+        }
+    }
+    let _ = file_type;
+    "special file"
+}
+
+/// Opens a regular file up front, so permission problems surface per file
+/// instead of as an import failure midway
+pub fn check_readable(path: &Path) -> std::io::Result<()> {
+    std::fs::File::open(path).map(drop)
+}
+
+/// Size and exclusion totals for a selection, shown before sending
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SelectionSummary {
+    pub files: usize,
+    pub bytes: u64,
+    pub excluded: Excluded,
+    /// The walk stopped at the entry limit, so the totals are lower bounds
+    pub truncated: bool,
+}
+
+/// Walks the selection with the send's rules, stopping after `limit` entries
+pub fn summarize(
+    paths: &[PathBuf],
+    follow_links: bool,
+    options: &ExcludeOptions,
+    limit: usize,
+) -> SelectionSummary {
+    let mut summary = SelectionSummary::default();
+    let mut seen = 0usize;
+
+    for path in paths {
+        let mut excluded = Excluded::default();
+        for entry in walk(path, follow_links, options, &mut excluded).flatten() {
+            seen += 1;
+            if seen > limit {
+                summary.truncated = true;
+                break;
+            }
+            if entry.file_type().is_file() {
+                summary.files += 1;
+                summary.bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+        summary.excluded.entries += excluded.entries;
+        summary.excluded.bytes += excluded.bytes;
+        if summary.truncated {
+            break;
+        }
+    }
+
+    summary
+}
+
+fn is_excluded(
+    entry: &DirEntry,
+    options: &ExcludeOptions,
+    patterns: &Gitignore,
+    ignore_stack: &[(usize, Gitignore)],
+) -> bool {
+    let is_dir = entry.file_type().is_dir();
+
+    if !options.include_hidden && entry.file_name().to_string_lossy().starts_with('.') {
+        return true;
+    }
+    if patterns.matched(entry.path(), is_dir).is_ignore() {
+        return true;
+    }
+
+    // The closest ignore file with an opinion wins, so `!keep.log` can re-include
+    ignore_stack
+        .iter()
+        .rev()
+        .map(|(_, gitignore)| gitignore.matched(entry.path(), is_dir))
+        .find(|m| !m.is_none())
+        .is_some_and(|m| m.is_ignore())
+}
+
+fn build_patterns(root: &Path, patterns: &[String]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        if let Err(e) = builder.add_line(None, pattern) {
+            tracing::warn!("Ignoring invalid exclude pattern {:?}: {}", pattern, e);
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        tracing::warn!("Failed to build exclude patterns: {}", e);
+        Gitignore::empty()
+    })
+}
+
+fn load_ignore_files(dir: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in IGNORE_FILES {
+        let path = dir.join(name);
+        if path.is_file() {
+            found = true;
+            if let Some(e) = builder.add(&path) {
+                tracing::warn!("Problem reading {}: {}", path.display(), e);
+            }
+        }
+    }
+    if !found {
+        return None;
+    }
+    builder.build().ok()
+}
+
+/// Total file bytes at `path`, without following links
+fn tree_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("src/debug.log"), "log").unwrap();
+        std::fs::write(root.join("src/keep.log"), "keep").unwrap();
+        std::fs::write(root.join("target/debug/app"), "0123456789").unwrap();
+        std::fs::write(root.join(".git/HEAD"), "ref").unwrap();
+        std::fs::write(root.join(".DS_Store"), "x").unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::write(root.join("src/.gitignore"), "!keep.log\n").unwrap();
+        dir
+    }
+
+    fn files(root: &Path, options: &ExcludeOptions) -> (Vec<String>, Excluded) {
+        let mut excluded = Excluded::default();
+        let mut names: Vec<String> = walk(root, false, options, &mut excluded)
+            .flatten()
+            .filter(|e| e.file_type().is_file())
+            .map(|e| {
+                let relative = e.path().strip_prefix(root).unwrap();
+                relative.to_string_lossy().replace('\\', "/")
+            })
+            .collect();
+        names.sort();
+        (names, excluded)
+    }
+
+    #[test]
+    fn test_walk_without_rules_keeps_everything() {
+        let dir = project();
+        let (names, excluded) = files(dir.path(), &ExcludeOptions::default());
+        assert_eq!(names.len(), 8);
+        assert_eq!(excluded, Excluded::default());
+    }
+
+    #[test]
+    fn test_walk_patterns_and_hidden() {
+        let dir = project();
+        let options = ExcludeOptions {
+            patterns: vec!["target/".into(), "*.log".into()],
+            include_hidden: false,
+            respect_gitignore: false,
+        };
+        let (names, excluded) = files(dir.path(), &options);
+        assert_eq!(names, vec!["src/main.rs"]);
+        // .DS_Store, .git, .gitignore, target, two logs, src/.gitignore
+        assert_eq!(excluded.entries, 7);
+        assert_eq!(excluded.bytes, 1 + 3 + 14 + 10 + 3 + 4 + 10);
+    }
+
+    #[test]
+    fn test_walk_respects_nested_gitignore() {
+        let dir = project();
+        let options = ExcludeOptions {
+            respect_gitignore: true,
+            ..Default::default()
+        };
+        let (names, excluded) = files(dir.path(), &options);
+        assert!(names.contains(&"src/keep.log".to_string()));
+        assert!(!names.contains(&"src/debug.log".to_string()));
+        assert!(!names.iter().any(|n| n.starts_with("target/")));
+        assert_eq!(excluded.entries, 2);
+    }
+
+    #[test]
+    fn test_selected_root_is_never_excluded() {
+        let dir = project();
+        let options = ExcludeOptions {
+            include_hidden: false,
+            ..Default::default()
+        };
+        let (names, _) = files(&dir.path().join(".git"), &options);
+        assert_eq!(names, vec!["HEAD"]);
+    }
+
+    #[test]
+    fn test_skip_or_fail_policy() {
+        let entry = SkippedEntry::new(Path::new("/data/secret"), "Permission denied");
+
+        let mut skipped = Vec::new();
+        let err = skip_or_fail(false, &mut skipped, entry.clone()).unwrap_err();
+        assert!(err.to_string().contains("/data/secret: Permission denied"));
+        assert!(skipped.is_empty());
+
+        skip_or_fail(true, &mut skipped, entry.clone()).unwrap();
+        assert_eq!(skipped, vec![entry]);
+    }
+
+    #[test]
+    fn test_summarize_counts_included_files() {
+        let dir = project();
+        let options = ExcludeOptions {
+            patterns: vec!["target".into()],
+            ..Default::default()
+        };
+        let summary = summarize(&[dir.path().to_path_buf()], false, &options, 1000);
+        assert_eq!(summary.files, 7);
+        assert_eq!(summary.excluded.entries, 1);
+        assert_eq!(summary.excluded.bytes, 10);
+        assert!(!summary.truncated);
+
+        let summary = summarize(&[dir.path().to_path_buf()], false, &options, 2);
+        assert!(summary.truncated);
+    }
+}
@@ -0,0 +1,467 @@
+//! User commands run when transfers finish
+//!
+//! Hooks run through the platform shell with the transfer's details in
+//! `TUIT_*` environment variables and as JSON on stdin. Their output is
+//! captured and reported as progress; a hook never changes how the transfer
+//! itself ended.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::TransferProgress;
+
+/// Lines of hook output kept for display
+const MAX_OUTPUT_LINES: usize = 20;
+
+/// Shell commands per event; events without one run nothing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hooks {
+    pub on_receive_complete: Option<String>,
+    pub on_send_complete: Option<String>,
+    pub on_failure: Option<String>,
+    /// Hooks still running after this are killed
+    pub timeout: Duration,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            on_receive_complete: None,
+            on_send_complete: None,
+            on_failure: None,
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    ReceiveComplete,
+    SendComplete,
+    Failure,
+}
+
+impl HookEvent {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::ReceiveComplete => "receive-complete",
+            Self::SendComplete => "send-complete",
+            Self::Failure => "failure",
+        }
+    }
+}
+
+/// What a hook is told about the transfer
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookDetails {
+    pub id: String,
+    /// True for sends, false for receives
+    pub sending: bool,
+    pub name: String,
+    pub bytes: u64,
+    /// Files read by a send, or saved by a receive
+    pub paths: Vec<PathBuf>,
+    pub error: Option<String>,
+}
+
+impl HookDetails {
+    pub fn direction(&self) -> &'static str {
+        if self.sending {
+            "send"
+        } else {
+            "receive"
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookStatus {
+    Running,
+    Succeeded,
+    /// Non-zero exit, timeout or a command that couldn't start
+    Failed(String),
+}
+
+/// One hook invocation, as shown in the Active tab
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookRun {
+    pub event: HookEvent,
+    /// Name of the transfer the hook ran for
+    pub transfer: String,
+    pub command: String,
+    pub status: HookStatus,
+    /// Last lines of stdout and stderr combined
+    pub output: String,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: &'static str,
+    id: &'a str,
+    direction: &'static str,
+    name: &'a str,
+    bytes: u64,
+    paths: Vec<String>,
+    error: Option<&'a str>,
+}
+
+impl Hooks {
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        match event {
+            HookEvent::ReceiveComplete => self.on_receive_complete.as_deref(),
+            HookEvent::SendComplete => self.on_send_complete.as_deref(),
+            HookEvent::Failure => self.on_failure.as_deref(),
+        }
+        .map(str::trim)
+        .filter(|command| !command.is_empty())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        [
+            HookEvent::ReceiveComplete,
+            HookEvent::SendComplete,
+            HookEvent::Failure,
+        ]
+        .iter()
+        .all(|&event| self.command(event).is_none())
+    }
+
+    /// The run as it starts, or None when the event has no hook
+    pub fn start(&self, event: HookEvent, details: &HookDetails) -> Option<HookRun> {
+        let command = self.command(event)?;
+        Some(HookRun {
+            event,
+            transfer: if details.name.is_empty() {
+                details.id.clone()
+            } else {
+                details.name.clone()
+            },
+            command: command.to_string(),
+            status: HookStatus::Running,
+            output: String::new(),
+        })
+    }
+
+    /// Runs a started hook to completion, killing it at the timeout
+    pub async fn finish(&self, mut run: HookRun, details: &HookDetails) -> HookRun {
+        let (status, output) = match execute(&run.command, run.event, details, self.timeout).await {
+            Ok(result) => result,
+            Err(e) => (
+                HookStatus::Failed(format!("could not start: {}", e)),
+                String::new(),
+            ),
+        };
+        match &status {
+            HookStatus::Failed(reason) => {
+                tracing::warn!("{} hook failed: {}", run.event.label(), reason)
+            }
+            _ => tracing::info!("{} hook finished", run.event.label()),
+        }
+        run.status = status;
+        run.output = output;
+        run
+    }
+}
+
+/// Collects hook details from progress and says when a hook is due
+#[derive(Debug)]
+pub(super) struct HookTracker {
+    hooks: Hooks,
+    transfers: HashMap<String, HookDetails>,
+}
+
+/// A hook whose run has been announced but not started
+pub(super) struct PendingHook {
+    pub run: HookRun,
+    pub details: HookDetails,
+    hooks: Hooks,
+}
+
+impl PendingHook {
+    pub async fn finish(self) -> HookRun {
+        self.hooks.finish(self.run, &self.details).await
+    }
+}
+
+impl HookTracker {
+    pub fn new(hooks: Hooks) -> Self {
+        Self {
+            hooks,
+            transfers: HashMap::new(),
+        }
+    }
+
+    /// Starts collecting details for a transfer; a no-op without hooks
+    pub fn watch(&mut self, id: &str, sending: bool, paths: Vec<PathBuf>) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        self.transfers.insert(
+            id.to_string(),
+            HookDetails {
+                id: id.to_string(),
+                sending,
+                paths,
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn forget(&mut self, id: &str) {
+        self.transfers.remove(id);
+    }
+
+    /// Records what `progress` tells about its transfer; returns the hook
+    /// to run when it completes or fails
+    pub fn observe(&mut self, progress: &TransferProgress) -> Option<PendingHook> {
+        let (event, details) = match progress {
+            TransferProgress::Started {
+                id,
+                name,
+                total_bytes,
+            } => {
+                let details = self.transfers.get_mut(id.as_ref())?;
+                details.name.clone_from(name);
+                details.bytes = *total_bytes;
+                return None;
+            }
+            TransferProgress::Saved { id, paths, .. } => {
+                self.transfers.get_mut(id.as_ref())?.paths.clone_from(paths);
+                return None;
+            }
+            TransferProgress::Cancelled { id } => {
+                self.transfers.remove(id.as_ref());
+                return None;
+            }
+            TransferProgress::Completed {
+                id, total_bytes, ..
+            } => {
+                let mut details = self.transfers.remove(id.as_ref())?;
+                details.bytes = *total_bytes;
+                let event = if details.sending {
+                    HookEvent::SendComplete
+                } else {
+                    HookEvent::ReceiveComplete
+                };
+                (event, details)
+            }
+            TransferProgress::Failed { id, error } => {
+                let mut details = self.transfers.remove(id.as_ref())?;
+                details.error = Some(error.clone());
+                (HookEvent::Failure, details)
+            }
+            _ => return None,
+        };
+        Some(PendingHook {
+            run: self.hooks.start(event, &details)?,
+            details,
+            hooks: self.hooks.clone(),
+        })
+    }
+}
+
+async fn execute(
+    command: &str,
+    event: HookEvent,
+    details: &HookDetails,
+    timeout: Duration,
+) -> std::io::Result<(HookStatus, String)> {
+    let payload = Payload {
+        event: event.label(),
+        id: &details.id,
+        direction: details.direction(),
+        name: &details.name,
+        bytes: details.bytes,
+        paths: details
+            .paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+        error: details.error.as_deref(),
+    };
+    let json = serde_json::to_vec(&payload).map_err(std::io::Error::other)?;
+
+    // Paths are passed as-is, one per line, so odd names survive
+    let mut paths = OsString::new();
+    for (i, path) in details.paths.iter().enumerate() {
+        if i > 0 {
+            paths.push("\n");
+        }
+        paths.push(path);
+    }
+
+    let mut child = shell(command)
+        .env("TUIT_EVENT", event.label())
+        .env("TUIT_ID", &details.id)
+        .env("TUIT_DIRECTION", details.direction())
+        .env("TUIT_NAME", &details.name)
+        .env("TUIT_BYTES", details.bytes.to_string())
+        .env("TUIT_PATHS", paths)
+        .env("TUIT_ERROR", details.error.as_deref().unwrap_or_default())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    // Written alongside the wait so a hook that ignores stdin can't stall it
+    let stdin = child.stdin.take();
+    let write = async move {
+        if let Some(mut stdin) = stdin {
+            let _ = stdin.write_all(&json).await;
+        }
+    };
+    let run = async {
+        let (_, output) = tokio::join!(write, child.wait_with_output());
+        output
+    };
+    let output = match tokio::time::timeout(timeout, run).await {
+        Ok(output) => output?,
+        Err(_) => {
+            return Ok((
+                HookStatus::Failed(format!("timed out after {}s", timeout.as_secs())),
+                String::new(),
+            ))
+        }
+    };
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    let status = if output.status.success() {
+        HookStatus::Succeeded
+    } else {
+        HookStatus::Failed(match output.status.code() {
+            Some(code) => format!("exit code {}", code),
+            None => "killed by a signal".to_string(),
+        })
+    };
+    Ok((status, tail(&text)))
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+fn tail(text: &str) -> String {
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(MAX_OUTPUT_LINES)..].join("\n")
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn details() -> HookDetails {
+        HookDetails {
+            id: "t1".to_string(),
+            name: "photos".to_string(),
+            bytes: 42,
+            paths: vec![PathBuf::from("/tmp/a.jpg"), PathBuf::from("/tmp/b.jpg")],
+            ..Default::default()
+        }
+    }
+
+    async fn run_hook(command: &str, timeout: Duration) -> HookRun {
+        let hooks = Hooks {
+            on_receive_complete: Some(command.to_string()),
+            timeout,
+            ..Default::default()
+        };
+        let details = details();
+        let run = hooks.start(HookEvent::ReceiveComplete, &details).unwrap();
+        hooks.finish(run, &details).await
+    }
+
+    #[tokio::test]
+    async fn test_hook_gets_details() {
+        let run = run_hook(
+            r#"echo "$TUIT_EVENT $TUIT_DIRECTION $TUIT_NAME $TUIT_BYTES"; echo "$TUIT_PATHS"; cat"#,
+            Duration::from_secs(10),
+        )
+        .await;
+        assert_eq!(run.status, HookStatus::Succeeded);
+        let mut lines = run.output.lines();
+        assert_eq!(lines.next(), Some("receive-complete receive photos 42"));
+        assert_eq!(lines.next(), Some("/tmp/a.jpg"));
+        assert_eq!(lines.next(), Some("/tmp/b.jpg"));
+        let json: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(json["id"], "t1");
+        assert_eq!(json["paths"][1], "/tmp/b.jpg");
+        assert!(json["error"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_failing_and_slow_hooks() {
+        let run = run_hook("echo oops >&2; exit 3", Duration::from_secs(10)).await;
+        assert_eq!(run.status, HookStatus::Failed("exit code 3".to_string()));
+        assert_eq!(run.output, "oops");
+
+        let run = run_hook("sleep 5", Duration::from_secs(1)).await;
+        assert_eq!(
+            run.status,
+            HookStatus::Failed("timed out after 1s".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tracker_collects_details() {
+        let mut tracker = HookTracker::new(Hooks {
+            on_receive_complete: Some("true".to_string()),
+            ..Default::default()
+        });
+        tracker.watch("t1", false, Vec::new());
+        let started = TransferProgress::Started {
+            id: "t1".into(),
+            name: "photos".to_string(),
+            total_bytes: 10,
+        };
+        assert!(tracker.observe(&started).is_none());
+        let saved = TransferProgress::Saved {
+            id: "t1".into(),
+            paths: vec![PathBuf::from("/tmp/a.jpg")],
+            entries: vec![("a.jpg".into(), PathBuf::from("/tmp/a.jpg"))],
+        };
+        assert!(tracker.observe(&saved).is_none());
+
+        let completed = TransferProgress::Completed {
+            id: "t1".into(),
+            total_bytes: 42,
+            duration_secs: 1.0,
+        };
+        let hook = tracker.observe(&completed).unwrap();
+        assert_eq!(hook.run.event, HookEvent::ReceiveComplete);
+        assert_eq!(hook.run.transfer, "photos");
+        assert_eq!(hook.details.bytes, 42);
+        assert_eq!(hook.details.paths, vec![PathBuf::from("/tmp/a.jpg")]);
+        // A transfer triggers its hook once
+        assert!(tracker.observe(&completed).is_none());
+    }
+
+    #[test]
+    fn test_blank_commands_are_ignored() {
+        let hooks = Hooks {
+            on_failure: Some("  ".to_string()),
+            ..Default::default()
+        };
+        assert!(hooks.start(HookEvent::Failure, &details()).is_none());
+        assert!(hooks.start(HookEvent::SendComplete, &details()).is_none());
+        assert!(hooks.is_empty());
+    }
+}
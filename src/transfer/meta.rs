@@ -0,0 +1,509 @@
+//! Tuit metadata sidecar carried inside a collection
+//!
+//! Stored as an extra JSON entry so sendme and other peers still see a valid
+//! collection; they simply receive one more (hidden) file.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use iroh_blobs::format::collection::Collection;
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+
+/// Collection entry name of the sidecar blob
+pub const META_NAME: &str = ".tuit-meta.json";
+
+/// Entry name of a shared text snippet
+pub const TEXT_NAME: &str = "snippet.txt";
+
+/// Longest share title kept, in characters
+pub const MAX_TITLE_LEN: usize = 80;
+
+/// Longest share note kept, in characters
+pub const MAX_NOTE_LEN: usize = 500;
+
+/// Per-share metadata, keyed by collection entry name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShareMeta {
+    pub files: BTreeMap<String, FileMeta>,
+    /// Every directory in the share, so empty ones survive the transfer
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub dirs: BTreeSet<String>,
+    /// Symlink entry name to its target, exactly as read from the sender's disk
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub symlinks: BTreeMap<String, String>,
+    /// Entries that are tuit tar+zstd archives, eligible for auto-extract
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub archives: BTreeSet<String>,
+    /// Name prefixes whose last component is an escaped non-UTF-8 name
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub escaped: BTreeSet<String>,
+    /// Symlinks whose non-UTF-8 targets are sent escaped as a whole
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub escaped_targets: BTreeSet<String>,
+    /// The only entry is a text snippet to display rather than save
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub text: bool,
+    /// Name the sender gave the share, shown instead of a generated one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Short message from the sender
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Permission bits carried across a transfer. Setuid, setgid and sticky
+/// bits are never sent or applied.
+const MODE_BITS: u32 = 0o777;
+
+/// File attributes preserved across a transfer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileMeta {
+    /// Unix permission bits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Modification time as seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtime_nsec: Option<u32>,
+}
+
+impl FileMeta {
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode() & MODE_BITS)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        let since_epoch = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok());
+
+        Self {
+            mode,
+            mtime: since_epoch.map(|d| d.as_secs() as i64),
+            mtime_nsec: since_epoch.map(|d| d.subsec_nanos()),
+        }
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        let secs = u64::try_from(self.mtime?).ok()?;
+        let nanos = self.mtime_nsec.unwrap_or(0);
+        SystemTime::UNIX_EPOCH.checked_add(Duration::new(secs, nanos))
+    }
+
+    pub fn apply_mtime(&self, path: &Path) -> Result<()> {
+        if let Some(modified) = self.modified() {
+            let file = std::fs::File::options().write(true).open(path)?;
+            file.set_modified(modified)?;
+        }
+        Ok(())
+    }
+
+    /// Applied last, once the file is in place and verified, since the mode
+    /// may make it read-only or unreadable
+    pub fn apply_mode(&self, path: &Path) -> Result<()> {
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & MODE_BITS))?;
+        }
+        #[cfg(not(unix))]
+        let _ = path;
+        Ok(())
+    }
+}
+
+impl ShareMeta {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+            && self.dirs.is_empty()
+            && self.symlinks.is_empty()
+            && self.archives.is_empty()
+            && self.escaped.is_empty()
+            && self.escaped_targets.is_empty()
+            && !self.text
+            && self.title.is_none()
+            && self.note.is_none()
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    /// Unknown fields are ignored so newer senders stay readable.
+    /// Title and note end up on screen, so they are cleaned on the way in.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut meta: Self = serde_json::from_slice(bytes)?;
+        meta.title = meta.title.and_then(|t| clean_label(&t, MAX_TITLE_LEN));
+        meta.note = meta.note.and_then(|n| clean_label(&n, MAX_NOTE_LEN));
+        Ok(meta)
+    }
+
+    pub fn file(&self, name: &str) -> Option<&FileMeta> {
+        self.files.get(name)
+    }
+
+    /// Drops the metadata of entries taken out of a share
+    pub fn remove_entries(&mut self, removed: &[String]) {
+        self.files.retain(|name, _| !is_removed(name, removed));
+        self.dirs.retain(|name| !is_removed(name, removed));
+        self.symlinks.retain(|name, _| !is_removed(name, removed));
+        self.archives.retain(|name| !is_removed(name, removed));
+        self.escaped.retain(|name| !is_removed(name, removed));
+        self.escaped_targets
+            .retain(|name| !is_removed(name, removed));
+    }
+
+    /// Re-keys the metadata after entries were saved under other names
+    pub fn rename_entries(&mut self, rename: impl Fn(&str) -> String) {
+        self.files = std::mem::take(&mut self.files)
+            .into_iter()
+            .map(|(name, meta)| (rename(&name), meta))
+            .collect();
+        self.dirs = self.dirs.iter().map(|name| rename(name)).collect();
+        self.symlinks = std::mem::take(&mut self.symlinks)
+            .into_iter()
+            .map(|(name, target)| (rename(&name), target))
+            .collect();
+        self.archives = self.archives.iter().map(|name| rename(name)).collect();
+        self.escaped = self.escaped.iter().map(|name| rename(name)).collect();
+        self.escaped_targets = self
+            .escaped_targets
+            .iter()
+            .map(|name| rename(name))
+            .collect();
+    }
+
+    /// Takes in the metadata of entries added to a share; `other` wins on clashes
+    pub fn merge(&mut self, other: ShareMeta) {
+        self.files.extend(other.files);
+        self.dirs.extend(other.dirs);
+        self.symlinks.extend(other.symlinks);
+        self.archives.extend(other.archives);
+        self.escaped.extend(other.escaped);
+        self.escaped_targets.extend(other.escaped_targets);
+    }
+}
+
+/// Single-line, length-capped text without control characters, which
+/// could otherwise drive the terminal; None when nothing is left
+pub fn clean_label(text: &str, max_chars: usize) -> Option<String> {
+    let cleaned: String = text
+        .split(char::is_control)
+        .filter(|part| !part.trim().is_empty())
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(max_chars)
+        .collect();
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
+/// Whether `name` is one of `removed` or lies beneath one of them
+pub fn is_removed(name: &str, removed: &[String]) -> bool {
+    removed.iter().any(|r| {
+        name == r
+            || name
+                .strip_prefix(r.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+/// Security: a link target must be relative and resolve inside the received
+/// tree. Resolution is lexical, so neither the link nor its target may pass
+/// through another link of the share (`is_link`); chained links could
+/// otherwise climb out one hop at a time.
+pub fn check_symlink_target(
+    name: &str,
+    target: &str,
+    is_link: impl Fn(&str) -> bool,
+) -> Result<()> {
+    anyhow::ensure!(!target.is_empty(), "empty symlink target for {}", name);
+    anyhow::ensure!(
+        !target.starts_with('/')
+            && !target.contains('\\')
+            && !target.split('/').next().is_some_and(|p| p.ends_with(':')),
+        "absolute symlink target: {}",
+        target
+    );
+
+    // Resolve lexically from the link's own directory
+    let mut resolved: Vec<&str> = name.split('/').collect();
+    resolved.pop();
+    for depth in 1..=resolved.len() {
+        anyhow::ensure!(
+            !is_link(&resolved[..depth].join("/")),
+            "symlink {} is inside another symlink",
+            name
+        );
+    }
+    let parts: Vec<&str> = target
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect();
+    for (i, part) in parts.iter().enumerate() {
+        if *part == ".." {
+            anyhow::ensure!(
+                resolved.pop().is_some(),
+                "symlink target escapes received tree: {}",
+                target
+            );
+            continue;
+        }
+        resolved.push(part);
+        // Only the last part may be a link; stepping through one is refused
+        anyhow::ensure!(
+            i + 1 == parts.len() || !is_link(&resolved.join("/")),
+            "symlink target passes through another symlink: {}",
+            target
+        );
+    }
+    anyhow::ensure!(
+        !resolved.is_empty(),
+        "symlink points at the output directory: {}",
+        target
+    );
+    Ok(())
+}
+
+/// Removes the sidecar entry, returning the remaining collection, the sizes
+/// of the remaining entries, and the sidecar's hash if present
+pub fn split_collection(
+    collection: Collection,
+    sizes: &[u64],
+) -> (Collection, Vec<u64>, Option<Hash>) {
+    let mut meta_hash = None;
+    let mut entry_sizes = Vec::with_capacity(sizes.len());
+    let mut entries = Vec::with_capacity(collection.len());
+
+    for (i, (name, hash)) in collection.into_iter().enumerate() {
+        if name == META_NAME {
+            meta_hash = Some(hash);
+            continue;
+        }
+        entry_sizes.push(sizes.get(i).copied().unwrap_or(0));
+        entries.push((name, hash));
+    }
+
+    (entries.into_iter().collect(), entry_sizes, meta_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_and_merge_entries() {
+        let mut meta = ShareMeta::default();
+        meta.files.insert("docs/a.md".into(), FileMeta::default());
+        meta.files
+            .insert("docs-old/b.md".into(), FileMeta::default());
+        meta.dirs.insert("docs".into());
+        meta.dirs.insert("docs/empty".into());
+        meta.symlinks.insert("docs/latest".into(), "a.md".into());
+        meta.archives.insert("site.tar.zst".into());
+
+        meta.remove_entries(&["docs".to_string(), "site.tar.zst".to_string()]);
+        assert_eq!(meta.files.keys().collect::<Vec<_>>(), vec!["docs-old/b.md"]);
+        assert!(meta.dirs.is_empty());
+        assert!(meta.symlinks.is_empty());
+        assert!(meta.archives.is_empty());
+
+        let mut added = ShareMeta::default();
+        added.files.insert("notes.txt".into(), FileMeta::default());
+        added.dirs.insert("docs".into());
+        added.archives.insert("photos.tar.zst".into());
+        meta.merge(added);
+        assert!(meta.file("notes.txt").is_some());
+        assert!(meta.dirs.contains("docs"));
+        assert!(meta.archives.contains("photos.tar.zst"));
+    }
+
+    #[test]
+    fn test_rename_entries() {
+        let mut meta = ShareMeta::default();
+        meta.files.insert("a:b/CON".into(), FileMeta::default());
+        meta.dirs.insert("a:b".into());
+        meta.archives.insert("a:b/CON".into());
+
+        meta.rename_entries(|name| name.replace(':', "_").replace("CON", "CON_"));
+        assert!(meta.file("a_b/CON_").is_some());
+        assert!(meta.dirs.contains("a_b"));
+        assert!(meta.archives.contains("a_b/CON_"));
+    }
+
+    #[test]
+    fn test_share_meta_roundtrip() {
+        let mut meta = ShareMeta::default();
+        meta.files.insert(
+            "dir/run.sh".into(),
+            FileMeta {
+                mode: Some(0o755),
+                mtime: Some(1_700_000_000),
+                mtime_nsec: Some(42),
+            },
+        );
+        meta.dirs.insert("dir".into());
+        meta.dirs.insert("dir/empty".into());
+        meta.symlinks.insert("dir/latest".into(), "run.sh".into());
+        meta.archives.insert("dir.tar.zst".into());
+        meta.text = true;
+        meta.title = Some("Q3 logs".into());
+        meta.note = Some("ignore the tmp folder".into());
+
+        let parsed = ShareMeta::from_bytes(&meta.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed, meta);
+    }
+
+    #[test]
+    fn test_share_meta_cleans_labels() {
+        let sent = ShareMeta {
+            title: Some("\u{1b}]0;pwned\u{7}Logs".into()),
+            note: Some("line one\nline two  ".into()),
+            ..Default::default()
+        };
+        let meta = ShareMeta::from_bytes(&sent.to_bytes().unwrap()).unwrap();
+        assert_eq!(meta.title.as_deref(), Some("]0;pwned Logs"));
+        assert_eq!(meta.note.as_deref(), Some("line one line two"));
+
+        assert_eq!(clean_label(" \t\n ", 10), None);
+        assert_eq!(clean_label("abcdef", 3).as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_share_meta_dirs_only_is_not_empty() {
+        let mut meta = ShareMeta::default();
+        assert!(meta.is_empty());
+        meta.dirs.insert("skeleton/uploads".into());
+        assert!(!meta.is_empty());
+    }
+
+    #[test]
+    fn test_share_meta_ignores_unknown_fields() {
+        let json = br#"{"files":{"a":{"mode":420,"future":true}},"later":1}"#;
+        let meta = ShareMeta::from_bytes(json).unwrap();
+        assert_eq!(meta.file("a").unwrap().mode, Some(0o644));
+        assert_eq!(meta.file("a").unwrap().mtime, None);
+    }
+
+    #[test]
+    fn test_file_meta_modified() {
+        let meta = FileMeta {
+            mode: None,
+            mtime: Some(10),
+            mtime_nsec: Some(5),
+        };
+        assert_eq!(
+            meta.modified(),
+            Some(SystemTime::UNIX_EPOCH + Duration::new(10, 5))
+        );
+        assert_eq!(FileMeta::default().modified(), None);
+    }
+
+    #[test]
+    fn test_file_meta_apply_restores_mtime_and_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.sh");
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+
+        let meta = FileMeta {
+            mode: Some(0o755),
+            mtime: Some(1_600_000_000),
+            mtime_nsec: Some(0),
+        };
+        meta.apply_mtime(&path).unwrap();
+        meta.apply_mode(&path).unwrap();
+
+        let restored = FileMeta::from_metadata(&std::fs::metadata(&path).unwrap());
+        assert_eq!(restored.mtime, Some(1_600_000_000));
+        #[cfg(unix)]
+        assert_eq!(restored.mode, Some(0o755));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_meta_drops_special_mode_bits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tool");
+        std::fs::write(&path, "x").unwrap();
+
+        let meta = FileMeta {
+            mode: Some(0o6755),
+            ..Default::default()
+        };
+        meta.apply_mode(&path).unwrap();
+        let restored = FileMeta::from_metadata(&std::fs::metadata(&path).unwrap());
+        assert_eq!(restored.mode, Some(0o755));
+    }
+
+    #[test]
+    fn test_check_symlink_target_inside_tree() {
+        let check = |name, target| check_symlink_target(name, target, |_| false);
+        assert!(check("repo/link", "file.txt").is_ok());
+        assert!(check("repo/a/link", "../b/./file").is_ok());
+        assert!(check("repo/link", "../repo/file").is_ok());
+    }
+
+    #[test]
+    fn test_check_symlink_target_rejects_escapes() {
+        let check = |name, target| check_symlink_target(name, target, |_| false);
+        assert!(check("repo/link", "/etc/passwd").is_err());
+        assert!(check("repo/link", "../../secret").is_err());
+        assert!(check("link", "..").is_err());
+        assert!(check("repo/link", "..").is_err());
+        assert!(check("repo/link", "C:\\Windows").is_err());
+        assert!(check("repo/link", "C:/Windows").is_err());
+        assert!(check("repo/link", "").is_err());
+    }
+
+    #[test]
+    fn test_check_symlink_target_rejects_chained_links() {
+        // a/b/up -> .. is fine alone, but a/esc -> b/up/../.. climbs out through it
+        let links = ["a/b/up", "a/esc", "a/last"];
+        let check = |name, target| check_symlink_target(name, target, |n| links.contains(&n));
+        assert!(check("a/b/up", "..").is_ok());
+        assert!(check("a/esc", "b/up/../..").is_err());
+        assert!(check("a/esc/inner", "file").is_err());
+        // Pointing at a link is fine, as that link is checked on its own
+        assert!(check("a/last", "b/up").is_ok());
+    }
+
+    #[test]
+    fn test_split_collection_removes_sidecar() {
+        let collection: Collection = [
+            (META_NAME.to_string(), Hash::new(b"meta")),
+            ("a.txt".to_string(), Hash::new(b"a")),
+            ("b.txt".to_string(), Hash::new(b"b")),
+        ]
+        .into_iter()
+        .collect();
+
+        let (rest, sizes, meta_hash) = split_collection(collection, &[3, 10, 20]);
+        assert_eq!(meta_hash, Some(Hash::new(b"meta")));
+        assert_eq!(rest.len(), 2);
+        assert_eq!(sizes, vec![10, 20]);
+        assert!(rest.iter().all(|(name, _)| name != META_NAME));
+    }
+
+    #[test]
+    fn test_split_collection_without_sidecar() {
+        let collection: Collection = [("a.txt".to_string(), Hash::new(b"a"))]
+            .into_iter()
+            .collect();
+        let (rest, sizes, meta_hash) = split_collection(collection, &[1]);
+        assert_eq!(meta_hash, None);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(sizes, vec![1]);
+    }
+}
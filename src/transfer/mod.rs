@@ -1,24 +1,48 @@
 //! Transfer management via iroh-blobs
 
+mod archive;
+mod filter;
+mod hooks;
+mod meta;
+mod naming;
+mod policy;
+mod rawname;
 mod receiver;
+mod route;
+mod sanitize;
 mod sender;
-
-pub use receiver::{ConflictResolver, ReceiveTask};
-pub use sender::{SendOptions, SendTask};
-
-use std::collections::VecDeque;
+mod staging;
+mod verify;
+
+pub use filter::{
+    summarize as summarize_selection, ExcludeOptions, SelectionSummary, SkippedEntry,
+};
+pub use hooks::{HookRun, HookStatus, Hooks};
+pub use naming::{list_selected_files, EntryNamer, EntryNaming, SelectedFile};
+pub use policy::{parse_size, ReceivePolicy};
+pub use receiver::{ConflictResolver, ReceiveOptions, ReceiveSubfolder, ReceiveTask};
+pub use route::{RouteRule, Router};
+pub use sanitize::{EntryRename, NameStrictness};
+pub use sender::{SendOptions, SendSource, SendTask, ShareUpdate, ShareUpdater, SymlinkMode};
+pub use staging::remove_stale as remove_stale_staging;
+pub use verify::{ManifestMode, VerifyReport};
+
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 pub type TransferId = Arc<str>;
 
 use anyhow::Result;
 use iroh_blobs::store::fs::FsStore;
 use iroh_blobs::ticket::BlobTicket;
+use iroh_blobs::Hash;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
+use hooks::HookTracker;
+
 /// Rolling window speed tracker
 #[derive(Debug)]
 pub struct SpeedTracker {
@@ -86,6 +110,65 @@ impl Default for TransferLimits {
 pub enum TransferProgress {
     /// Transfer is preparing (importing files, creating endpoint)
     Preparing { id: TransferId, status: String },
+    /// Files are being imported and hashed before the ticket exists (sender only)
+    ImportProgress {
+        id: TransferId,
+        hashed_bytes: u64,
+        total_bytes: u64,
+        files_done: usize,
+        files_total: usize,
+        /// Entry name of the most recently started file
+        current: String,
+    },
+    /// Entries left out of a send, with the reason for each (sender only)
+    Skipped {
+        id: TransferId,
+        entries: Vec<SkippedEntry>,
+    },
+    /// Collection hash and the BLAKE3 hash of each entry, sent once the
+    /// collection is known and again whenever a share changes
+    Hashes {
+        id: TransferId,
+        root: Hash,
+        entries: Vec<(String, Hash)>,
+    },
+    /// Entry names a share currently serves (sender only)
+    ShareContents {
+        id: TransferId,
+        entries: Vec<String>,
+    },
+    /// A change to a running share was rejected; the share keeps going (sender only)
+    ShareUpdateFailed { id: TransferId, error: String },
+    /// Entries sent with escaped names, or saved under cleaned-up ones
+    Renamed {
+        id: TransferId,
+        renames: Vec<EntryRename>,
+    },
+    /// Title and note the sender attached to a share (receiver only)
+    ShareInfo {
+        id: TransferId,
+        title: Option<String>,
+        note: Option<String>,
+    },
+    /// Directory a receive saves into, subfolder included (receiver only)
+    Destination { id: TransferId, dir: PathBuf },
+    /// Saved files hashed again and compared with what was sent (receiver only)
+    Verified {
+        id: TransferId,
+        report: VerifyReport,
+    },
+    /// Every file a receive moved into place, sent just before `Completed`,
+    /// and the final path of each entry saved as a file (receiver only)
+    Saved {
+        id: TransferId,
+        paths: Vec<PathBuf>,
+        entries: Vec<(String, PathBuf)>,
+    },
+    /// Folders routing rules sent files to, with a file count each (receiver only)
+    Routed {
+        id: TransferId,
+        dirs: Vec<(PathBuf, usize)>,
+    },
     /// Transfer is waiting for peer connection
     Connecting { id: TransferId },
     /// Transfer has started with known size
@@ -117,29 +200,48 @@ pub enum TransferProgress {
     /// File conflicts detected (receiver only)
     FileConflicts {
         id: TransferId,
-        conflicts: Vec<(String, PathBuf)>,
+        conflicts: Vec<FileConflict>,
+        total_bytes: u64,
+    },
+    /// Share is over the confirmation size and waits for a yes or no (receiver only)
+    ApprovalNeeded {
+        id: TransferId,
+        files: usize,
         total_bytes: u64,
+        /// Biggest entries first (name, size), a handful at most
+        largest: Vec<(String, u64)>,
     },
     /// Transfer is queued waiting for slot
     Queued { id: TransferId, position: usize },
-    /// File list for received transfers (name, size)
+    /// File list for received transfers (full entry name, size)
     FileList {
         id: TransferId,
         files: Vec<(String, u64)>,
     },
+    /// A received text snippet, shown instead of being saved
+    TextReceived { id: TransferId, text: String },
+    /// A hook started or finished; a started one is reported before the
+    /// `Completed` or `Failed` that triggered it
+    Hook { id: TransferId, run: HookRun },
+    /// Answer to `HashFile`, not tied to any transfer
+    FileHashed {
+        path: PathBuf,
+        hash: Result<Hash, String>,
+    },
 }
 
 #[derive(Debug)]
 pub enum TransferCommand {
     Send {
         id: String,
-        paths: Vec<PathBuf>,
-        follow_symlinks: bool,
+        source: SendSource,
+        options: SendOptions,
     },
     Receive {
         id: String,
         ticket: BlobTicket,
         output_dir: PathBuf,
+        options: ReceiveOptions,
     },
     Cancel {
         id: String,
@@ -147,28 +249,74 @@ pub enum TransferCommand {
     Shutdown,
     ResolveConflict {
         id: String,
-        resolution: ConflictResolution,
+        resolutions: ConflictResolutions,
+    },
+    /// Answer to `ApprovalNeeded`; declining cancels the receive
+    ApproveReceive {
+        id: String,
+        approved: bool,
+    },
+    /// Add entries to or take entries out of a running send
+    UpdateShare {
+        id: String,
+        update: ShareUpdate,
+    },
+    /// BLAKE3 of a local file, answered with `FileHashed`
+    HashFile {
+        path: PathBuf,
     },
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum ConflictResolution {
     Rename, // Add (1), (2), etc.
     Overwrite,
     Skip,
     Cancel,
+    SkipIdentical, // Existing file already has the same content (set automatically)
+    KeepNewer,     // Overwrite only if the incoming file is newer
 }
 
+/// An incoming entry whose target path already exists (receiver only)
+#[derive(Debug, Clone)]
+pub struct FileConflict {
+    pub name: String,
+    pub path: PathBuf,
+    pub existing_size: u64,
+    pub existing_modified: Option<SystemTime>,
+    pub incoming_size: u64,
+    /// Known only when the sender shared file metadata
+    pub incoming_modified: Option<SystemTime>,
+    /// Existing file hashes to the same BLAKE3 as the incoming entry
+    pub identical: bool,
+}
+
+impl FileConflict {
+    /// None when either modification time is unknown
+    pub fn incoming_is_newer(&self) -> Option<bool> {
+        Some(self.incoming_modified? > self.existing_modified?)
+    }
+}
+
+/// Per-file conflict choices keyed by collection entry name.
+/// Entries missing from the map fall back to `Rename`; any `Cancel` aborts the transfer.
+pub type ConflictResolutions = HashMap<String, ConflictResolution>;
+
 pub struct TransferManager {
     cmd_tx: mpsc::Sender<TransferCommand>,
     progress_rx: mpsc::Receiver<TransferProgress>,
 }
 
 impl TransferManager {
-    pub async fn with_limits(data_dir: PathBuf, limits: TransferLimits) -> Result<Self> {
+    /// Hooks run as transfers complete or fail
+    pub async fn with_limits(
+        data_dir: PathBuf,
+        limits: TransferLimits,
+        hooks: Hooks,
+    ) -> Result<Self> {
         let (cmd_tx, cmd_rx) = mpsc::channel(32);
         let (progress_tx, progress_rx) = mpsc::channel(256);
-        tokio::spawn(run_manager(data_dir, cmd_rx, progress_tx, limits));
+        tokio::spawn(run_manager(data_dir, cmd_rx, progress_tx, limits, hooks));
 
         Ok(Self {
             cmd_tx,
@@ -184,17 +332,37 @@ impl TransferManager {
     pub fn try_recv_progress(&mut self) -> Option<TransferProgress> {
         self.progress_rx.try_recv().ok()
     }
+
+    /// Waits for the next update; for headless use outside the TUI loop
+    pub async fn recv_progress(&mut self) -> Option<TransferProgress> {
+        self.progress_rx.recv().await
+    }
 }
 
 struct QueuedSend {
     id: String,
-    paths: Vec<PathBuf>,
-    follow_symlinks: bool,
+    source: SendSource,
+    options: SendOptions,
 }
 struct QueuedReceive {
     id: String,
     ticket: BlobTicket,
     output_dir: PathBuf,
+    options: ReceiveOptions,
+}
+
+/// Surfaces task errors that were returned rather than reported
+async fn report_failure(
+    progress_tx: &mpsc::Sender<TransferProgress>,
+    id: String,
+    error: &anyhow::Error,
+) {
+    let _ = progress_tx
+        .send(TransferProgress::Failed {
+            id: id.into(),
+            error: format!("{:#}", error),
+        })
+        .await;
 }
 
 /// Background task managing all transfers
@@ -203,6 +371,7 @@ async fn run_manager(
     mut cmd_rx: mpsc::Receiver<TransferCommand>,
     progress_tx: mpsc::Sender<TransferProgress>,
     limits: TransferLimits,
+    hooks: Hooks,
 ) {
     if let Err(e) = tokio::fs::create_dir_all(&data_dir).await {
         tracing::error!("Failed to create data directory: {}", e);
@@ -233,35 +402,41 @@ async fn run_manager(
     > = std::collections::HashMap::new();
     let mut conflict_resolvers: std::collections::HashMap<String, ConflictResolver> =
         std::collections::HashMap::new();
+    let mut share_updaters: std::collections::HashMap<String, ShareUpdater> =
+        std::collections::HashMap::new();
     let mut send_queue: Vec<QueuedSend> = Vec::new();
     let mut receive_queue: Vec<QueuedReceive> = Vec::new();
+    // Tasks report here so finished transfers can trigger hooks on the way through
+    let (task_tx, mut task_rx) = mpsc::channel::<TransferProgress>(256);
+    let mut hook_tracker = HookTracker::new(hooks);
     let start_send = |id: String,
-                      paths: Vec<PathBuf>,
-                      follow_symlinks: bool,
+                      source: SendSource,
+                      options: SendOptions,
                       store: Arc<FsStore>,
                       progress_tx: mpsc::Sender<TransferProgress>|
-     -> (tokio::task::JoinHandle<()>, CancellationToken) {
+     -> (tokio::task::JoinHandle<()>, CancellationToken, ShareUpdater) {
         let cancel_token = CancellationToken::new();
-        let options = SendOptions { follow_symlinks };
-        let task = SendTask::new(
+        let (task, updater) = SendTask::new(
             id.clone(),
-            paths,
+            source,
             store,
-            progress_tx,
+            progress_tx.clone(),
             options,
             cancel_token.clone(),
         );
         let handle = tokio::spawn(async move {
             if let Err(e) = task.run().await {
                 tracing::error!("Send task failed: {}", e);
+                report_failure(&progress_tx, id, &e).await;
             }
         });
-        (handle, cancel_token)
+        (handle, cancel_token, updater)
     };
 
     let start_receive = |id: String,
                          ticket: BlobTicket,
                          output_dir: PathBuf,
+                         options: ReceiveOptions,
                          store: Arc<FsStore>,
                          progress_tx: mpsc::Sender<TransferProgress>|
      -> (
@@ -275,13 +450,16 @@ async fn run_manager(
             ticket,
             output_dir,
             store,
-            progress_tx,
+            data_dir.clone(),
+            progress_tx.clone(),
+            options,
             cancel_token.clone(),
         );
         let handle = tokio::spawn(async move {
             tracing::info!("Receive task spawned, running...");
             if let Err(e) = task.run().await {
                 tracing::error!("Receive task failed: {}", e);
+                report_failure(&progress_tx, id, &e).await;
             }
         });
         (handle, cancel_token, resolver)
@@ -289,7 +467,14 @@ async fn run_manager(
 
     loop {
         // Clean up finished tasks
-        active_sends.retain(|_, (handle, _)| !handle.is_finished());
+        active_sends.retain(|id, (handle, _)| {
+            if handle.is_finished() {
+                share_updaters.remove(id);
+                false
+            } else {
+                true
+            }
+        });
         active_receives.retain(|id, (handle, _)| {
             if handle.is_finished() {
                 conflict_resolvers.remove(id);
@@ -303,13 +488,14 @@ async fn run_manager(
         while active_sends.len() < limits.max_concurrent_sends && !send_queue.is_empty() {
             let queued = send_queue.remove(0);
             tracing::info!("Starting queued send: {}", queued.id);
-            let (handle, cancel_token) = start_send(
+            let (handle, cancel_token, updater) = start_send(
                 queued.id.clone(),
-                queued.paths,
-                queued.follow_symlinks,
+                queued.source,
+                queued.options,
                 store.clone(),
-                progress_tx.clone(),
+                task_tx.clone(),
             );
+            share_updaters.insert(queued.id.clone(), updater);
             active_sends.insert(queued.id, (handle, cancel_token));
 
             for (pos, q) in send_queue.iter().enumerate() {
@@ -329,8 +515,9 @@ async fn run_manager(
                 queued.id.clone(),
                 queued.ticket,
                 queued.output_dir,
+                queued.options,
                 store.clone(),
-                progress_tx.clone(),
+                task_tx.clone(),
             );
             conflict_resolvers.insert(queued.id.clone(), resolver);
             active_receives.insert(queued.id, (handle, cancel_token));
@@ -348,50 +535,101 @@ async fn run_manager(
         tokio::select! {
             Some(cmd) = cmd_rx.recv() => {
                 match cmd {
-                    TransferCommand::Send { id, paths, follow_symlinks } => {
+                    TransferCommand::Send { id, source, options } => {
+                        let paths = match &source {
+                            SendSource::Paths(paths) => paths.clone(),
+                            _ => Vec::new(),
+                        };
+                        hook_tracker.watch(&id, true, paths);
                         if active_sends.len() < limits.max_concurrent_sends {
-                            let (handle, cancel_token) = start_send(id.clone(), paths, follow_symlinks, store.clone(), progress_tx.clone());
+                            let (handle, cancel_token, updater) = start_send(id.clone(), source, options, store.clone(), task_tx.clone());
+                            share_updaters.insert(id.clone(), updater);
                             active_sends.insert(id, (handle, cancel_token));
                         } else {
                             let position = send_queue.len() + 1;
                             tracing::info!("Send {} queued at position {}", id, position);
-                            send_queue.push(QueuedSend { id: id.clone(), paths, follow_symlinks });
+                            send_queue.push(QueuedSend { id: id.clone(), source, options });
                             let _ = progress_tx
                                 .send(TransferProgress::Queued { id: id.clone().into(), position })
                                 .await;
                         }
                     }
-                    TransferCommand::Receive { id, ticket, output_dir } => {
+                    TransferCommand::Receive { id, ticket, output_dir, options } => {
                         tracing::info!("Receive request for id: {}", id);
+                        hook_tracker.watch(&id, false, Vec::new());
                         if active_receives.len() < limits.max_concurrent_receives {
                             let (handle, cancel_token, resolver) = start_receive(
                                 id.clone(),
                                 ticket,
                                 output_dir,
+                                options,
                                 store.clone(),
-                                progress_tx.clone(),
+                                task_tx.clone(),
                             );
                             conflict_resolvers.insert(id.clone(), resolver);
                             active_receives.insert(id, (handle, cancel_token));
                         } else {
                             let position = receive_queue.len() + 1;
                             tracing::info!("Receive {} queued at position {}", id, position);
-                            receive_queue.push(QueuedReceive { id: id.clone(), ticket, output_dir });
+                            receive_queue.push(QueuedReceive { id: id.clone(), ticket, output_dir, options });
                             let _ = progress_tx
                                 .send(TransferProgress::Queued { id: id.clone().into(), position })
                                 .await;
                         }
                     }
-                    TransferCommand::ResolveConflict { id, resolution } => {
-                        tracing::info!("Resolving conflict for id: {}, resolution: {:?}", id, resolution);
+                    TransferCommand::ResolveConflict { id, resolutions } => {
+                        tracing::info!("Resolving {} conflict(s) for id: {}", resolutions.len(), id);
                         if let Some(resolver) = conflict_resolvers.remove(&id) {
-                            if let Err(e) = resolver.tx.send(resolution).await {
+                            if let Err(e) = resolver.tx.send(resolutions).await {
                                 tracing::error!("Failed to send resolution: {}", e);
                             }
                         } else {
                             tracing::warn!("No conflict resolver found for id: {}", id);
                         }
                     }
+                    TransferCommand::ApproveReceive { id, approved } => {
+                        tracing::info!("Receive {} approved: {}", id, approved);
+                        // Kept, as conflicts may still need resolving afterwards
+                        if let Some(resolver) = conflict_resolvers.get(&id) {
+                            if let Err(e) = resolver.approval_tx.send(approved).await {
+                                tracing::error!("Failed to send approval: {}", e);
+                            }
+                        } else {
+                            tracing::warn!("No conflict resolver found for id: {}", id);
+                        }
+                    }
+                    TransferCommand::UpdateShare { id, update } => {
+                        tracing::info!(
+                            "Updating share {}: +{} -{}",
+                            id,
+                            update.add.len(),
+                            update.remove.len()
+                        );
+                        if let Some(updater) = share_updaters.get(&id) {
+                            if let Err(e) = updater.tx.send(update).await {
+                                tracing::error!("Failed to send share update: {}", e);
+                            }
+                        } else {
+                            tracing::warn!("No running share found for id: {}", id);
+                            let _ = progress_tx
+                                .send(TransferProgress::ShareUpdateFailed {
+                                    id: id.into(),
+                                    error: "share is not running".to_string(),
+                                })
+                                .await;
+                        }
+                    }
+                    TransferCommand::HashFile { path } => {
+                        let progress_tx = progress_tx.clone();
+                        tokio::spawn(async move {
+                            let file = path.clone();
+                            let hash = tokio::task::spawn_blocking(move || verify::blake3_file(&file))
+                                .await
+                                .map_err(|e| e.to_string())
+                                .and_then(|hash| hash.map_err(|e| e.to_string()));
+                            let _ = progress_tx.send(TransferProgress::FileHashed { path, hash }).await;
+                        });
+                    }
                     TransferCommand::Cancel { id } => {
                         let was_queued = send_queue.iter().any(|q| q.id == id)
                             || receive_queue.iter().any(|q| q.id == id);
@@ -399,11 +637,13 @@ async fn run_manager(
                         receive_queue.retain(|q| q.id != id);
 
                         if was_queued {
+                            hook_tracker.forget(&id);
                             let _ = progress_tx.send(TransferProgress::Cancelled { id: id.clone().into() }).await;
                         } else {
                             if let Some((_, cancel_token)) = active_sends.remove(&id) {
                                 tracing::info!("Cancelling send task: {}", id);
                                 cancel_token.cancel();
+                                share_updaters.remove(&id);
                             }
                             if let Some((_, cancel_token)) = active_receives.remove(&id) {
                                 tracing::info!("Cancelling receive task: {}", id);
@@ -424,6 +664,20 @@ async fn run_manager(
                     }
                 }
             }
+            Some(progress) = task_rx.recv() => {
+                if let Some(hook) = hook_tracker.observe(&progress) {
+                    let id: TransferId = hook.details.id.as_str().into();
+                    let _ = progress_tx
+                        .send(TransferProgress::Hook { id: id.clone(), run: hook.run.clone() })
+                        .await;
+                    let progress_tx = progress_tx.clone();
+                    tokio::spawn(async move {
+                        let run = hook.finish().await;
+                        let _ = progress_tx.send(TransferProgress::Hook { id, run }).await;
+                    });
+                }
+                let _ = progress_tx.send(progress).await;
+            }
             _ = tokio::time::sleep(std::time::Duration::from_millis(100)) => {}
             else => break,
         }
@@ -531,6 +785,20 @@ mod tests {
             id: id.clone(),
             files: vec![("file".into(), 100)],
         };
+        let _ = TransferProgress::Saved {
+            id: id.clone(),
+            paths: vec![PathBuf::from("file")],
+            entries: vec![("file".into(), PathBuf::from("file"))],
+        };
+        let _ = TransferProgress::Hashes {
+            id: id.clone(),
+            root: Hash::new(b"collection"),
+            entries: vec![("file".into(), Hash::new(b"file"))],
+        };
+        let _ = TransferProgress::FileHashed {
+            path: PathBuf::from("file"),
+            hash: Err("not found".into()),
+        };
     }
 
     #[test]
@@ -539,20 +807,67 @@ mod tests {
         let _ = ConflictResolution::Overwrite;
         let _ = ConflictResolution::Skip;
         let _ = ConflictResolution::Cancel;
+        let _ = ConflictResolution::SkipIdentical;
+        let _ = ConflictResolution::KeepNewer;
+    }
+
+    fn test_conflict(existing: Option<u64>, incoming: Option<u64>) -> FileConflict {
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        FileConflict {
+            name: "a.txt".into(),
+            path: PathBuf::from("/tmp/a.txt"),
+            existing_size: 1,
+            existing_modified: existing.map(at),
+            incoming_size: 1,
+            incoming_modified: incoming.map(at),
+            identical: false,
+        }
+    }
+
+    #[test]
+    fn test_file_conflict_incoming_is_newer() {
+        assert_eq!(
+            test_conflict(Some(10), Some(20)).incoming_is_newer(),
+            Some(true)
+        );
+        assert_eq!(
+            test_conflict(Some(20), Some(10)).incoming_is_newer(),
+            Some(false)
+        );
+        assert_eq!(
+            test_conflict(Some(10), Some(10)).incoming_is_newer(),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn test_file_conflict_unknown_times() {
+        assert_eq!(test_conflict(Some(10), None).incoming_is_newer(), None);
+        assert_eq!(test_conflict(None, Some(10)).incoming_is_newer(), None);
     }
 
     #[test]
     fn test_transfer_command_variants() {
         let _ = TransferCommand::Send {
             id: "test".into(),
-            paths: vec![],
-            follow_symlinks: false,
+            source: SendSource::Paths(vec![]),
+            options: SendOptions::default(),
         };
         let _ = TransferCommand::Cancel { id: "test".into() };
         let _ = TransferCommand::Shutdown;
         let _ = TransferCommand::ResolveConflict {
             id: "test".into(),
-            resolution: ConflictResolution::Skip,
+            resolutions: ConflictResolutions::from([(
+                "a.txt".to_string(),
+                ConflictResolution::Skip,
+            )]),
+        };
+        let _ = TransferCommand::UpdateShare {
+            id: "test".into(),
+            update: ShareUpdate {
+                add: vec![PathBuf::from("/tmp/notes.txt")],
+                remove: vec!["old.txt".into()],
+            },
         };
     }
 }
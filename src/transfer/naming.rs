@@ -0,0 +1,299 @@
+//! Collection entry names for a send
+//!
+//! By default each selected path becomes a top-level entry, named relative to
+//! its own parent. The review screen can instead keep the directory structure
+//! below the selection's common ancestor, flatten everything to bare file
+//! names, put it all under a prefix, or rename single files.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use super::filter::{self, ExcludeOptions, Excluded};
+use super::rawname;
+
+/// How entry names are derived from paths
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntryLayout {
+    /// Each selected path at the top level
+    #[default]
+    Selection,
+    /// Paths relative to the common ancestor of the selection
+    KeepStructure,
+    /// Bare file names, without directories
+    Flatten,
+}
+
+impl EntryLayout {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Selection => Self::KeepStructure,
+            Self::KeepStructure => Self::Flatten,
+            Self::Flatten => Self::Selection,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Selection => "selection",
+            Self::KeepStructure => "keep structure",
+            Self::Flatten => "flatten",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryNaming {
+    pub layout: EntryLayout,
+    /// Folder every entry is placed under
+    pub prefix: Option<String>,
+    /// File path to the name it gets instead, before the prefix is applied
+    pub renames: BTreeMap<PathBuf, String>,
+}
+
+/// Names entries for one send; `roots` are the canonical selected paths
+pub struct EntryNamer<'a> {
+    naming: &'a EntryNaming,
+    base: Option<PathBuf>,
+}
+
+impl<'a> EntryNamer<'a> {
+    pub fn new(naming: &'a EntryNaming, roots: &[PathBuf]) -> Self {
+        let base = match naming.layout {
+            EntryLayout::KeepStructure => common_ancestor(roots.iter().filter_map(|r| r.parent())),
+            _ => None,
+        };
+        Self { naming, base }
+    }
+
+    /// Name within the layout, before renames and the prefix. None for
+    /// directories when flattening, as they have no entry of their own.
+    fn layout_name(&self, root: &Path, path: &Path, is_dir: bool) -> Result<Option<String>> {
+        let relative = match (self.naming.layout, &self.base) {
+            (EntryLayout::Flatten, _) if is_dir => return Ok(None),
+            (EntryLayout::Flatten, _) => {
+                Path::new(path.file_name().context("path has no file name")?)
+            }
+            (EntryLayout::KeepStructure, Some(base)) => path.strip_prefix(base)?,
+            _ => path.strip_prefix(root.parent().context("cannot get parent directory")?)?,
+        };
+        relative_name(relative).map(Some)
+    }
+
+    /// Name of the entry for `path`, found while walking the selected `root`
+    pub fn name(&self, root: &Path, path: &Path, is_dir: bool) -> Result<Option<String>> {
+        let name = match self.naming.renames.get(path) {
+            Some(renamed) if !is_dir => renamed.clone(),
+            _ => match self.layout_name(root, path, is_dir)? {
+                Some(name) => name,
+                None => return Ok(None),
+            },
+        };
+        let name = match &self.naming.prefix {
+            Some(prefix) => format!("{}/{}", prefix.trim_matches('/'), name),
+            None => name,
+        };
+        check_entry_name(&name)?;
+        Ok(Some(name))
+    }
+
+    /// The name a rename starts from
+    pub fn unrenamed(&self, root: &Path, path: &Path) -> Result<String> {
+        Ok(self.layout_name(root, path, false)?.unwrap_or_default())
+    }
+}
+
+/// Rejects names two paths would share; the collection would be unusable
+#[derive(Default)]
+pub struct NameCheck {
+    seen: HashMap<String, PathBuf>,
+}
+
+impl NameCheck {
+    pub fn insert(&mut self, name: &str, path: &Path) -> Result<()> {
+        if let Some(first) = self.seen.insert(name.to_string(), path.to_path_buf()) {
+            anyhow::bail!(
+                "{} and {} would both be sent as {}; rename one or change the layout",
+                first.display(),
+                path.display(),
+                name
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A file the selection would send, with the selected path it was found under
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedFile {
+    pub root: PathBuf,
+    pub path: PathBuf,
+}
+
+/// Files a send of `paths` would include, up to `limit`, for reviewing names
+pub fn list_selected_files(
+    paths: &[PathBuf],
+    follow_links: bool,
+    options: &ExcludeOptions,
+    limit: usize,
+) -> Vec<SelectedFile> {
+    let mut files = Vec::new();
+    for path in paths {
+        let Ok(root) = path.canonicalize() else {
+            continue;
+        };
+        let mut excluded = Excluded::default();
+        for entry in filter::walk(&root, follow_links, options, &mut excluded).flatten() {
+            if files.len() >= limit {
+                return files;
+            }
+            if entry.file_type().is_file() {
+                files.push(SelectedFile {
+                    root: root.clone(),
+                    path: entry.into_path(),
+                });
+            }
+        }
+    }
+    files
+}
+
+/// Deepest directory containing all of `dirs`
+fn common_ancestor<'p>(mut dirs: impl Iterator<Item = &'p Path>) -> Option<PathBuf> {
+    let mut common = dirs.next()?.to_path_buf();
+    for dir in dirs {
+        while !dir.starts_with(&common) {
+            common = common.parent()?.to_path_buf();
+        }
+    }
+    Some(common)
+}
+
+/// Non-UTF-8 components are escaped, see [`rawname`]
+fn relative_name(path: &Path) -> Result<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => match rawname::encode(part) {
+                Some(escaped) => parts.push(escaped),
+                None => parts.push(part.to_string_lossy().into_owned()),
+            },
+            Component::CurDir => {}
+            _ => anyhow::bail!("unexpected path component in {}", path.display()),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+/// Security: names typed in review must still be plain relative paths
+pub fn check_entry_name(name: &str) -> Result<()> {
+    anyhow::ensure!(!name.is_empty(), "empty entry name");
+    for part in name.split('/') {
+        anyhow::ensure!(!part.is_empty(), "empty path component in {}", name);
+        anyhow::ensure!(
+            part != "." && part != "..",
+            "relative component in {}",
+            name
+        );
+        anyhow::ensure!(!part.contains('\\'), "backslash in {}", name);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> (Vec<PathBuf>, Vec<(PathBuf, PathBuf)>) {
+        let roots = vec![
+            PathBuf::from("/home/me/a/x/notes.txt"),
+            PathBuf::from("/home/me/b/y"),
+        ];
+        let files = vec![
+            (roots[0].clone(), roots[0].clone()),
+            (roots[1].clone(), PathBuf::from("/home/me/b/y/notes.txt")),
+            (roots[1].clone(), PathBuf::from("/home/me/b/y/sub/data.csv")),
+        ];
+        (roots, files)
+    }
+
+    fn names(naming: &EntryNaming) -> Vec<String> {
+        let (roots, files) = files();
+        let namer = EntryNamer::new(naming, &roots);
+        files
+            .iter()
+            .map(|(root, path)| namer.name(root, path, false).unwrap().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_layouts() {
+        let mut naming = EntryNaming::default();
+        assert_eq!(
+            names(&naming),
+            vec!["notes.txt", "y/notes.txt", "y/sub/data.csv"]
+        );
+
+        naming.layout = EntryLayout::KeepStructure;
+        assert_eq!(
+            names(&naming),
+            vec!["a/x/notes.txt", "b/y/notes.txt", "b/y/sub/data.csv"]
+        );
+
+        naming.layout = EntryLayout::Flatten;
+        assert_eq!(names(&naming), vec!["notes.txt", "notes.txt", "data.csv"]);
+        let (roots, _) = files();
+        let namer = EntryNamer::new(&naming, &roots);
+        let dir = namer.name(&roots[1], &roots[1], true).unwrap();
+        assert_eq!(dir, None);
+    }
+
+    #[test]
+    fn test_prefix_and_renames() {
+        let mut naming = EntryNaming {
+            layout: EntryLayout::Flatten,
+            prefix: Some("/q3/".to_string()),
+            ..Default::default()
+        };
+        naming.renames.insert(
+            PathBuf::from("/home/me/b/y/notes.txt"),
+            "notes-y.txt".to_string(),
+        );
+        assert_eq!(
+            names(&naming),
+            vec!["q3/notes.txt", "q3/notes-y.txt", "q3/data.csv"]
+        );
+
+        naming
+            .renames
+            .insert(PathBuf::from("/home/me/b/y/sub/data.csv"), "../x".into());
+        let (roots, files) = files();
+        let namer = EntryNamer::new(&naming, &roots);
+        assert!(namer.name(&files[2].0, &files[2].1, false).is_err());
+    }
+
+    #[test]
+    fn test_name_check_reports_both_paths() {
+        let mut check = NameCheck::default();
+        check
+            .insert("notes.txt", Path::new("/a/notes.txt"))
+            .unwrap();
+        check.insert("data.csv", Path::new("/a/data.csv")).unwrap();
+        let err = check
+            .insert("notes.txt", Path::new("/b/notes.txt"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("/a/notes.txt") && err.contains("/b/notes.txt"));
+    }
+
+    #[test]
+    fn test_common_ancestor() {
+        let dirs = [Path::new("/home/me/a/x"), Path::new("/home/me/b")];
+        assert_eq!(
+            common_ancestor(dirs.into_iter()),
+            Some(PathBuf::from("/home/me"))
+        );
+        assert_eq!(common_ancestor(std::iter::empty()), None);
+    }
+}
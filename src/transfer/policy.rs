@@ -0,0 +1,221 @@
+//! Limits on what a receive accepts
+//!
+//! Policies are checked against the collection's names and sizes, which the
+//! receiver has before any file data is downloaded. Archive contents are only
+//! known once unpacked, so each unpacked file is checked with `check_entry`
+//! before it is written and a refused one aborts the receive.
+
+use anyhow::Result;
+
+/// Shares a receive refuses outright, or asks about first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReceivePolicy {
+    /// Refuse shares larger than this
+    pub max_total_bytes: Option<u64>,
+    /// Refuse shares with more entries than this
+    pub max_files: Option<usize>,
+    /// Refuse shares with entries ending in these extensions (lowercase, no dot)
+    pub blocked_extensions: Vec<String>,
+    /// Ask before downloading shares larger than this
+    pub confirm_above_bytes: Option<u64>,
+}
+
+impl ReceivePolicy {
+    /// Extensions are accepted as `exe`, `.EXE` or `tar.gz`
+    pub fn with_blocked_extensions(mut self, extensions: &[String]) -> Self {
+        self.blocked_extensions = extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        self
+    }
+
+    /// Fails with the reason a share of `entries` (name, size) is refused
+    pub fn check<'a>(&self, entries: impl IntoIterator<Item = (&'a str, u64)>) -> Result<()> {
+        let mut total_bytes = 0u64;
+        let mut files = 0usize;
+        let mut blocked = Vec::new();
+        for (name, size) in entries {
+            total_bytes += size;
+            files += 1;
+            if self.is_blocked(name) {
+                blocked.push(name);
+            }
+        }
+
+        if let Some(max) = self.max_total_bytes {
+            anyhow::ensure!(
+                total_bytes <= max,
+                "share is {}, over the {} receive limit",
+                format_size(total_bytes),
+                format_size(max)
+            );
+        }
+        if let Some(max) = self.max_files {
+            anyhow::ensure!(
+                files <= max,
+                "share has {} files, over the {} file receive limit",
+                files,
+                max
+            );
+        }
+        if let Some(first) = blocked.first() {
+            anyhow::bail!(
+                "share contains blocked file types: {}{}",
+                first,
+                match blocked.len() - 1 {
+                    0 => String::new(),
+                    more => format!(" and {} more", more),
+                }
+            );
+        }
+        Ok(())
+    }
+
+    /// Fails if a file unpacked from an archive is refused. `files` counts it
+    /// and every file of the share before it.
+    pub fn check_entry(&self, name: &str, files: usize) -> Result<()> {
+        if let Some(max) = self.max_files {
+            anyhow::ensure!(
+                files <= max,
+                "share has over {} files, the receive limit",
+                max
+            );
+        }
+        anyhow::ensure!(
+            !self.is_blocked(name),
+            "share contains blocked file types: {}",
+            name
+        );
+        Ok(())
+    }
+
+    pub fn needs_confirmation(&self, total_bytes: u64) -> bool {
+        self.confirm_above_bytes
+            .is_some_and(|threshold| total_bytes > threshold)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Short description of the limits in force, for the Receive tab
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(max) = self.max_total_bytes {
+            parts.push(format!("max {}", format_size(max)));
+        }
+        if let Some(max) = self.max_files {
+            parts.push(format!("max {} files", max));
+        }
+        if let Some(threshold) = self.confirm_above_bytes {
+            parts.push(format!("ask above {}", format_size(threshold)));
+        }
+        if !self.blocked_extensions.is_empty() {
+            parts.push(format!("blocked .{}", self.blocked_extensions.join(" .")));
+        }
+        parts.join(", ")
+    }
+
+    /// Trailing dots and spaces are ignored, as Windows would drop them
+    fn is_blocked(&self, name: &str) -> bool {
+        let file = name.rsplit('/').next().unwrap_or(name);
+        let file = file.trim_end_matches(['.', ' ']).to_lowercase();
+        self.blocked_extensions.iter().any(|ext| {
+            file.strip_suffix(ext.as_str())
+                .is_some_and(|stem| stem.len() > 1 && stem.ends_with('.'))
+        })
+    }
+}
+
+/// Parses sizes like `500M`, `10 GiB` or `2GB`; bare numbers are bytes.
+/// Single letters and `iB` suffixes are binary, `B` suffixes decimal.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid size: {}", input))?;
+    let multiplier: u64 = match unit.trim().to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        _ => anyhow::bail!("unknown size unit in: {}", input),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+fn format_size(bytes: u64) -> String {
+    humansize::format_size(bytes, humansize::BINARY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10 GiB").unwrap(), 10 << 30);
+        assert_eq!(parse_size("1.5M").unwrap(), 3 << 19);
+        assert_eq!(parse_size("2GB").unwrap(), 2_000_000_000);
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("5 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_limits() {
+        let policy = ReceivePolicy {
+            max_total_bytes: Some(100),
+            max_files: Some(2),
+            ..Default::default()
+        };
+        assert!(policy.check([("a", 50), ("b", 50)]).is_ok());
+        assert!(policy.check([("a", 101)]).is_err());
+        assert!(policy.check([("a", 1), ("b", 1), ("c", 1)]).is_err());
+    }
+
+    #[test]
+    fn test_blocked_extensions() {
+        let policy = ReceivePolicy::default()
+            .with_blocked_extensions(&[".EXE".to_string(), "tar.gz".to_string()]);
+        assert!(policy.check([("dir/setup.exe", 1)]).is_err());
+        assert!(policy.check([("setup.Exe. ", 1)]).is_err());
+        assert!(policy.check([("backup.tar.gz", 1)]).is_err());
+        assert!(policy.check([("exe", 1), ("notes.exe.txt", 1)]).is_ok());
+        assert!(policy.check([("dir.exe/readme", 1)]).is_ok());
+    }
+
+    #[test]
+    fn test_archive_entries() {
+        let policy = ReceivePolicy {
+            max_files: Some(2),
+            ..Default::default()
+        }
+        .with_blocked_extensions(&["exe".to_string()]);
+        assert!(policy.check_entry("proj/readme", 2).is_ok());
+        assert!(policy.check_entry("proj/notes", 3).is_err());
+        assert!(policy.check_entry("proj/bin/setup.exe", 1).is_err());
+    }
+
+    #[test]
+    fn test_confirmation_threshold() {
+        let policy = ReceivePolicy {
+            confirm_above_bytes: Some(10),
+            ..Default::default()
+        };
+        assert!(!policy.needs_confirmation(10));
+        assert!(policy.needs_confirmation(11));
+        assert!(!ReceivePolicy::default().needs_confirmation(u64::MAX));
+    }
+}
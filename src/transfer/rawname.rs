@@ -0,0 +1,205 @@
+//! Non-UTF-8 file names carried in collection entry names
+//!
+//! Collection names are strings, so a path component that isn't valid UTF-8
+//! is sent in an escaped byte form: valid text stays as is, each invalid byte
+//! becomes `%XX` and `%` itself becomes `%25`. Peers without tuit just see
+//! that readable form. Tuit receivers find the escaped components listed in
+//! the share metadata and turn them back into the original bytes. A file
+//! sent under the sidecar's own name travels escaped the same way.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Component, Path};
+
+use anyhow::Result;
+
+use super::meta::META_NAME;
+
+/// Escaped form of `part`, or None if it is valid UTF-8 and needs none
+pub fn encode(part: &OsStr) -> Option<String> {
+    if part.to_str().is_some() {
+        return None;
+    }
+    let mut encoded = String::new();
+    for chunk in part.as_encoded_bytes().utf8_chunks() {
+        encoded.push_str(&chunk.valid().replace('%', "%25"));
+        for byte in chunk.invalid() {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    Some(encoded)
+}
+
+/// Escaped form of a top-level entry name tuit keeps for the sidecar, so a
+/// user's file of that name stays an entry of its own
+pub fn escape_reserved(name: &str) -> Option<String> {
+    let rest = name.strip_prefix('.').filter(|_| name == META_NAME)?;
+    Some(format!("%2E{}", rest))
+}
+
+/// Original bytes of an escaped component; fails on malformed escapes
+fn decode(part: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(part.len());
+    let mut rest = part.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            let Some(decoded) = hex else {
+                anyhow::bail!("malformed escape in {}", part);
+            };
+            bytes.push(decoded);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    Ok(bytes)
+}
+
+/// The file name for an escaped component: the original bytes on unix,
+/// a lossy but readable name elsewhere.
+/// Security: decoded bytes must still be a single plain component.
+pub fn decode_os(part: &str) -> Result<OsString> {
+    let bytes = decode(part)?;
+    anyhow::ensure!(
+        !bytes.is_empty()
+            && bytes != b"."
+            && bytes != b".."
+            && !bytes.iter().any(|&b| b == b'/' || b == b'\\' || b == 0),
+        "unsafe escaped component: {}",
+        part
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        Ok(OsString::from_vec(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(String::from_utf8_lossy(&bytes).into_owned().into())
+    }
+}
+
+/// A symlink target escaped as a whole by `encode`, back in its original
+/// bytes. Security: escapes may not add separators or `.`/`..` components,
+/// so the target keeps the components the symlink checks saw.
+pub fn decode_target(target: &str) -> Result<OsString> {
+    let mut bytes = Vec::with_capacity(target.len());
+    for (i, part) in target.split('/').enumerate() {
+        let decoded = decode(part)?;
+        let dots = decoded == b"." || decoded == b"..";
+        anyhow::ensure!(
+            !decoded.iter().any(|&b| b == b'/' || b == b'\\' || b == 0)
+                && (!dots || decoded == part.as_bytes()),
+            "unsafe escaped symlink target: {}",
+            target
+        );
+        if i > 0 {
+            bytes.push(b'/');
+        }
+        bytes.extend(decoded);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        Ok(OsString::from_vec(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(String::from_utf8_lossy(&bytes).into_owned().into())
+    }
+}
+
+/// Prefixes of `name` that end in a component escaped from `path`. Names
+/// map onto the tail of their path whatever the layout; renamed or prefixed
+/// components simply don't match.
+pub fn escaped_prefixes(name: &str, path: &Path) -> Vec<String> {
+    let parts: Vec<&str> = name.split('/').collect();
+    let components: Vec<&OsStr> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect();
+
+    let mut prefixes = Vec::new();
+    for (i, (part, component)) in parts.iter().rev().zip(components.iter().rev()).enumerate() {
+        if encode(component).as_deref() == Some(*part) {
+            prefixes.push(parts[..parts.len() - i].join("/"));
+        }
+    }
+    prefixes.reverse();
+    prefixes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_names_are_untouched() {
+        assert_eq!(encode(OsStr::new("100% café")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_roundtrip_latin1() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let raw = OsStr::from_bytes(b"caf\xe9 100%.txt");
+        let encoded = encode(raw).unwrap();
+        assert_eq!(encoded, "caf%E9 100%25.txt");
+        assert_eq!(decode_os(&encoded).unwrap(), raw);
+
+        let path = Path::new("/home/me")
+            .join(OsStr::from_bytes(b"\xff"))
+            .join(raw)
+            .join("menu.txt");
+        assert_eq!(
+            escaped_prefixes(&format!("%FF/{}/menu.txt", encoded), &path),
+            vec!["%FF".to_string(), format!("%FF/{}", encoded)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_target_roundtrip() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let raw = OsStr::from_bytes(b"../caf\xe9/100%");
+        let encoded = encode(raw).unwrap();
+        assert_eq!(encoded, "../caf%E9/100%25");
+        assert_eq!(decode_target(&encoded).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_reserved_name_roundtrip() {
+        let escaped = escape_reserved(META_NAME).unwrap();
+        assert_ne!(escaped, META_NAME);
+        assert_eq!(decode_os(&escaped).unwrap(), META_NAME);
+        assert_eq!(escape_reserved("docs/.tuit-meta.json"), None);
+        assert_eq!(escape_reserved("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_decode_target_keeps_components() {
+        assert!(decode_target("a/%2E%2E/b").is_err());
+        assert!(decode_target("a%2F..").is_err());
+        assert!(decode_target("a%00").is_err());
+        assert!(decode_target("../a/./b").is_ok());
+    }
+
+    #[test]
+    fn test_decode_rejects_unsafe_components() {
+        assert!(decode_os("%2E%2E").is_err());
+        assert!(decode_os("a%2Fb").is_err());
+        assert!(decode_os("bad%G1").is_err());
+        assert!(decode_os("trailing%").is_err());
+    }
+}
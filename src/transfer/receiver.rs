@@ -1,83 +1,215 @@
 //! Receive files via iroh-blobs (based on sendme)
 
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
+use bao_tree::io::BaoContentItem;
+use bytes::Bytes;
 use fs2::available_space;
 use iroh::discovery::dns::DnsDiscovery;
+use iroh::endpoint::Connection;
 use iroh::Endpoint;
 use iroh::Watcher;
 use iroh_blobs::api::blobs::{ExportMode, ExportOptions, ExportProgressItem};
 use iroh_blobs::api::remote::GetProgressItem;
 use iroh_blobs::api::Store;
 use iroh_blobs::format::collection::Collection;
+use iroh_blobs::get::fsm;
 use iroh_blobs::get::request::get_hash_seq_and_sizes;
+use iroh_blobs::hashseq::HashSeq;
+use iroh_blobs::protocol::{ChunkRanges, GetRequest};
 use iroh_blobs::store::fs::FsStore;
 use iroh_blobs::ticket::BlobTicket;
+use iroh_blobs::{Hash, HashAndFormat};
 use n0_future::StreamExt;
 use std::ops::Deref;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
 use tokio::sync::mpsc;
+use tokio_util::io::{StreamReader, SyncIoBridge};
 use tokio_util::sync::CancellationToken;
 
-use super::{ConflictResolution, SpeedTracker, TransferId, TransferProgress};
+use super::archive;
+use super::meta::{self, FileMeta, ShareMeta};
+use super::policy::ReceivePolicy;
+use super::rawname;
+use super::route::Router;
+use super::sanitize::{self, EntryRename, NameSanitizer, NameStrictness};
+use super::staging::{self, OnExisting, Placed, Staging};
+use super::verify::{self, ExportedFile, ManifestMode};
+use super::{
+    ConflictResolution, ConflictResolutions, FileConflict, SpeedTracker, TransferId,
+    TransferProgress,
+};
 
+/// Folder each receive gets of its own inside the output directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReceiveSubfolder {
+    /// Entries land directly in the output directory
+    #[default]
+    Off,
+    /// Named after the share's title, or its file when there is just one
+    Share,
+    /// Named after the sender's short endpoint id
+    Sender,
+}
+
+impl ReceiveSubfolder {
+    pub fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "share" | "collection" => Self::Share,
+            "sender" => Self::Sender,
+            _ => Self::Off,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Share => "share",
+            Self::Sender => "sender",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Share,
+            Self::Share => Self::Sender,
+            Self::Sender => Self::Off,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReceiveOptions {
+    /// Unpack tuit archives instead of saving the `.tar.zst`
+    pub auto_extract: bool,
+    /// Write a single-file share to stdout instead of the output directory
+    pub to_stdout: bool,
+    /// Stream files to the output directory instead of through the store
+    pub low_footprint: bool,
+    /// How far entry names are changed to stay portable
+    pub sanitize: NameStrictness,
+    /// Shares to refuse, or to ask about before downloading
+    pub policy: ReceivePolicy,
+    /// Give each receive a folder of its own
+    pub subfolder: ReceiveSubfolder,
+    /// Folders other than the output directory for matching files
+    pub routes: Router,
+    /// Hash saved files again once they are in place
+    pub verify: bool,
+    /// Checksum manifests written next to verified files
+    pub manifest: ManifestMode,
+}
+
+impl Default for ReceiveOptions {
+    fn default() -> Self {
+        Self {
+            auto_extract: true,
+            to_stdout: false,
+            low_footprint: false,
+            sanitize: NameStrictness::default(),
+            policy: ReceivePolicy::default(),
+            subfolder: ReceiveSubfolder::default(),
+            routes: Router::default(),
+            verify: true,
+            manifest: ManifestMode::default(),
+        }
+    }
+}
+
+const STDOUT_CHUNK: usize = 64 * 1024;
+const MAX_TEXT_SIZE: u64 = 1024 * 1024; // Larger snippets are saved as files
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
 const MIN_FREE_SPACE: u64 = 1024 * 1024 * 1024; // 1 GB buffer
+const APPROVAL_LARGEST: usize = 5; // Entries listed when asking to approve
+
+/// Where entry content is read from during export
+enum EntrySource {
+    /// Already downloaded into the blob store
+    Store,
+    /// Fetched and verified per entry, bypassing the store
+    Stream {
+        connection: Connection,
+        root: Hash,
+        hash_seq: HashSeq,
+    },
+}
 
 /// Each ReceiveTask creates an isolated Endpoint for privacy (fresh NodeID per receive)
 pub struct ReceiveTask {
     id: TransferId,
     ticket: BlobTicket,
     output_dir: PathBuf,
+    /// The output directory before any subfolder; receives stage here so the
+    /// cleanup on start finds what a crash left behind
+    base_dir: PathBuf,
     store: Arc<FsStore>,
+    store_dir: PathBuf,
     progress_tx: mpsc::Sender<TransferProgress>,
-    resolution_rx: mpsc::Receiver<ConflictResolution>,
+    resolution_rx: mpsc::Receiver<ConflictResolutions>,
+    approval_rx: mpsc::Receiver<bool>,
+    options: ReceiveOptions,
+    source: EntrySource,
+    /// Entry names (and directory prefixes) sent in escaped byte form
+    escaped: BTreeSet<String>,
+    /// Files staged so far, by the path they were staged for
+    exported: Vec<ExportedFile>,
+    /// Sent permissions, by the path the file was staged for; applied once
+    /// the files are in place and verified
+    modes: Vec<(PathBuf, FileMeta)>,
     cancel_token: CancellationToken,
-    exported_files: Vec<PathBuf>, // For cleanup on cancel
 }
 
 pub struct ConflictResolver {
-    pub tx: mpsc::Sender<ConflictResolution>,
+    pub tx: mpsc::Sender<ConflictResolutions>,
+    /// Answers a receive waiting on its confirmation size
+    pub approval_tx: mpsc::Sender<bool>,
 }
 
 impl ReceiveTask {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: impl Into<TransferId>,
         ticket: BlobTicket,
         output_dir: PathBuf,
         store: Arc<FsStore>,
+        store_dir: PathBuf,
         progress_tx: mpsc::Sender<TransferProgress>,
+        options: ReceiveOptions,
         cancel_token: CancellationToken,
     ) -> (Self, ConflictResolver) {
         let (resolution_tx, resolution_rx) = mpsc::channel(1);
+        let (approval_tx, approval_rx) = mpsc::channel(1);
         (
             Self {
                 id: id.into(),
                 ticket,
+                base_dir: output_dir.clone(),
                 output_dir,
                 store,
+                store_dir,
                 progress_tx,
                 resolution_rx,
+                approval_rx,
+                options,
+                source: EntrySource::Store,
+                escaped: BTreeSet::new(),
+                exported: Vec::new(),
+                modes: Vec::new(),
                 cancel_token,
-                exported_files: Vec::new(),
             },
-            ConflictResolver { tx: resolution_tx },
+            ConflictResolver {
+                tx: resolution_tx,
+                approval_tx,
+            },
         )
     }
 
-    async fn cleanup_exported_files(&self) {
-        for file in &self.exported_files {
-            if file.exists() {
-                tracing::info!("Cleaning up partial file: {}", file.display());
-                if let Err(e) = tokio::fs::remove_file(file).await {
-                    tracing::warn!("Failed to clean up {}: {}", file.display(), e);
-                }
-            }
-        }
-    }
-
     pub async fn run(mut self) -> Result<()> {
         let start = Instant::now();
 
@@ -145,7 +277,7 @@ impl ReceiveTask {
             "Getting hash_seq and sizes for hash: {}",
             hash_and_format.hash
         );
-        let (_hash_seq, sizes) =
+        let (hash_seq, sizes) =
             get_hash_seq_and_sizes(&connection, &hash_and_format.hash, 1024 * 1024 * 32, None)
                 .await
                 .map_err(|e| anyhow::anyhow!("failed to get sizes: {}", e))?;
@@ -161,36 +293,19 @@ impl ReceiveTask {
             total_files
         );
 
-        let free_space =
-            available_space(&self.output_dir).context("failed to check available disk space")?;
-        let required_space = payload_size + MIN_FREE_SPACE;
-        if free_space < required_space {
-            let err_msg = format!(
-                "insufficient disk space: need {} (+ {} buffer) but only {} available",
-                humansize::format_size(payload_size, humansize::BINARY),
-                humansize::format_size(MIN_FREE_SPACE, humansize::BINARY),
-                humansize::format_size(free_space, humansize::BINARY)
-            );
-            if let Err(e) = self
-                .progress_tx
-                .send(TransferProgress::Failed {
-                    id: self.id.clone(),
-                    error: err_msg.clone(),
-                })
-                .await
-            {
-                tracing::warn!("Failed to send Failed progress: {}", e);
-            }
-            anyhow::bail!(err_msg);
-        }
-        tracing::info!(
-            "Disk space OK: {} available, {} required",
-            humansize::format_size(free_space, humansize::BINARY),
-            humansize::format_size(required_space, humansize::BINARY)
-        );
-
         // Check local availability - we need the collection metadata to check conflicts
         let local = self.store.remote().local(hash_and_format).await?;
+        if local.is_complete() {
+            // Everything is already in the store, so there is nothing to stream
+            self.options.low_footprint = false;
+        }
+
+        // Low-footprint receives only keep the collection index in the store
+        let store_bytes = if self.options.low_footprint {
+            0
+        } else {
+            total_size.saturating_sub(local.local_bytes())
+        };
 
         // We need the collection to check for conflicts
         // First, ensure we have at least the metadata downloaded
@@ -199,39 +314,125 @@ impl ReceiveTask {
             let store: &Store = self.store.deref();
             Collection::load(hash_and_format.hash, store).await?
         } else {
-            // Download just enough to get the collection metadata
-            // The collection metadata is the first blob, which we got with get_hash_seq_and_sizes
-            // We need to do a partial download to get the collection
-            let _local_size = local.local_bytes();
-            let get = self
-                .store
-                .remote()
-                .execute_get(connection.clone(), local.missing());
-
-            let mut stream = get.stream();
-            while let Some(item) = stream.next().await {
-                match item {
-                    GetProgressItem::Progress(_) => {}
-                    GetProgressItem::Done(_) => break,
-                    GetProgressItem::Error(cause) => {
-                        anyhow::bail!("download error while getting metadata: {}", cause);
-                    }
-                }
-            }
+            // Only the names for now: policies are checked before any
+            // entry data is downloaded
+            let request = GetRequest::builder()
+                .root(ChunkRanges::all())
+                .next(ChunkRanges::all())
+                .build(hash_and_format.hash);
+            self.fetch(&connection, request).await?;
 
             let store: &Store = self.store.deref();
             Collection::load(hash_and_format.hash, store).await?
         };
 
+        // Strip the tuit sidecar so it is neither listed nor exported
+        let (collection, entry_sizes, meta_hash) =
+            meta::split_collection(collection, sizes.get(1..).unwrap_or_default());
+        let mut share_meta = match meta_hash {
+            Some(hash) => {
+                if !local.is_complete() {
+                    let fetched = match child_request(hash_and_format.hash, &hash_seq, hash) {
+                        Ok(request) => self.fetch(&connection, request).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = fetched {
+                        tracing::warn!("Failed to fetch share metadata: {}", e);
+                    }
+                }
+                self.load_share_meta(hash).await
+            }
+            None => ShareMeta::default(),
+        };
+        if share_meta.title.is_some() || share_meta.note.is_some() {
+            let _ = self
+                .progress_tx
+                .send(TransferProgress::ShareInfo {
+                    id: self.id.clone(),
+                    title: share_meta.title.clone(),
+                    note: share_meta.note.clone(),
+                })
+                .await;
+        }
+        let payload_size: u64 = entry_sizes.iter().sum();
+        let total_files = entry_sizes.len();
+
+        self.options.policy.check(
+            collection
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .zip(entry_sizes.iter().copied()),
+        )?;
+        if self.options.policy.needs_confirmation(payload_size)
+            && !self.await_approval(&collection, &entry_sizes).await
+        {
+            let _ = self
+                .progress_tx
+                .send(TransferProgress::Cancelled {
+                    id: self.id.clone(),
+                })
+                .await;
+            return Ok(());
+        }
+        anyhow::ensure!(
+            !self.options.to_stdout || total_files == 1,
+            "stdout receive needs a single-file share, this one has {} entries",
+            total_files
+        );
+
+        // Oversized snippets fall through and are saved like any file
+        if share_meta.text
+            && !self.options.to_stdout
+            && total_files == 1
+            && payload_size <= MAX_TEXT_SIZE
+        {
+            let name = share_meta
+                .title
+                .clone()
+                .unwrap_or_else(|| "Text snippet".to_string());
+            self.check_disk_space(store_bytes, vec![(self.output_dir.clone(), payload_size)])?;
+            self.download(&connection, hash_and_format, hash_seq, local.is_complete())
+                .await?;
+            return self
+                .receive_text(&collection, payload_size, name, start)
+                .await;
+        }
+
+        let collection = self.sanitize_names(collection, &mut share_meta).await;
+        self.escaped = share_meta.escaped.clone();
+
+        if let Some(folder) = self.subfolder_name(&collection, &share_meta) {
+            self.output_dir = self.output_dir.join(folder);
+        }
+        // Only now are the destinations known; nothing big is downloaded yet
+        let outputs = self.output_bytes(&collection, &entry_sizes);
+        self.check_disk_space(store_bytes, outputs)?;
+        self.download(&connection, hash_and_format, hash_seq, local.is_complete())
+            .await?;
+        let _ = self
+            .progress_tx
+            .send(TransferProgress::Destination {
+                id: self.id.clone(),
+                dir: self.output_dir.clone(),
+            })
+            .await;
+        let _ = self
+            .progress_tx
+            .send(TransferProgress::Hashes {
+                id: self.id.clone(),
+                root: hash_and_format.hash,
+                entries: collection
+                    .iter()
+                    .map(|(name, hash)| (name.clone(), *hash))
+                    .collect(),
+            })
+            .await;
+
         // Send file list for history
         let files: Vec<(String, u64)> = collection
             .iter()
-            .zip(sizes.iter().skip(1)) // Skip metadata size
-            .map(|((name, _hash), &size)| {
-                // Extract just the filename from path
-                let filename = name.rsplit('/').next().unwrap_or(name).to_string();
-                (filename, size)
-            })
+            .zip(entry_sizes.iter())
+            .map(|((name, _hash), &size)| (name.clone(), size))
             .collect();
 
         if !files.is_empty() {
@@ -247,9 +448,32 @@ impl ReceiveTask {
             }
         }
 
-        let conflicts = self.check_conflicts(&collection)?;
-        let resolution = if !conflicts.is_empty() {
-            tracing::info!("Found {} file conflicts", conflicts.len());
+        let conflicts = if self.options.to_stdout {
+            Vec::new()
+        } else {
+            let _ = self
+                .progress_tx
+                .send(TransferProgress::Preparing {
+                    id: self.id.clone(),
+                    status: "Checking existing files...".to_string(),
+                })
+                .await;
+            self.check_conflicts(&collection, &entry_sizes, &share_meta)
+                .await?
+        };
+        let mut resolutions: ConflictResolutions = conflicts
+            .iter()
+            .filter(|c| c.identical)
+            .map(|c| (c.name.clone(), ConflictResolution::SkipIdentical))
+            .collect();
+        let needs_decision = conflicts.iter().any(|c| !c.identical);
+
+        if !conflicts.is_empty() {
+            tracing::info!(
+                "Found {} file conflicts ({} identical)",
+                conflicts.len(),
+                resolutions.len()
+            );
             if let Err(e) = self
                 .progress_tx
                 .send(TransferProgress::FileConflicts {
@@ -261,23 +485,26 @@ impl ReceiveTask {
             {
                 tracing::warn!("Failed to send FileConflicts progress: {}", e);
             }
+        }
 
+        if needs_decision {
             tracing::info!("Waiting for conflict resolution...");
             match self.resolution_rx.recv().await {
                 Some(res) => {
-                    tracing::info!("Got resolution: {:?}", res);
-                    res
+                    tracing::info!("Got resolutions: {:?}", res);
+                    resolutions.extend(res);
                 }
                 None => {
                     tracing::info!("Resolution channel closed, cancelling");
                     return Ok(());
                 }
             }
-        } else {
-            ConflictResolution::Rename
-        };
+        }
 
-        if matches!(resolution, ConflictResolution::Cancel) {
+        if resolutions
+            .values()
+            .any(|r| matches!(r, ConflictResolution::Cancel))
+        {
             if let Err(e) = self
                 .progress_tx
                 .send(TransferProgress::Cancelled {
@@ -290,17 +517,21 @@ impl ReceiveTask {
             return Ok(());
         }
 
-        let name = if total_files == 1 {
+        let name = if let Some(title) = &share_meta.title {
+            title.clone()
+        } else if total_files == 1 {
             collection
                 .iter()
                 .next()
                 .map(|(name, _)| name.to_string())
                 .unwrap_or_else(|| "file".to_string())
         } else {
+            // A share of only empty directories has no entries to name it by
             let first_name = collection
                 .iter()
                 .next()
                 .map(|(name, _)| name.to_string())
+                .or_else(|| share_meta.dirs.iter().next().cloned())
                 .unwrap_or_default();
             if let Some(folder) = first_name.split('/').next() {
                 if collection.iter().all(|(name, _)| name.starts_with(folder)) {
@@ -325,7 +556,41 @@ impl ReceiveTask {
             tracing::warn!("Failed to send Started progress: {}", e);
         }
 
-        let completed = self.export_collection(&collection, &resolution).await?;
+        let completed = if self.options.to_stdout {
+            self.write_to_stdout(&collection).await?
+        } else {
+            // Dropping the staging area on any early return removes it
+            staging::remove_stale(&self.base_dir);
+            let mut staging = Staging::create(&self.base_dir)?;
+            let completed = self
+                .export_collection(
+                    &mut staging,
+                    &collection,
+                    &entry_sizes,
+                    &share_meta,
+                    &resolutions,
+                    &conflicts,
+                )
+                .await?;
+            if completed {
+                let placed = self.commit_staging(staging).await?;
+                self.create_dirs(&share_meta).await?;
+                self.create_symlinks(&share_meta).await;
+                self.report_routes(&collection, &share_meta).await;
+                let entries = self.saved_entries(&placed);
+                self.verify_files(&placed).await;
+                apply_modes(std::mem::take(&mut self.modes), &placed);
+                let _ = self
+                    .progress_tx
+                    .send(TransferProgress::Saved {
+                        id: self.id.clone(),
+                        paths: placed.into_iter().map(|placed| placed.path).collect(),
+                        entries,
+                    })
+                    .await;
+            }
+            completed
+        };
 
         if completed {
             let duration = start.elapsed();
@@ -341,7 +606,6 @@ impl ReceiveTask {
                 tracing::warn!("Failed to send Completed progress: {}", e);
             }
         } else {
-            self.cleanup_exported_files().await;
             if let Err(e) = self
                 .progress_tx
                 .send(TransferProgress::Cancelled {
@@ -356,140 +620,847 @@ impl ReceiveTask {
         Ok(())
     }
 
-    fn check_conflicts(&self, collection: &Collection) -> Result<Vec<(String, PathBuf)>> {
+    /// Shows what the share holds and waits for the user; false when
+    /// declined or when the receive is cancelled while waiting
+    async fn await_approval(&mut self, collection: &Collection, entry_sizes: &[u64]) -> bool {
+        let mut largest: Vec<(String, u64)> = collection
+            .iter()
+            .map(|(name, _)| name.clone())
+            .zip(entry_sizes.iter().copied())
+            .collect();
+        largest.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        largest.truncate(APPROVAL_LARGEST);
+
+        tracing::info!("Waiting for approval...");
+        if let Err(e) = self
+            .progress_tx
+            .send(TransferProgress::ApprovalNeeded {
+                id: self.id.clone(),
+                files: entry_sizes.len(),
+                total_bytes: entry_sizes.iter().sum(),
+                largest,
+            })
+            .await
+        {
+            tracing::warn!("Failed to send ApprovalNeeded progress: {}", e);
+        }
+        self.approval_rx.recv().await.unwrap_or(false)
+    }
+
+    /// Bytes written below each destination. Everything is staged in the
+    /// base directory first, so routed entries on another volume count twice.
+    /// Stdout receives write nothing to disk but the store.
+    fn output_bytes(&self, collection: &Collection, entry_sizes: &[u64]) -> Vec<(PathBuf, u64)> {
+        if self.options.to_stdout {
+            return Vec::new();
+        }
+        let mut outputs = vec![(self.base_dir.clone(), entry_sizes.iter().sum())];
+        for ((name, _), &size) in collection.iter().zip(entry_sizes) {
+            if let Some(dir) = self.route(name) {
+                if !same_volume(&existing_ancestor(dir), &existing_ancestor(&self.base_dir)) {
+                    outputs.push((dir.to_path_buf(), size));
+                }
+            }
+        }
+        outputs
+    }
+
+    /// Fails the receive unless every volume it writes to has room to spare;
+    /// the caller reports the failure
+    fn check_disk_space(&self, store_bytes: u64, outputs: Vec<(PathBuf, u64)>) -> Result<()> {
+        let needs = space_requirements(&self.store_dir, store_bytes, outputs);
+        for (dir, needed) in needs {
+            let free_space =
+                available_space(&dir).context("failed to check available disk space")?;
+            let required_space = needed + MIN_FREE_SPACE;
+            anyhow::ensure!(
+                free_space >= required_space,
+                "insufficient disk space in {}: need {} (+ {} buffer) but only {} available",
+                dir.display(),
+                humansize::format_size(needed, humansize::BINARY),
+                humansize::format_size(MIN_FREE_SPACE, humansize::BINARY),
+                humansize::format_size(free_space, humansize::BINARY)
+            );
+            tracing::info!(
+                "Disk space OK in {}: {} available, {} required",
+                dir.display(),
+                humansize::format_size(free_space, humansize::BINARY),
+                humansize::format_size(required_space, humansize::BINARY)
+            );
+        }
+        Ok(())
+    }
+
+    /// Gets the entry data into the store, or for low-footprint receives sets
+    /// up streaming so it never lands there
+    async fn download(
+        &mut self,
+        connection: &Connection,
+        hash_and_format: HashAndFormat,
+        hash_seq: HashSeq,
+        complete: bool,
+    ) -> Result<()> {
+        if self.options.low_footprint {
+            self.source = EntrySource::Stream {
+                connection: connection.clone(),
+                root: hash_and_format.hash,
+                hash_seq,
+            };
+        } else if !complete {
+            let local = self.store.remote().local(hash_and_format).await?;
+            self.fetch(connection, local.missing()).await?;
+        }
+        Ok(())
+    }
+
+    /// Downloads the requested ranges into the store
+    async fn fetch(&self, connection: &Connection, request: GetRequest) -> Result<()> {
+        let get = self.store.remote().execute_get(connection.clone(), request);
+        let mut stream = get.stream();
+        while let Some(item) = stream.next().await {
+            match item {
+                GetProgressItem::Progress(_) => {}
+                GetProgressItem::Done(_) => break,
+                GetProgressItem::Error(cause) => {
+                    anyhow::bail!("download error: {}", cause);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn receive_text(
+        &self,
+        collection: &Collection,
+        total_bytes: u64,
+        name: String,
+        start: Instant,
+    ) -> Result<()> {
+        let (_, hash) = collection.iter().next().context("share has no entries")?;
+        let mut bytes = Vec::new();
+        self.entry_reader(*hash)?.read_to_end(&mut bytes).await?;
+        let text = String::from_utf8_lossy(&bytes).into_owned();
+
+        let progress = [
+            TransferProgress::Started {
+                id: self.id.clone(),
+                name,
+                total_bytes,
+            },
+            TransferProgress::TextReceived {
+                id: self.id.clone(),
+                text,
+            },
+            TransferProgress::Completed {
+                id: self.id.clone(),
+                total_bytes,
+                duration_secs: start.elapsed().as_secs_f64(),
+            },
+        ];
+        for update in progress {
+            if let Err(e) = self.progress_tx.send(update).await {
+                tracing::warn!("Failed to send text progress: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Streams the only entry to stdout. Returns false if cancelled.
+    async fn write_to_stdout(&mut self, collection: &Collection) -> Result<bool> {
+        let (_, hash) = collection.iter().next().context("share has no entries")?;
+        let mut reader = self.entry_reader(*hash)?;
+        let mut stdout = tokio::io::stdout();
+        let mut buf = vec![0u8; STDOUT_CHUNK];
+        let mut speed_tracker = SpeedTracker::default_window();
+        let mut written: u64 = 0;
+
+        loop {
+            if self.cancel_token.is_cancelled() {
+                tracing::info!("Receive task {} cancelled during stdout write", self.id);
+                return Ok(false);
+            }
+
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            stdout.write_all(&buf[..n]).await?;
+            written += n as u64;
+
+            speed_tracker.add_sample(written);
+            if let Err(e) = self.progress_tx.try_send(TransferProgress::Progress {
+                id: self.id.clone(),
+                transferred_bytes: written,
+                speed_bps: speed_tracker.speed_bps(),
+            }) {
+                tracing::debug!("Progress channel full, skipping update: {}", e);
+            }
+        }
+
+        stdout.flush().await?;
+        Ok(true)
+    }
+
+    /// Entry content from the store, or straight off the connection
+    fn entry_reader(&self, hash: Hash) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+        match &self.source {
+            EntrySource::Store => Ok(Box::new(self.store.reader(hash))),
+            EntrySource::Stream {
+                connection,
+                root,
+                hash_seq,
+            } => {
+                let request = child_request(*root, hash_seq, hash)?;
+                Ok(Box::new(stream_entry(connection.clone(), request, hash)))
+            }
+        }
+    }
+
+    /// Copies a streamed entry into `target`. Returns the bytes written, or
+    /// None if cancelled
+    async fn stream_to_file(
+        &mut self,
+        mut reader: Box<dyn AsyncRead + Send + Unpin>,
+        target: &Path,
+        base: u64,
+        speed_tracker: &mut SpeedTracker,
+    ) -> Result<Option<u64>> {
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::File::create(target).await?;
+        let mut buf = vec![0u8; STDOUT_CHUNK];
+        let mut written: u64 = 0;
+
+        loop {
+            if self.cancel_token.is_cancelled() {
+                tracing::info!("Receive task {} cancelled during file stream", self.id);
+                return Ok(None);
+            }
+
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buf[..n]).await?;
+            written += n as u64;
+
+            let cumulative_bytes = base + written;
+            speed_tracker.add_sample(cumulative_bytes);
+            if let Err(e) = self.progress_tx.try_send(TransferProgress::Progress {
+                id: self.id.clone(),
+                transferred_bytes: cumulative_bytes,
+                speed_bps: speed_tracker.speed_bps(),
+            }) {
+                tracing::debug!("Progress channel full, skipping update: {}", e);
+            }
+        }
+
+        file.flush().await?;
+        Ok(Some(written))
+    }
+
+    fn extracts(&self, share_meta: &ShareMeta, name: &str) -> bool {
+        self.options.auto_extract && share_meta.archives.contains(name)
+    }
+
+    /// Moves the staged collection into the output directory in one go
+    /// Returns where every file moved into place ended up
+    async fn commit_staging(&self, mut staging: Staging) -> Result<Vec<Placed>> {
+        let _ = self
+            .progress_tx
+            .send(TransferProgress::Preparing {
+                id: self.id.clone(),
+                status: "Moving files into place...".to_string(),
+            })
+            .await;
+        let output_dir = self.output_dir.clone();
+        let placed = tokio::task::spawn_blocking(move || {
+            staging.stage_tree(&output_dir)?;
+            staging.commit(Self::find_available_path)
+        })
+        .await??;
+        tracing::info!(
+            "Moved {} files into {}",
+            placed.len(),
+            self.output_dir.display()
+        );
+        Ok(placed)
+    }
+
+    /// Unpacks a tuit archive straight from the store into the staging area;
+    /// existing files are auto-renamed on commit. Every unpacked file is held
+    /// to the receive policy, with `files` the share's running count.
+    /// Returns false if cancelled.
+    async fn extract_archive(
+        &mut self,
+        staging: &Staging,
+        hash: Hash,
+        base: u64,
+        files: &mut usize,
+        speed_tracker: &mut SpeedTracker,
+    ) -> Result<bool> {
+        let read = Arc::new(AtomicU64::new(0));
+        let reader =
+            archive::CountingReader::new(SyncIoBridge::new(self.entry_reader(hash)?), read.clone());
+        let tree = staging.tree();
+        let cancel_token = self.cancel_token.clone();
+        let strictness = self.options.sanitize;
+        // Archive members escaped from non-UTF-8 names are listed with the
+        // share's entries
+        let escaped = self.escaped.clone();
+        let policy = self.options.policy.clone();
+        let mut count = *files;
+        let mut task = tokio::task::spawn_blocking(move || {
+            let mut created = Vec::new();
+            let mut modes = Vec::new();
+            let renames = RefCell::new(Vec::new());
+            let result = archive::extract_archive(
+                reader,
+                |name| {
+                    let clean = sanitize::clean_path(name, strictness);
+                    if clean != name {
+                        renames.borrow_mut().push(EntryRename {
+                            from: name.to_string(),
+                            to: clean.clone(),
+                        });
+                    }
+                    export_path(&tree, &clean, &escaped)
+                },
+                |name| {
+                    count += 1;
+                    // Both the sent name and the one it lands under
+                    policy.check_entry(name, count)?;
+                    policy.check_entry(&sanitize::clean_path(name, strictness), count)
+                },
+                Self::find_available_path,
+                &cancel_token,
+                &mut created,
+                &mut modes,
+            );
+            (result, renames.into_inner(), count, modes)
+        });
+
+        let mut ticker = tokio::time::interval(Duration::from_millis(200));
+        loop {
+            tokio::select! {
+                joined = &mut task => {
+                    let (result, renames, count, modes) = joined?;
+                    *files = count;
+                    // Applied with the other sent modes once in place, by the
+                    // path `stage_tree` stages each file for
+                    for (staged, mode) in modes {
+                        if let Ok(relative) = staged.strip_prefix(staging.tree()) {
+                            let file_meta = FileMeta {
+                                mode: Some(mode),
+                                ..Default::default()
+                            };
+                            self.modes.push((self.output_dir.join(relative), file_meta));
+                        }
+                    }
+                    self.report_renames(renames).await;
+                    return result;
+                }
+                _ = ticker.tick() => {
+                    let cumulative_bytes = base + read.load(Ordering::Relaxed);
+                    speed_tracker.add_sample(cumulative_bytes);
+                    if let Err(e) = self.progress_tx.try_send(TransferProgress::Progress {
+                        id: self.id.clone(),
+                        transferred_bytes: cumulative_bytes,
+                        speed_bps: speed_tracker.speed_bps(),
+                    }) {
+                        tracing::debug!("Progress channel full, skipping update: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Recreates the sender's directories, including empty ones
+    async fn create_dirs(&self, share_meta: &ShareMeta) -> Result<()> {
+        for dir in &share_meta.dirs {
+            let target = self.get_export_path(dir)?;
+            tokio::fs::create_dir_all(&target).await?;
+        }
+        Ok(())
+    }
+
+    /// Links that would escape the tree or clobber existing files are skipped
+    async fn create_symlinks(&self, share_meta: &ShareMeta) {
+        let is_link = |name: &str| share_meta.symlinks.contains_key(name);
+        for (name, target) in &share_meta.symlinks {
+            let checked = meta::check_symlink_target(name, target, is_link).and_then(|_| {
+                let target = if share_meta.escaped_targets.contains(name) {
+                    rawname::decode_target(target)?
+                } else {
+                    target.into()
+                };
+                Ok((self.get_export_path(name)?, target))
+            });
+            let (link, target) = match checked {
+                Ok(checked) => checked,
+                Err(e) => {
+                    tracing::warn!("Rejecting symlink {}: {}", name, e);
+                    continue;
+                }
+            };
+            if tokio::fs::symlink_metadata(&link).await.is_ok() {
+                tracing::warn!("Skipping symlink, path exists: {}", link.display());
+                continue;
+            }
+            if let Some(parent) = link.parent() {
+                if let Err(e) = tokio::fs::create_dir_all(parent).await {
+                    tracing::warn!("Failed to create {}: {}", parent.display(), e);
+                    continue;
+                }
+            }
+
+            #[cfg(unix)]
+            let result = tokio::fs::symlink(&target, &link).await;
+            #[cfg(not(unix))]
+            let result: std::io::Result<()> = Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "symlinks are only recreated on unix",
+            ));
+            if let Err(e) = result {
+                tracing::warn!("Failed to create symlink {}: {}", link.display(), e);
+                continue;
+            }
+            // Links already on disk aren't known to the lexical check
+            let root = self.route(name).unwrap_or(&self.output_dir);
+            if let (Ok(resolved), Ok(root)) = (
+                tokio::fs::canonicalize(&link).await,
+                tokio::fs::canonicalize(root).await,
+            ) {
+                if !resolved.starts_with(&root) {
+                    tracing::warn!("Removing symlink {}, it resolves outside", link.display());
+                    let _ = tokio::fs::remove_file(&link).await;
+                }
+            }
+        }
+    }
+
+    /// Renames entries other systems can't store and reports every rename
+    async fn sanitize_names(
+        &self,
+        collection: Collection,
+        share_meta: &mut ShareMeta,
+    ) -> Collection {
+        let strictness = self.options.sanitize;
+        let sanitizer =
+            NameSanitizer::new(strictness, collection.iter().map(|(name, _)| name.as_str()));
+        let collection = collection
+            .into_iter()
+            .map(|(name, hash)| (sanitizer.name(&name), hash))
+            .collect();
+        share_meta.rename_entries(|name| sanitizer.name(name));
+        // Links keep pointing at their targets under the cleaned names
+        for target in share_meta.symlinks.values_mut() {
+            *target = sanitize::clean_path(target, strictness);
+        }
+        self.report_renames(sanitizer.renames().to_vec()).await;
+        collection
+    }
+
+    /// None when entries should land in the output directory itself
+    fn subfolder_name(&self, collection: &Collection, share_meta: &ShareMeta) -> Option<String> {
+        let name = match self.options.subfolder {
+            ReceiveSubfolder::Off => return None,
+            ReceiveSubfolder::Sender => self.ticket.addr().id.fmt_short().to_string(),
+            ReceiveSubfolder::Share => {
+                let names: Vec<&str> = collection
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .chain(share_meta.dirs.iter().map(String::as_str))
+                    .collect();
+                let top = names.first().and_then(|name| name.split_once('/'));
+                if let Some(title) = &share_meta.title {
+                    title.clone()
+                } else if let Some((folder, _)) = top.filter(|(folder, _)| {
+                    names.iter().all(|name| {
+                        name.strip_prefix(folder)
+                            .is_some_and(|rest| rest.starts_with('/') || rest.is_empty())
+                    })
+                }) {
+                    // Entries already sit in a folder of their own
+                    tracing::debug!("Share is already inside {}", folder);
+                    return None;
+                } else if let [name] = names.as_slice() {
+                    let file = name.rsplit('/').next().unwrap_or(name);
+                    match file.rsplit_once('.') {
+                        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+                        _ => file.to_string(),
+                    }
+                } else {
+                    String::new()
+                }
+            }
+        };
+        Some(
+            sanitize::folder_name(&name, self.options.sanitize).unwrap_or_else(|| {
+                let hash = self.ticket.hash().to_hex();
+                format!("Share {}", &hash[..8])
+            }),
+        )
+    }
+
+    async fn report_renames(&self, renames: Vec<EntryRename>) {
+        if renames.is_empty() {
+            return;
+        }
+        tracing::info!("Renamed {} entries for portability", renames.len());
+        if let Err(e) = self
+            .progress_tx
+            .send(TransferProgress::Renamed {
+                id: self.id.clone(),
+                renames,
+            })
+            .await
+        {
+            tracing::warn!("Failed to send Renamed progress: {}", e);
+        }
+    }
+
+    /// Falls back to no metadata if the sidecar is unreadable
+    async fn load_share_meta(&self, hash: Hash) -> ShareMeta {
+        let bytes = match self.store.get_bytes(hash).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::warn!("Failed to read share metadata: {}", e);
+                return ShareMeta::default();
+            }
+        };
+        ShareMeta::from_bytes(&bytes).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring invalid share metadata: {}", e);
+            ShareMeta::default()
+        })
+    }
+
+    /// Existing targets, with identical content detected by hashing same-sized files
+    async fn check_conflicts(
+        &self,
+        collection: &Collection,
+        sizes: &[u64],
+        share_meta: &ShareMeta,
+    ) -> Result<Vec<FileConflict>> {
         let mut conflicts = Vec::new();
 
-        for (name, _hash) in collection.iter() {
-            let target = self.get_export_path(name)?;
-            if target.exists() {
-                conflicts.push((name.to_string(), target));
+        for ((name, hash), &incoming_size) in collection.iter().zip(sizes) {
+            if self.extracts(share_meta, name) {
+                continue;
             }
+            let target = self.get_export_path(name)?;
+            let Ok(metadata) = tokio::fs::metadata(&target).await else {
+                continue;
+            };
+
+            let file = target.clone();
+            let identical = metadata.is_file()
+                && metadata.len() == incoming_size
+                && match tokio::task::spawn_blocking(move || verify::blake3_file(&file))
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|hash| hash.map_err(|e| e.to_string()))
+                {
+                    Ok(existing) => existing == *hash,
+                    Err(e) => {
+                        tracing::warn!("Failed to hash {}: {}", target.display(), e);
+                        false
+                    }
+                };
+
+            conflicts.push(FileConflict {
+                name: name.to_string(),
+                path: target,
+                existing_size: metadata.len(),
+                existing_modified: metadata.modified().ok(),
+                incoming_size,
+                incoming_modified: share_meta.file(name).and_then(|m| m.modified()),
+                identical,
+            });
         }
 
         Ok(conflicts)
     }
 
-    /// Returns true if completed, false if cancelled
+    /// Exports into `staging`. Returns true if completed, false if cancelled
     async fn export_collection(
         &mut self,
+        staging: &mut Staging,
         collection: &Collection,
-        resolution: &ConflictResolution,
+        sizes: &[u64],
+        share_meta: &ShareMeta,
+        resolutions: &ConflictResolutions,
+        conflicts: &[FileConflict],
     ) -> Result<bool> {
         let mut speed_tracker = SpeedTracker::default_window();
         let mut current_file_base: u64 = 0;
+        // Archives count by their contents against the file limit
+        let mut files = collection
+            .iter()
+            .filter(|(name, _)| !self.extracts(share_meta, name))
+            .count();
 
-        for (name, hash) in collection.iter() {
+        for (i, (name, hash)) in collection.iter().enumerate() {
             if self.cancel_token.is_cancelled() {
                 tracing::info!("Receive task {} cancelled during export", self.id);
                 return Ok(false);
             }
 
-            let base_target = self.get_export_path(name)?;
-            if let Some(parent) = base_target.parent() {
-                tokio::fs::create_dir_all(parent).await?;
+            if self.extracts(share_meta, name) {
+                let size = sizes.get(i).copied().unwrap_or(0);
+                if !self
+                    .extract_archive(
+                        staging,
+                        *hash,
+                        current_file_base,
+                        &mut files,
+                        &mut speed_tracker,
+                    )
+                    .await?
+                {
+                    return Ok(false);
+                }
+                current_file_base += size;
+                continue;
             }
 
-            let target = if base_target.exists() {
-                match resolution {
-                    ConflictResolution::Rename => Self::find_available_path(&base_target),
-                    ConflictResolution::Overwrite => {
-                        if let Err(e) = tokio::fs::remove_file(&base_target).await {
-                            tracing::warn!("Failed to remove existing file for overwrite: {}", e);
+            let base_target = self.get_export_path(name)?;
+            let on_existing = if base_target.exists() {
+                let resolution = resolutions
+                    .get(name)
+                    .copied()
+                    .unwrap_or(ConflictResolution::Rename);
+                // Keep newer: unknown times keep the existing file
+                let resolution = match resolution {
+                    ConflictResolution::KeepNewer => {
+                        let incoming_newer = conflicts
+                            .iter()
+                            .find(|c| c.name == *name)
+                            .and_then(FileConflict::incoming_is_newer)
+                            .unwrap_or(false);
+                        if incoming_newer {
+                            ConflictResolution::Overwrite
+                        } else {
+                            ConflictResolution::Skip
                         }
-                        base_target
                     }
-                    ConflictResolution::Skip => {
+                    other => other,
+                };
+                match resolution {
+                    ConflictResolution::Rename => OnExisting::Rename,
+                    // The existing file is only replaced once everything arrived
+                    ConflictResolution::Overwrite => OnExisting::Overwrite,
+                    ConflictResolution::Skip
+                    | ConflictResolution::SkipIdentical
+                    | ConflictResolution::KeepNewer => {
                         tracing::info!("Skipping existing file: {}", base_target.display());
+                        current_file_base += sizes.get(i).copied().unwrap_or(0);
                         continue;
                     }
                     ConflictResolution::Cancel => return Ok(false),
                 }
             } else {
-                base_target
+                OnExisting::Rename
             };
 
-            self.exported_files.push(target.clone());
-            let mut stream = self
-                .store
-                .export_with_opts(ExportOptions {
-                    hash: *hash,
-                    target: target.clone(),
-                    mode: ExportMode::TryReference,
-                })
-                .stream()
-                .await;
-
-            let mut current_file_size: u64 = 0;
-            let mut file_complete = false;
-
-            while let Some(item) = stream.next().await {
-                if self.cancel_token.is_cancelled() {
-                    tracing::info!("Receive task {} cancelled during file export", self.id);
-                    return Ok(false);
+            let exported = ExportedFile {
+                name: name.clone(),
+                hash: *hash,
+                path: base_target.clone(),
+            };
+            let target = staging.stage(base_target, on_existing, sizes.get(i).copied());
+            let file_complete = if let EntrySource::Stream { .. } = self.source {
+                let reader = self.entry_reader(*hash)?;
+                match self
+                    .stream_to_file(reader, &target, current_file_base, &mut speed_tracker)
+                    .await
+                    .with_context(|| format!("error receiving {}", name))?
+                {
+                    Some(written) => {
+                        current_file_base += written;
+                        true
+                    }
+                    None => return Ok(false),
                 }
+            } else {
+                let mut stream = self
+                    .store
+                    .export_with_opts(ExportOptions {
+                        hash: *hash,
+                        target: target.clone(),
+                        mode: ExportMode::TryReference,
+                    })
+                    .stream()
+                    .await;
+
+                let mut current_file_size: u64 = 0;
+                let mut file_complete = false;
 
-                match item {
-                    ExportProgressItem::Size(size) => {
-                        current_file_size = size;
+                while let Some(item) = stream.next().await {
+                    if self.cancel_token.is_cancelled() {
+                        tracing::info!("Receive task {} cancelled during file export", self.id);
+                        return Ok(false);
                     }
-                    ExportProgressItem::CopyProgress(offset) => {
-                        let cumulative_bytes = current_file_base + offset;
-                        speed_tracker.add_sample(cumulative_bytes);
-                        if let Err(e) = self.progress_tx.try_send(TransferProgress::Progress {
-                            id: self.id.clone(),
-                            transferred_bytes: cumulative_bytes,
-                            speed_bps: speed_tracker.speed_bps(),
-                        }) {
-                            tracing::debug!("Progress channel full, skipping update: {}", e);
+
+                    match item {
+                        ExportProgressItem::Size(size) => {
+                            current_file_size = size;
+                        }
+                        ExportProgressItem::CopyProgress(offset) => {
+                            let cumulative_bytes = current_file_base + offset;
+                            speed_tracker.add_sample(cumulative_bytes);
+                            if let Err(e) = self.progress_tx.try_send(TransferProgress::Progress {
+                                id: self.id.clone(),
+                                transferred_bytes: cumulative_bytes,
+                                speed_bps: speed_tracker.speed_bps(),
+                            }) {
+                                tracing::debug!("Progress channel full, skipping update: {}", e);
+                            }
+                        }
+                        ExportProgressItem::Done => {
+                            current_file_base += current_file_size;
+                            file_complete = true;
+                            break;
+                        }
+                        ExportProgressItem::Error(cause) => {
+                            anyhow::bail!("error exporting {}: {}", name, cause);
                         }
-                    }
-                    ExportProgressItem::Done => {
-                        current_file_base += current_file_size;
-                        file_complete = true;
-                        break;
-                    }
-                    ExportProgressItem::Error(cause) => {
-                        anyhow::bail!("error exporting {}: {}", name, cause);
                     }
                 }
-            }
+                file_complete
+            };
 
             if file_complete {
-                self.exported_files.pop();
+                if let Some(file_meta) = share_meta.file(name) {
+                    if let Err(e) = file_meta.apply_mtime(&target) {
+                        tracing::warn!("Failed to restore metadata on {}: {}", target.display(), e);
+                    }
+                    if file_meta.mode.is_some() {
+                        self.modes.push((exported.path.clone(), *file_meta));
+                    }
+                }
+                self.exported.push(exported);
             }
         }
 
         Ok(true)
     }
 
-    /// Security: Validates path to prevent traversal attacks
-    fn get_export_path(&self, name: &str) -> Result<PathBuf> {
-        let parts: Vec<&str> = name.split('/').collect();
-        let mut path = self.output_dir.clone();
+    /// Final path of each entry saved as a file, after renames and routing
+    fn saved_entries(&self, placed: &[Placed]) -> Vec<(String, PathBuf)> {
+        let final_paths: HashMap<&Path, &Path> = placed
+            .iter()
+            .map(|placed| (placed.target.as_path(), placed.path.as_path()))
+            .collect();
+        self.exported
+            .iter()
+            .filter_map(|file| {
+                let path = final_paths.get(file.path.as_path())?;
+                Some((file.name.clone(), path.to_path_buf()))
+            })
+            .collect()
+    }
 
-        for part in parts {
-            anyhow::ensure!(!part.is_empty(), "empty path component in: {}", name);
-            anyhow::ensure!(
-                part != ".." && part != ".",
-                "path traversal attempt: {}",
-                part
-            );
-            anyhow::ensure!(
-                !part.contains('/') && !part.contains('\\'),
-                "path separator in component: {}",
-                part
-            );
-            path.push(part);
+    /// Hashes the saved files again and reports the result; a mismatch is
+    /// reported but doesn't fail the receive, as the files are already in place
+    async fn verify_files(&mut self, placed: &[Placed]) {
+        if !self.options.verify {
+            return;
+        }
+        let final_paths: HashMap<&Path, &Path> = placed
+            .iter()
+            .map(|placed| (placed.target.as_path(), placed.path.as_path()))
+            .collect();
+        let files: Vec<ExportedFile> = std::mem::take(&mut self.exported)
+            .into_iter()
+            .filter_map(|mut file| {
+                file.path = final_paths.get(file.path.as_path())?.to_path_buf();
+                Some(file)
+            })
+            .collect();
+        if files.is_empty() {
+            return;
         }
 
-        // Catch edge cases like unicode normalization attacks
-        anyhow::ensure!(
-            path.starts_with(&self.output_dir),
-            "path escaped output directory: {}",
-            path.display()
-        );
+        let _ = self
+            .progress_tx
+            .send(TransferProgress::Preparing {
+                id: self.id.clone(),
+                status: format!("Verifying {} files...", files.len()),
+            })
+            .await;
+        let mode = self.options.manifest;
+        let dir = self.output_dir.clone();
+        let stem = format!("checksums-{}", &self.ticket.hash().to_hex()[..8]);
+        let cancel_token = self.cancel_token.clone();
+        let report = match tokio::task::spawn_blocking(move || {
+            verify::verify(&files, mode, &dir, &stem, Self::find_available_path, || {
+                !cancel_token.is_cancelled()
+            })
+        })
+        .await
+        {
+            Ok(report) => report,
+            Err(e) => {
+                tracing::warn!("Verification did not finish: {}", e);
+                return;
+            }
+        };
+        tracing::info!("Verified {} of {} files", report.passed(), report.checked);
+        if let Err(e) = self
+            .progress_tx
+            .send(TransferProgress::Verified {
+                id: self.id.clone(),
+                report,
+            })
+            .await
+        {
+            tracing::warn!("Failed to send Verified progress: {}", e);
+        }
+    }
+
+    /// Routed entries get the same checks, against their route's folder
+    fn get_export_path(&self, name: &str) -> Result<PathBuf> {
+        let dir = self.route(name).unwrap_or(&self.output_dir);
+        export_path(dir, name, &self.escaped)
+    }
+
+    fn route(&self, name: &str) -> Option<&Path> {
+        if self.options.routes.is_empty() {
+            return None;
+        }
+        let sender = self.ticket.addr().id.to_string();
+        self.options.routes.route(name, &sender)
+    }
 
-        Ok(path)
+    /// Files each routing rule's folder got, in folder order
+    async fn report_routes(&self, collection: &Collection, share_meta: &ShareMeta) {
+        let mut dirs: BTreeMap<PathBuf, usize> = BTreeMap::new();
+        for (name, _) in collection.iter() {
+            if self.extracts(share_meta, name) {
+                continue;
+            }
+            if let Some(dir) = self.route(name) {
+                *dirs.entry(dir.to_path_buf()).or_default() += 1;
+            }
+        }
+        if dirs.is_empty() {
+            return;
+        }
+        for (dir, files) in &dirs {
+            tracing::info!("Routed {} file(s) to {}", files, dir.display());
+        }
+        if let Err(e) = self
+            .progress_tx
+            .send(TransferProgress::Routed {
+                id: self.id.clone(),
+                dirs: dirs.into_iter().collect(),
+            })
+            .await
+        {
+            tracing::warn!("Failed to send Routed progress: {}", e);
+        }
     }
 
     /// Find available path by appending (1), (2), etc.
@@ -498,15 +1469,20 @@ impl ReceiveTask {
             return base.to_path_buf();
         }
 
-        let stem = base.file_stem().and_then(|s| s.to_str()).unwrap_or("file");
-        let ext = base.extension().and_then(|e| e.to_str());
+        // Stays on OsStr so names restored from escaped bytes survive
+        let stem = base
+            .file_stem()
+            .unwrap_or_else(|| std::ffi::OsStr::new("file"));
+        let ext = base.extension();
         let parent = base.parent();
 
         for i in 1..1000 {
-            let new_name = match ext {
-                Some(e) => format!("{} ({}).{}", stem, i, e),
-                None => format!("{} ({})", stem, i),
-            };
+            let mut new_name = stem.to_os_string();
+            new_name.push(format!(" ({})", i));
+            if let Some(e) = ext {
+                new_name.push(".");
+                new_name.push(e);
+            }
             let new_path = match parent {
                 Some(p) => p.join(&new_name),
                 None => PathBuf::from(&new_name),
@@ -520,3 +1496,258 @@ impl ReceiveTask {
         base.to_path_buf()
     }
 }
+
+/// Security: Validates path to prevent traversal attacks
+/// Components listed in `escaped` are turned back into their original bytes.
+fn export_path(output_dir: &Path, name: &str, escaped: &BTreeSet<String>) -> Result<PathBuf> {
+    let parts: Vec<&str> = name.split('/').collect();
+    let mut path = output_dir.to_path_buf();
+
+    for (i, &part) in parts.iter().enumerate() {
+        anyhow::ensure!(!part.is_empty(), "empty path component in: {}", name);
+        anyhow::ensure!(
+            part != ".." && part != ".",
+            "path traversal attempt: {}",
+            part
+        );
+        anyhow::ensure!(
+            !part.contains('/') && !part.contains('\\'),
+            "path separator in component: {}",
+            part
+        );
+        if escaped.contains(&parts[..=i].join("/")) {
+            path.push(rawname::decode_os(part)?);
+        } else {
+            path.push(part);
+        }
+    }
+
+    // Catch edge cases like unicode normalization attacks
+    anyhow::ensure!(
+        path.starts_with(output_dir),
+        "path escaped output directory: {}",
+        path.display()
+    );
+
+    Ok(path)
+}
+
+/// Restores sent permissions on the files where they ended up
+fn apply_modes(modes: Vec<(PathBuf, FileMeta)>, placed: &[Placed]) {
+    let final_paths: HashMap<&Path, &Path> = placed
+        .iter()
+        .map(|placed| (placed.target.as_path(), placed.path.as_path()))
+        .collect();
+    for (target, file_meta) in modes {
+        let Some(path) = final_paths.get(target.as_path()) else {
+            continue;
+        };
+        if let Err(e) = file_meta.apply_mode(path) {
+            tracing::warn!("Failed to restore permissions on {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Requests a single collection entry; senders only serve requests rooted at
+/// the share's collection, so entries can't be fetched by their own hash
+fn child_request(root: Hash, hash_seq: &HashSeq, hash: Hash) -> Result<GetRequest> {
+    let child = hash_seq
+        .iter()
+        .position(|h| h == hash)
+        .with_context(|| format!("{} is not part of this share", hash))?;
+    Ok(GetRequest::builder()
+        .child(child as u64, ChunkRanges::all())
+        .build(root))
+}
+
+/// Verified content of one entry, read straight off the connection
+fn stream_entry(
+    connection: Connection,
+    request: GetRequest,
+    hash: Hash,
+) -> impl AsyncRead + Send + Unpin {
+    let (tx, rx) = mpsc::channel::<std::io::Result<Bytes>>(16);
+    tokio::spawn(async move {
+        if let Err(e) = read_entry(connection, request, hash, &tx).await {
+            let _ = tx.send(Err(std::io::Error::other(e))).await;
+        }
+    });
+    StreamReader::new(Box::pin(futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    })))
+}
+
+/// Feeds verified leaves to `tx` in order; stops early if the reader is dropped
+async fn read_entry(
+    connection: Connection,
+    request: GetRequest,
+    hash: Hash,
+    tx: &mpsc::Sender<std::io::Result<Bytes>>,
+) -> Result<()> {
+    let connected = fsm::start(connection, request, Default::default())
+        .next()
+        .await?;
+    let fsm::ConnectedNext::StartChild(child) = connected.next().await? else {
+        anyhow::bail!("unexpected response while streaming {}", hash);
+    };
+    let (mut content, _size) = child.next(hash).next().await?;
+    let end = loop {
+        match content.next().await {
+            fsm::BlobContentNext::More((next, item)) => {
+                if let BaoContentItem::Leaf(leaf) = item? {
+                    if tx.send(Ok(leaf.data)).await.is_err() {
+                        return Ok(());
+                    }
+                }
+                content = next;
+            }
+            fsm::BlobContentNext::Done(end) => break end,
+        }
+    };
+    if let fsm::EndBlobNext::Closing(closing) = end.next() {
+        closing.next().await?;
+    }
+    Ok(())
+}
+
+/// Bytes each volume must hold. The store's copy and the outputs add up
+/// when they live on the same filesystem
+fn space_requirements(
+    store_dir: &Path,
+    store_bytes: u64,
+    outputs: Vec<(PathBuf, u64)>,
+) -> Vec<(PathBuf, u64)> {
+    let store = (store_bytes > 0).then(|| (store_dir.to_path_buf(), store_bytes));
+    let mut needs: Vec<(PathBuf, u64)> = Vec::new();
+    for (dir, bytes) in outputs.into_iter().chain(store) {
+        // Subfolders and route folders may not exist yet
+        let dir = existing_ancestor(&dir);
+        match needs.iter_mut().find(|(seen, _)| same_volume(seen, &dir)) {
+            Some((_, total)) => *total += bytes,
+            None => needs.push((dir, bytes)),
+        }
+    }
+    needs
+}
+
+fn existing_ancestor(path: &Path) -> PathBuf {
+    path.ancestors()
+        .find(|dir| dir.exists())
+        .unwrap_or(path)
+        .to_path_buf()
+}
+
+/// Unknown counts as shared, which only makes the space check stricter
+fn same_volume(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (std::fs::metadata(a), std::fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev(),
+            _ => true,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (a, b);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_requirements_add_up_on_one_volume() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("store");
+        let output = dir.path().join("out");
+        std::fs::create_dir_all(&store).unwrap();
+        std::fs::create_dir_all(&output).unwrap();
+
+        assert_eq!(
+            space_requirements(&store, 40, vec![(output.clone(), 40)]),
+            vec![(output.clone(), 80)]
+        );
+        // Low-footprint receives keep nothing in the store
+        assert_eq!(
+            space_requirements(&store, 0, vec![(output.clone(), 40)]),
+            vec![(output.clone(), 40)]
+        );
+        // Folders still to be created count against the volume they'll be on
+        assert_eq!(
+            space_requirements(
+                &store,
+                10,
+                vec![(output.clone(), 40), (output.join("sub/dir"), 5)]
+            ),
+            vec![(output, 55)]
+        );
+        // Stdout receives only need room in the store
+        assert_eq!(
+            space_requirements(&store, 40, Vec::new()),
+            vec![(store, 40)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_export_path_restores_escaped_components() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let out = Path::new("/out");
+        let escaped = BTreeSet::from(["caf%E9".to_string()]);
+        assert_eq!(
+            export_path(out, "caf%E9/100%25.txt", &escaped).unwrap(),
+            out.join(std::ffi::OsStr::from_bytes(b"caf\xe9"))
+                .join("100%25.txt")
+        );
+        // Names not listed as escaped are taken literally
+        assert_eq!(
+            export_path(out, "caf%E9", &BTreeSet::new()).unwrap(),
+            out.join("caf%E9")
+        );
+        let traversal = BTreeSet::from(["%2E%2E".to_string()]);
+        assert!(export_path(out, "%2E%2E/x", &traversal).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_archive_members_keep_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let src = tempfile::tempdir().unwrap();
+        let project = src.path().join("project");
+        let raw = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join(raw), "menu").unwrap();
+
+        let mut escaped = BTreeSet::new();
+        let archive = archive::write_archive(
+            &[project],
+            &crate::transfer::SendOptions::default(),
+            &mut Vec::new(),
+            &mut escaped,
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(escaped, BTreeSet::from(["project/caf%E9.txt".to_string()]));
+
+        let dest = tempfile::tempdir().unwrap();
+        archive::extract_archive(
+            archive.as_slice(),
+            |name| export_path(dest.path(), name, &escaped),
+            |_| Ok(()),
+            Path::to_path_buf,
+            &CancellationToken::new(),
+            &mut Vec::new(),
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dest.path().join("project").join(raw)).unwrap(),
+            "menu"
+        );
+    }
+}
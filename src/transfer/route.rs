@@ -0,0 +1,168 @@
+//! Rule-based routing of received files
+//!
+//! Rules send matching entries to a folder of their own instead of the
+//! receive directory. Entries keep their relative path below that folder and
+//! pass the same traversal checks as any other export. Archive contents are
+//! unpacked as a whole and are not routed.
+
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Where files matching all of the given conditions are saved
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteRule {
+    /// Gitignore-style pattern matched against entry names, like `*.pdf`,
+    /// ignoring case
+    pub pattern: Option<String>,
+    /// Sender endpoint id, or the start of one
+    pub sender: Option<String>,
+    /// Folder matching files are saved under; `~/` is the home directory
+    pub to: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    pattern: Option<Gitignore>,
+    sender: Option<String>,
+    dir: PathBuf,
+}
+
+/// Rules in config order; the first match wins
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    /// Rules without a condition, without a folder or with a bad pattern
+    /// are left out with a warning
+    pub fn new(rules: &[RouteRule]) -> Self {
+        let routes = rules
+            .iter()
+            .filter_map(|rule| match build_route(rule) {
+                Ok(route) => Some(route),
+                Err(e) => {
+                    tracing::warn!("Ignoring receive route to {}: {}", rule.to.display(), e);
+                    None
+                }
+            })
+            .collect();
+        Self { routes }
+    }
+
+    /// Folder for entry `name` from `sender`, if a rule claims it
+    pub fn route(&self, name: &str, sender: &str) -> Option<&Path> {
+        self.routes
+            .iter()
+            .find(|route| {
+                let pattern_ok = route.pattern.as_ref().is_none_or(|pattern| {
+                    pattern.matched_path_or_any_parents(name, false).is_ignore()
+                });
+                let sender_ok = route
+                    .sender
+                    .as_ref()
+                    .is_none_or(|prefix| sender.to_lowercase().starts_with(prefix));
+                pattern_ok && sender_ok
+            })
+            .map(|route| route.dir.as_path())
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+fn build_route(rule: &RouteRule) -> anyhow::Result<Route> {
+    anyhow::ensure!(
+        rule.pattern.is_some() || rule.sender.is_some(),
+        "a route needs a pattern or a sender"
+    );
+    anyhow::ensure!(!rule.to.as_os_str().is_empty(), "a route needs a folder");
+
+    let pattern = match &rule.pattern {
+        Some(pattern) => {
+            let mut builder = GitignoreBuilder::new("");
+            builder.case_insensitive(true)?;
+            builder.add_line(None, pattern)?;
+            Some(builder.build()?)
+        }
+        None => None,
+    };
+    let sender = rule
+        .sender
+        .as_ref()
+        .map(|sender| sender.trim().to_lowercase())
+        .filter(|sender| !sender.is_empty());
+    anyhow::ensure!(rule.sender.is_none() || sender.is_some(), "empty sender");
+
+    Ok(Route {
+        pattern,
+        sender,
+        dir: expand_home(&rule.to),
+    })
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    match (path.strip_prefix("~"), home) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: Option<&str>, sender: Option<&str>, to: &str) -> RouteRule {
+        RouteRule {
+            pattern: pattern.map(String::from),
+            sender: sender.map(String::from),
+            to: PathBuf::from(to),
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let router = Router::new(&[
+            rule(Some("*.pdf"), Some("ABC"), "/shared/abc"),
+            rule(Some("*.pdf"), None, "/inbox"),
+            rule(None, Some("def"), "/shared/def"),
+        ]);
+        assert_eq!(
+            router.route("docs/a.pdf", "abc123"),
+            Some(Path::new("/shared/abc"))
+        );
+        assert_eq!(router.route("a.PDF", "def456"), Some(Path::new("/inbox")));
+        assert_eq!(
+            router.route("notes.txt", "def456"),
+            Some(Path::new("/shared/def"))
+        );
+        assert_eq!(router.route("notes.txt", "abc123"), None);
+    }
+
+    #[test]
+    fn test_directory_patterns_match_their_entries() {
+        let router = Router::new(&[rule(Some("photos/"), None, "/pictures")]);
+        assert_eq!(
+            router.route("photos/2024/a.jpg", "x"),
+            Some(Path::new("/pictures"))
+        );
+        assert_eq!(router.route("photos.txt", "x"), None);
+    }
+
+    #[test]
+    fn test_incomplete_rules_are_skipped() {
+        let router = Router::new(&[
+            rule(None, None, "/anything"),
+            rule(Some("*.iso"), None, ""),
+            rule(None, Some("  "), "/blank"),
+        ]);
+        assert!(router.is_empty());
+    }
+}
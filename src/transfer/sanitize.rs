@@ -0,0 +1,303 @@
+//! Receive-side cleanup of entry names other systems can't store
+//!
+//! Traversal is still rejected by the receiver; this only renames entries
+//! that are legal here but break on Windows, macOS or shared Linux volumes.
+//! Every component is cleaned on its own, so a renamed directory carries
+//! all of its entries along.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+/// How far received names are changed to stay portable
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NameStrictness {
+    /// Names are kept as sent
+    Off,
+    /// Fix names that Windows or macOS can't store or tell apart
+    #[default]
+    Portable,
+    /// Portable, plus names that are awkward in shells or too long
+    Strict,
+}
+
+impl NameStrictness {
+    pub fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "off" | "none" => Self::Off,
+            "strict" => Self::Strict,
+            _ => Self::Portable,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Portable => "portable",
+            Self::Strict => "strict",
+        }
+    }
+}
+
+/// An entry saved under a different name than the sender gave it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// Device names Windows reserves in any directory, with or without extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters Windows refuses in file names; `:` also trips up macOS
+const FORBIDDEN_CHARS: [char; 8] = ['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+/// Longest component most filesystems accept, in bytes
+const MAX_COMPONENT_BYTES: usize = 255;
+
+/// Final names for a share's entries, decided once up front so the same
+/// share is always saved the same way
+pub struct NameSanitizer {
+    strictness: NameStrictness,
+    renames: Vec<EntryRename>,
+}
+
+impl NameSanitizer {
+    /// Entries that still clash after cleaning, including case-only and
+    /// NFC/NFD twins, get ` (1)`, ` (2)`, etc. in collection order
+    pub fn new<'a>(strictness: NameStrictness, names: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut renames = Vec::new();
+        if strictness == NameStrictness::Off {
+            return Self {
+                strictness,
+                renames,
+            };
+        }
+
+        let cleaned: Vec<(&str, String)> = names
+            .into_iter()
+            .map(|name| (name, clean_path(name, strictness)))
+            .collect();
+        let wanted: HashSet<String> = cleaned.iter().map(|(_, c)| fold(c)).collect();
+        let mut taken = HashSet::new();
+
+        for (name, clean) in cleaned {
+            // Suffixed names also steer clear of names other entries want
+            let mut target = clean.clone();
+            let mut n = 1;
+            while taken.contains(&fold(&target))
+                || (target != clean && wanted.contains(&fold(&target)))
+            {
+                target = with_suffix(&clean, n);
+                n += 1;
+            }
+            taken.insert(fold(&target));
+            if target != name {
+                renames.push(EntryRename {
+                    from: name.to_string(),
+                    to: target,
+                });
+            }
+        }
+
+        Self {
+            strictness,
+            renames,
+        }
+    }
+
+    /// Name to save `name` under; also covers directories and symlinks
+    /// that are not entries of their own
+    pub fn name(&self, name: &str) -> String {
+        match self.renames.iter().find(|r| r.from == name) {
+            Some(rename) => rename.to.clone(),
+            None => clean_path(name, self.strictness),
+        }
+    }
+
+    pub fn renames(&self) -> &[EntryRename] {
+        &self.renames
+    }
+}
+
+/// Cleans every component of a `/`-separated entry name
+pub fn clean_path(name: &str, strictness: NameStrictness) -> String {
+    name.split('/')
+        .map(|part| clean_component(part, strictness))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A single directory name made from free text such as a share title,
+/// cleaned at least as far as `Portable`; None if nothing usable is left
+pub fn folder_name(text: &str, strictness: NameStrictness) -> Option<String> {
+    let strictness = match strictness {
+        NameStrictness::Off => NameStrictness::Portable,
+        other => other,
+    };
+    let name = clean_component(text.replace(['/', '\\'], "_").trim(), strictness);
+    (!matches!(name.as_str(), "" | "." | "..")).then_some(name)
+}
+
+fn clean_component(part: &str, strictness: NameStrictness) -> String {
+    // Left alone so the receiver's traversal checks still reject them
+    if strictness == NameStrictness::Off || matches!(part, "" | "." | "..") {
+        return part.to_string();
+    }
+
+    let mut name: String = part
+        .nfc()
+        .map(|c| {
+            if c.is_control() || FORBIDDEN_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    if strictness == NameStrictness::Strict {
+        name = name.trim_start().to_string();
+        if let Some(rest) = name.strip_prefix('-') {
+            name = format!("_{}", rest);
+        }
+    }
+
+    // Windows silently drops trailing dots and spaces
+    name.truncate(name.trim_end_matches(['.', ' ']).len());
+    if name.is_empty() {
+        name.push('_');
+    }
+
+    let (stem, ext) = split_ext(&name);
+    if RESERVED_NAMES.contains(&stem.trim_end().to_uppercase().as_str()) {
+        name = format!("{}_{}", stem, ext);
+    }
+
+    if strictness == NameStrictness::Strict && name.len() > MAX_COMPONENT_BYTES {
+        let (stem, ext) = split_ext(&name);
+        let mut end = MAX_COMPONENT_BYTES.saturating_sub(ext.len());
+        while !stem.is_char_boundary(end) {
+            end -= 1;
+        }
+        name = format!("{}{}", &stem[..end], ext);
+    }
+
+    name
+}
+
+/// Splits at the first dot, which is where Windows ends a device name;
+/// the extension keeps its dot
+fn split_ext(name: &str) -> (&str, &str) {
+    match name.find('.') {
+        Some(0) | None => (name, ""),
+        Some(i) => name.split_at(i),
+    }
+}
+
+/// Adds ` (n)` to the last component, before its extension
+fn with_suffix(name: &str, n: usize) -> String {
+    let (dir, file) = match name.rsplit_once('/') {
+        Some((dir, file)) => (Some(dir), file),
+        None => (None, name),
+    };
+    let file = match file.rfind('.') {
+        Some(i) if i > 0 => format!("{} ({}){}", &file[..i], n, &file[i..]),
+        _ => format!("{} ({})", file, n),
+    };
+    match dir {
+        Some(dir) => format!("{}/{}", dir, file),
+        None => file,
+    }
+}
+
+/// Key under which case-insensitive filesystems consider names equal
+fn fold(name: &str) -> String {
+    name.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sanitize(names: &[&str], strictness: NameStrictness) -> Vec<String> {
+        let sanitizer = NameSanitizer::new(strictness, names.iter().copied());
+        names.iter().map(|n| sanitizer.name(n)).collect()
+    }
+
+    #[test]
+    fn test_portable_fixes_windows_names() {
+        assert_eq!(
+            sanitize(
+                &["CON", "docs/aux.txt", "a:b?.txt", "notes. ", "com1.tar.gz"],
+                NameStrictness::Portable
+            ),
+            vec!["CON_", "docs/aux_.txt", "a_b_.txt", "notes", "com1_.tar.gz"]
+        );
+    }
+
+    #[test]
+    fn test_collisions_get_suffixes() {
+        let nfd = "cafe\u{301}.txt";
+        let nfc = "caf\u{e9}.txt";
+        assert_eq!(
+            sanitize(
+                &["Readme", "README", "dir/README (1)", nfd, nfc],
+                NameStrictness::Portable
+            ),
+            vec![
+                "Readme",
+                "README (1)",
+                "dir/README (1)",
+                nfc,
+                "caf\u{e9} (1).txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_renames_are_listed() {
+        let sanitizer = NameSanitizer::new(NameStrictness::Portable, ["ok.txt", "a*b"]);
+        assert_eq!(
+            sanitizer.renames(),
+            &[EntryRename {
+                from: "a*b".into(),
+                to: "a_b".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_traversal_components_are_kept() {
+        assert_eq!(clean_path("../x", NameStrictness::Strict), "../x");
+        assert_eq!(clean_path("a//b", NameStrictness::Portable), "a//b");
+    }
+
+    #[test]
+    fn test_folder_name_is_one_component() {
+        assert_eq!(
+            folder_name(" Trip: photos/2024 ", NameStrictness::Off).as_deref(),
+            Some("Trip_ photos_2024")
+        );
+        assert_eq!(folder_name("..", NameStrictness::Portable), None);
+        assert_eq!(folder_name("   ", NameStrictness::Portable), None);
+    }
+
+    #[test]
+    fn test_strict_and_off() {
+        let long = format!("{}.txt", "x".repeat(300));
+        let strict = sanitize(&[" -rf", &long], NameStrictness::Strict);
+        assert_eq!(strict[0], "_rf");
+        assert_eq!(strict[1].len(), MAX_COMPONENT_BYTES);
+        assert!(strict[1].ends_with(".txt"));
+
+        assert_eq!(
+            sanitize(&["CON", "con"], NameStrictness::Off),
+            vec!["CON", "con"]
+        );
+    }
+}
@@ -1,66 +1,231 @@
 //! Send files via iroh-blobs (based on sendme)
 
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Write;
 use std::path::{Component, Path, PathBuf};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{Context, Result};
+use bytes::Bytes;
 use futures::stream;
 use iroh::discovery::dns::DnsDiscovery;
 use iroh::protocol::Router;
 use iroh::Endpoint;
-use iroh_blobs::api::blobs::{AddPathOptions, AddProgressItem, ImportMode};
+use iroh_blobs::api::blobs::{AddPathOptions, AddProgressItem, BlobStatus, ImportMode};
 use iroh_blobs::api::TempTag;
 use iroh_blobs::format::collection::Collection;
 use iroh_blobs::provider::events::{
-    ConnectMode, EventMask, EventSender, ProviderMessage, RequestMode, RequestUpdate,
+    AbortReason, ConnectMode, EventMask, EventSender, ProviderMessage, RequestMode, RequestUpdate,
 };
 use iroh_blobs::store::fs::FsStore;
 use iroh_blobs::ticket::BlobTicket;
-use iroh_blobs::{BlobFormat, BlobsProtocol};
+use iroh_blobs::{BlobFormat, BlobsProtocol, Hash};
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
-use walkdir::WalkDir;
 
+use super::archive;
+use super::filter::{self, ExcludeOptions, Excluded, SkippedEntry};
+use super::meta::{self, FileMeta, ShareMeta, META_NAME, TEXT_NAME};
+use super::naming::{EntryNamer, EntryNaming, NameCheck};
+use super::rawname;
+use super::sanitize::EntryRename;
 use super::{SpeedTracker, TransferId, TransferProgress};
 
 const PARALLEL_IMPORTS: usize = 4;
 
+/// How often import progress is reported while hashing
+const IMPORT_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often files shared in place are checked for modification
+const REFERENCE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How symlinks inside a selection are sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkMode {
+    #[default]
+    Skip, // Default for security
+    Follow,
+    /// Record the link target; the receiver recreates it if it stays inside the tree
+    Preserve,
+}
+
+impl SymlinkMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Skip => Self::Follow,
+            Self::Follow => Self::Preserve,
+            Self::Preserve => Self::Skip,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Follow => "follow",
+            Self::Preserve => "keep",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SendOptions {
-    pub follow_symlinks: bool, // Default false for security
+    pub symlinks: SymlinkMode,
+    /// Pack the whole selection into one tar+zstd blob
+    pub archive: bool,
+    /// Reference files where they are instead of copying them into the store.
+    /// They must not change until the transfer finishes.
+    pub in_place: bool,
+    pub exclude: ExcludeOptions,
+    /// Leave out unreadable entries and report them instead of failing
+    pub skip_unreadable: bool,
+    /// Stop serving earlier tickets once the share is changed
+    pub retire_old_tickets: bool,
+    /// Name the receiver sees instead of one derived from the entries
+    pub title: Option<String>,
+    /// Short message shown to the receiver
+    pub note: Option<String>,
+    /// Layout, prefix and renames for the collection entry names
+    pub naming: EntryNaming,
+}
+
+/// What a send imports into the store
+#[derive(Debug, Clone)]
+pub enum SendSource {
+    Paths(Vec<PathBuf>),
+    /// Stdin read to EOF as a single entry with this name
+    Stdin {
+        name: String,
+    },
+    /// A snippet the receiver displays instead of saving
+    Text(String),
+}
+
+/// Entries to add to or take out of a running share
+#[derive(Debug, Clone, Default)]
+pub struct ShareUpdate {
+    /// Paths added at the top level, replacing entries with the same name
+    pub add: Vec<PathBuf>,
+    /// Entry names to take out; a directory takes everything beneath it
+    pub remove: Vec<String>,
+}
+
+pub struct ShareUpdater {
+    pub tx: mpsc::Sender<ShareUpdate>,
+}
+
+/// Progress shared by the parallel file imports
+#[derive(Default)]
+struct ImportCounters {
+    hashed_bytes: AtomicU64,
+    files_done: AtomicUsize,
+    current: Mutex<String>,
+}
+
+/// What an import leaves behind besides the collection
+#[derive(Default)]
+struct ImportReport {
+    /// Files shared in place, checked for changes while serving
+    referenced: Vec<FileSnapshot>,
+    /// Entries left out: unreadable under the skip policy, or special files
+    skipped: Vec<SkippedEntry>,
+    /// Entries whose non-UTF-8 names are sent escaped
+    escaped: Vec<EntryRename>,
+}
+
+/// Size and mtime of a file shared in place, taken before import
+struct FileSnapshot {
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileSnapshot {
+    fn new(path: PathBuf, metadata: &std::fs::Metadata) -> Self {
+        Self {
+            path,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+
+    /// The store serves referenced data straight from disk, so any change
+    /// would hand the peer content that no longer matches its hash
+    fn check(&self) -> Result<()> {
+        let metadata = std::fs::metadata(&self.path)
+            .with_context(|| format!("{} is no longer readable", self.path.display()))?;
+        anyhow::ensure!(
+            metadata.len() == self.len && metadata.modified().ok() == self.modified,
+            "{} was modified while being shared",
+            self.path.display()
+        );
+        Ok(())
+    }
+}
+
+fn check_snapshots(snapshots: &[FileSnapshot]) -> Result<()> {
+    snapshots.iter().try_for_each(FileSnapshot::check)
+}
+
+/// Blocking writer that forwards chunks to an async blob import
+struct ChannelWriter {
+    tx: mpsc::Sender<std::io::Result<Bytes>>,
+    written: u64,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::BrokenPipe, "blob import stopped")
+            })?;
+        self.written += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 /// Each SendTask creates an isolated Endpoint (avoids concurrent send conflicts)
 pub struct SendTask {
     id: TransferId,
-    paths: Vec<PathBuf>,
+    source: SendSource,
     store: Arc<FsStore>,
     progress_tx: mpsc::Sender<TransferProgress>,
     options: SendOptions,
     cancel_token: CancellationToken,
+    update_rx: mpsc::Receiver<ShareUpdate>,
 }
 
 impl SendTask {
     pub fn new(
         id: impl Into<TransferId>,
-        paths: Vec<PathBuf>,
+        source: SendSource,
         store: Arc<FsStore>,
         progress_tx: mpsc::Sender<TransferProgress>,
         options: SendOptions,
         cancel_token: CancellationToken,
-    ) -> Self {
-        Self {
-            id: id.into(),
-            paths,
-            store,
-            progress_tx,
-            options,
-            cancel_token,
-        }
+    ) -> (Self, ShareUpdater) {
+        let (update_tx, update_rx) = mpsc::channel(4);
+        (
+            Self {
+                id: id.into(),
+                source,
+                store,
+                progress_tx,
+                options,
+                cancel_token,
+                update_rx,
+            },
+            ShareUpdater { tx: update_tx },
+        )
     }
 
-    pub async fn run(self) -> Result<()> {
+    pub async fn run(mut self) -> Result<()> {
         let start = Instant::now();
 
         // Phase 1: Preparing - importing files
@@ -72,7 +237,32 @@ impl SendTask {
             })
             .await;
 
-        let (temp_tag, total_size, collection, name) = self.import_files().await?;
+        let mut report = ImportReport::default();
+        let (mut temp_tag, mut total_size, mut collection, name) =
+            self.import_files(&mut report).await?;
+        let name = self.title().unwrap_or(name);
+        let mut referenced = report.referenced;
+        // Catch edits made while the files were being hashed
+        check_snapshots(&referenced)?;
+
+        if !report.skipped.is_empty() {
+            let _ = self
+                .progress_tx
+                .send(TransferProgress::Skipped {
+                    id: self.id.clone(),
+                    entries: report.skipped,
+                })
+                .await;
+        }
+        self.report_escaped(report.escaped).await;
+        self.report_hashes(temp_tag.hash(), &collection).await;
+        let _ = self
+            .progress_tx
+            .send(TransferProgress::ShareContents {
+                id: self.id.clone(),
+                entries: share_entries(&collection),
+            })
+            .await;
 
         // Phase 2: Preparing - creating endpoint
         let _ = self
@@ -91,13 +281,21 @@ impl SendTask {
             .context("failed to create endpoint for send")?;
 
         let (event_tx, mut event_rx) = mpsc::channel::<ProviderMessage>(32);
+        // Get requests are checked against the tickets this share still serves.
+        // Multi-blob requests bypass that check, so retiring disables them.
+        let get_many = if self.options.retire_old_tickets {
+            RequestMode::Disabled
+        } else {
+            RequestMode::None
+        };
         let blobs = BlobsProtocol::new(
             &self.store,
             Some(EventSender::new(
                 event_tx,
                 EventMask {
                     connected: ConnectMode::Notify,
-                    get: RequestMode::NotifyLog,
+                    get: RequestMode::InterceptLog,
+                    get_many,
                     ..EventMask::DEFAULT
                 },
             )),
@@ -125,7 +323,7 @@ impl SendTask {
         // Phase 4: Generate ticket
         let addr = router.endpoint().addr();
         tracing::info!("Creating ticket with addr: {:?}", addr);
-        let ticket = BlobTicket::new(addr, temp_tag.hash(), BlobFormat::HashSeq);
+        let ticket = BlobTicket::new(addr.clone(), temp_tag.hash(), BlobFormat::HashSeq);
         tracing::info!("Ticket: {}", ticket);
 
         let _ = self
@@ -156,13 +354,20 @@ impl SendTask {
 
         // (blob_index, offset, is_complete) progress channel
         let (progress_update_tx, mut progress_update_rx) = mpsc::channel::<(u64, u64, bool)>(32);
-        let mut blob_progress: std::collections::HashMap<u64, u64> =
-            std::collections::HashMap::new();
-        let mut completed_blobs: std::collections::HashSet<u64> = std::collections::HashSet::new();
+        let mut blob_progress: HashMap<u64, u64> = HashMap::new();
+        let mut completed_blobs: HashSet<u64> = HashSet::new();
         let mut had_connection = false;
         let mut connection_reported = false;
         let mut speed_tracker = SpeedTracker::default_window();
-        let expected_blob_count = collection.len() + 1; // metadata + files
+        let mut expected_blob_count = collection.len() + 1; // metadata + files
+
+        // Hashes peers may request; earlier versions of the share stay here
+        // unless old tickets are retired
+        let mut served: HashSet<Hash> = share_hashes(&temp_tag, &collection).collect();
+        let mut previous_tags: Vec<TempTag> = Vec::new();
+        let mut refused_connections: HashSet<u64> = HashSet::new();
+
+        let mut reference_check = tokio::time::interval(REFERENCE_CHECK_INTERVAL);
 
         let mut cancelled = false;
         loop {
@@ -172,6 +377,73 @@ impl SendTask {
                     cancelled = true;
                     break;
                 }
+                _ = reference_check.tick(), if !referenced.is_empty() => {
+                    // Dropping the router on return closes the peer's connection
+                    check_snapshots(&referenced)?;
+                }
+                Some(update) = self.update_rx.recv() => {
+                    let mut report = ImportReport::default();
+                    let (new_tag, new_collection, new_size) =
+                        match self.update_share(&collection, update, &mut report).await {
+                            Ok(updated) => updated,
+                            Err(e) => {
+                                tracing::warn!("Failed to update share {}: {:#}", self.id, e);
+                                let _ = self.progress_tx
+                                    .send(TransferProgress::ShareUpdateFailed {
+                                        id: self.id.clone(),
+                                        error: format!("{:#}", e),
+                                    })
+                                    .await;
+                                continue;
+                            }
+                        };
+
+                    if self.options.retire_old_tickets {
+                        served.clear();
+                    } else {
+                        previous_tags.push(temp_tag);
+                    }
+                    served.extend(share_hashes(&new_tag, &new_collection));
+                    temp_tag = new_tag;
+                    collection = new_collection;
+                    total_size = new_size;
+                    referenced.extend(report.referenced);
+                    expected_blob_count = collection.len() + 1;
+                    blob_progress.clear();
+                    completed_blobs.clear();
+
+                    let ticket = BlobTicket::new(addr.clone(), temp_tag.hash(), BlobFormat::HashSeq);
+                    tracing::info!("Share {} updated, new ticket: {}", self.id, ticket);
+                    if !report.skipped.is_empty() {
+                        let _ = self.progress_tx
+                            .send(TransferProgress::Skipped {
+                                id: self.id.clone(),
+                                entries: report.skipped,
+                            })
+                            .await;
+                    }
+                    self.report_escaped(report.escaped).await;
+                    self.report_hashes(temp_tag.hash(), &collection).await;
+                    let _ = self.progress_tx
+                        .send(TransferProgress::ShareContents {
+                            id: self.id.clone(),
+                            entries: share_entries(&collection),
+                        })
+                        .await;
+                    let _ = self.progress_tx
+                        .send(TransferProgress::TicketReady {
+                            id: self.id.clone(),
+                            ticket: ticket.to_string(),
+                        })
+                        .await;
+                    let _ = self.progress_tx
+                        .send(TransferProgress::Started {
+                            id: self.id.clone(),
+                            name: name.clone(),
+                            total_bytes: total_size,
+                        })
+                        .await;
+                }
                 msg = event_rx.recv() => {
                     match msg {
                         Some(ProviderMessage::ClientConnectedNotify(msg)) => {
@@ -191,8 +463,18 @@ impl SendTask {
                                     .await;
                             }
                         }
-                        Some(ProviderMessage::GetRequestReceivedNotify(msg)) => {
-                            tracing::info!("Get request: connection_id={}", msg.inner.connection_id);
+                        Some(ProviderMessage::GetRequestReceived(msg)) => {
+                            let connection_id = msg.inner.connection_id;
+                            let hash = msg.inner.request.hash;
+                            if !served.contains(&hash) {
+                                tracing::info!("Refusing get request for retired {}: connection_id={}", hash, connection_id);
+                                refused_connections.insert(connection_id);
+                                msg.tx.send(Err(AbortReason::Permission)).await.ok();
+                                continue;
+                            }
+                            tracing::info!("Get request: connection_id={}", connection_id);
+                            refused_connections.remove(&connection_id);
+                            msg.tx.send(Ok(())).await.ok();
                             let progress_tx = progress_update_tx.clone();
                             tokio::spawn(async move {
                                 let mut rx = msg.rx;
@@ -221,6 +503,10 @@ impl SendTask {
                         }
                         Some(ProviderMessage::ConnectionClosed(msg)) => {
                             tracing::info!("Connection closed: connection_id={}", msg.inner.connection_id);
+                            // A peer holding a retired ticket doesn't end the share
+                            if refused_connections.remove(&msg.inner.connection_id) {
+                                continue;
+                            }
                             if completed_blobs.len() >= expected_blob_count || had_connection {
                                 break;
                             }
@@ -258,6 +544,7 @@ impl SendTask {
         }
 
         drop(temp_tag);
+        drop(previous_tags);
         drop(router);
 
         if cancelled {
@@ -288,87 +575,504 @@ impl SendTask {
         Ok(())
     }
 
-    async fn import_files(&self) -> Result<(TempTag, u64, Collection, String)> {
+    /// Files imported in place and skipped entries are recorded in `report`
+    async fn import_files(
+        &self,
+        report: &mut ImportReport,
+    ) -> Result<(TempTag, u64, Collection, String)> {
+        let paths = match &self.source {
+            SendSource::Paths(paths) => paths,
+            SendSource::Stdin { name } => return self.import_stdin(name).await,
+            SendSource::Text(text) => return self.import_text(text).await,
+        };
+
+        let name = if paths.len() == 1 {
+            paths[0]
+                .file_name()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "files".to_string())
+        } else {
+            format!("{} items", paths.len())
+        };
+
+        if self.options.archive {
+            return self.import_archive(name, paths.clone(), report).await;
+        }
+
+        let (mut names_and_tags, mut share_meta) = self
+            .import_paths(paths, &self.options.naming, report)
+            .await?;
+        self.add_labels(&mut share_meta);
+        let total_size: u64 = names_and_tags.iter().map(|(_, _, size)| *size).sum();
+
+        // Sidecar with modes/mtimes/dirs/links; peers that don't know it just get an extra file
+        if !share_meta.is_empty() {
+            let meta_tag = self
+                .store
+                .add_bytes(share_meta.to_bytes()?)
+                .temp_tag()
+                .await?;
+            names_and_tags.push((META_NAME.to_string(), meta_tag, 0));
+        }
+
+        let (temp_tag, collection) = self
+            .store_collection(
+                names_and_tags
+                    .into_iter()
+                    .map(|(name, tag, _)| (name, tag))
+                    .collect(),
+            )
+            .await?;
+        Ok((temp_tag, total_size, collection, name))
+    }
+
+    /// Builds the collection for a changed share, reusing the blobs of
+    /// entries that stay; the current collection is left as it is
+    async fn update_share(
+        &self,
+        collection: &Collection,
+        update: ShareUpdate,
+        report: &mut ImportReport,
+    ) -> Result<(TempTag, Collection, u64)> {
+        anyhow::ensure!(
+            matches!(self.source, SendSource::Paths(_)) && !self.options.archive,
+            "only file and folder shares can be changed"
+        );
+
+        let mut share_meta = match collection.iter().find(|(name, _)| name == META_NAME) {
+            Some((_, hash)) => ShareMeta::from_bytes(&self.store.get_bytes(*hash).await?)?,
+            None => ShareMeta::default(),
+        };
+        share_meta.remove_entries(&update.remove);
+
+        let (added, added_meta) = self
+            .import_paths(&update.add, &EntryNaming::default(), report)
+            .await?;
+        share_meta.merge(added_meta);
+
+        let mut entries: Vec<(String, Hash)> = Vec::with_capacity(collection.len());
+        let mut total_size = 0u64;
+        for (name, hash) in collection.iter() {
+            if name == META_NAME
+                || meta::is_removed(name, &update.remove)
+                || added.iter().any(|(added, _, _)| added == name)
+            {
+                continue;
+            }
+            total_size += self.blob_size(*hash).await?;
+            entries.push((name.clone(), *hash));
+        }
+        for (name, tag, size) in &added {
+            total_size += size;
+            entries.push((name.clone(), tag.hash()));
+        }
+        anyhow::ensure!(!entries.is_empty(), "a share needs at least one entry");
+
+        let meta_tag = if share_meta.is_empty() {
+            None
+        } else {
+            let tag = self
+                .store
+                .add_bytes(share_meta.to_bytes()?)
+                .temp_tag()
+                .await?;
+            entries.push((META_NAME.to_string(), tag.hash()));
+            Some(tag)
+        };
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let collection: Collection = entries.into_iter().collect();
+        let temp_tag = collection.clone().store(&self.store).await?;
+        drop(added); // Data now protected by collection
+        drop(meta_tag);
+        Ok((temp_tag, collection, total_size))
+    }
+
+    async fn blob_size(&self, hash: Hash) -> Result<u64> {
+        match self.store.blobs().status(hash).await? {
+            BlobStatus::Complete { size } => Ok(size),
+            _ => anyhow::bail!("blob {} is missing from the store", hash),
+        }
+    }
+
+    /// Walks and imports `paths`, each becoming a top-level entry of the share
+    async fn import_paths(
+        &self,
+        paths: &[PathBuf],
+        naming: &EntryNaming,
+        report: &mut ImportReport,
+    ) -> Result<(Vec<(String, TempTag, u64)>, ShareMeta)> {
         let mut all_files: Vec<(String, PathBuf)> = Vec::new();
+        let mut total_bytes = 0u64;
+        let mut share_meta = ShareMeta::default();
+        let mut excluded = Excluded::default();
+        let mut names = NameCheck::default();
 
-        for path in &self.paths {
-            let path = path.canonicalize()?;
+        let roots = paths
+            .iter()
+            .map(|path| path.canonicalize())
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let namer = EntryNamer::new(naming, &roots);
+        for path in &roots {
             anyhow::ensure!(path.exists(), "path {} does not exist", path.display());
-            let root = path.parent().context("cannot get parent directory")?;
 
             // Security: don't follow symlinks by default
-            for entry in WalkDir::new(&path).follow_links(self.options.follow_symlinks) {
-                let entry = entry?;
-                if !self.options.follow_symlinks && entry.file_type().is_symlink() {
-                    tracing::warn!("skipping symlink: {}", entry.path().display());
+            let follow_links = self.options.symlinks == SymlinkMode::Follow;
+            let skip = self.options.skip_unreadable;
+            for entry in filter::walk(path, follow_links, &self.options.exclude, &mut excluded) {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        let entry = SkippedEntry::from_walk_error(&e);
+                        filter::skip_or_fail(skip, &mut report.skipped, entry)?;
+                        continue;
+                    }
+                };
+                if !follow_links && entry.file_type().is_symlink() {
+                    if self.options.symlinks == SymlinkMode::Preserve {
+                        let target = match std::fs::read_link(entry.path()) {
+                            Ok(target) => target,
+                            Err(e) => {
+                                let entry = SkippedEntry::new(entry.path(), e);
+                                filter::skip_or_fail(skip, &mut report.skipped, entry)?;
+                                continue;
+                            }
+                        };
+                        let Some(name) = namer.name(path, entry.path(), false)? else {
+                            continue;
+                        };
+                        names.insert(&name, entry.path())?;
+                        note_escaped(&name, entry.path(), &mut share_meta, report);
+                        let target = match rawname::encode(target.as_os_str()) {
+                            Some(escaped) => {
+                                share_meta.escaped_targets.insert(name.clone());
+                                escaped
+                            }
+                            None => target.to_string_lossy().into_owned(),
+                        };
+                        share_meta.symlinks.insert(name, target);
+                    } else {
+                        tracing::warn!("skipping symlink: {}", entry.path().display());
+                    }
                     continue;
                 }
 
+                if entry.file_type().is_dir() {
+                    if let Some(name) = namer.name(path, entry.path(), true)? {
+                        note_escaped(&name, entry.path(), &mut share_meta, report);
+                        share_meta.dirs.insert(name);
+                    }
+                    continue;
+                }
                 if !entry.file_type().is_file() {
+                    let reason = filter::special_file_reason(&entry.file_type());
+                    tracing::warn!("Skipping {}: {}", entry.path().display(), reason);
+                    report.skipped.push(SkippedEntry::new(entry.path(), reason));
+                    continue;
+                }
+                if let Err(e) = filter::check_readable(entry.path()) {
+                    let entry = SkippedEntry::new(entry.path(), e);
+                    filter::skip_or_fail(skip, &mut report.skipped, entry)?;
                     continue;
                 }
+                let metadata = entry.metadata();
                 let file_path = entry.into_path();
-                let relative = file_path.strip_prefix(root)?;
-                let name = canonicalized_path_to_string(relative, true)?;
+                let Some(name) = namer.name(path, &file_path, false)? else {
+                    continue;
+                };
+                let name = reserve_meta_name(name, &mut share_meta);
+                names.insert(&name, &file_path)?;
+                note_escaped(&name, &file_path, &mut share_meta, report);
+                if let Ok(metadata) = &metadata {
+                    total_bytes += metadata.len();
+                    share_meta
+                        .files
+                        .insert(name.clone(), FileMeta::from_metadata(metadata));
+                }
+                if self.options.in_place {
+                    let metadata = metadata?;
+                    report
+                        .referenced
+                        .push(FileSnapshot::new(file_path.clone(), &metadata));
+                }
                 all_files.push((name, file_path));
             }
         }
 
-        let name = if self.paths.len() == 1 {
-            self.paths[0]
-                .file_name()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_else(|| "files".to_string())
+        if excluded.entries > 0 {
+            tracing::info!(
+                "Excluded {} entries ({} bytes)",
+                excluded.entries,
+                excluded.bytes
+            );
+        }
+
+        let store = self.store.clone();
+        let import_mode = if self.options.in_place {
+            tracing::warn!("sharing files in place; they must not change until the send finishes");
+            ImportMode::TryReference
         } else {
-            format!("{} items", self.paths.len())
+            ImportMode::Copy
         };
 
-        let store = self.store.clone();
+        let files_total = all_files.len();
+        let counters = ImportCounters::default();
+        let counters_ref = &counters;
 
-        let import_results: Vec<Result<(String, TempTag, u64)>> =
-            futures::stream::StreamExt::collect(futures::stream::StreamExt::buffer_unordered(
+        let imports = futures::stream::StreamExt::collect::<Vec<Result<(String, TempTag, u64)>>>(
+            futures::stream::StreamExt::buffer_unordered(
                 futures::stream::StreamExt::map(
                     stream::iter(all_files),
                     |(file_name, file_path)| {
                         let store = store.clone();
-                        async move { Self::import_single_file(&store, file_name, file_path).await }
+                        async move {
+                            Self::import_single_file(
+                                &store,
+                                file_name,
+                                file_path,
+                                import_mode,
+                                counters_ref,
+                            )
+                            .await
+                        }
                     },
                 ),
                 PARALLEL_IMPORTS,
-            ))
-            .await;
+            ),
+        );
+        let mut imports = std::pin::pin!(imports);
+
+        // Report hashing progress until every import has finished
+        let mut ticker = tokio::time::interval(IMPORT_PROGRESS_INTERVAL);
+        let import_results = loop {
+            tokio::select! {
+                results = &mut imports => break results,
+                _ = ticker.tick() => {
+                    let current = counters.current.lock().map(|c| c.clone()).unwrap_or_default();
+                    let _ = self.progress_tx.try_send(TransferProgress::ImportProgress {
+                        id: self.id.clone(),
+                        hashed_bytes: counters.hashed_bytes.load(Ordering::Relaxed),
+                        total_bytes,
+                        files_done: counters.files_done.load(Ordering::Relaxed),
+                        files_total,
+                        current,
+                    });
+                }
+            }
+        };
 
         let mut names_and_tags: Vec<(String, TempTag, u64)> =
-            Vec::with_capacity(import_results.len());
+            Vec::with_capacity(import_results.len() + 1);
         for result in import_results {
             names_and_tags.push(result?);
         }
-        names_and_tags.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
-        let total_size: u64 = names_and_tags.iter().map(|(_, _, size)| *size).sum();
+        Ok((names_and_tags, share_meta))
+    }
+
+    /// Streams the selection through tar+zstd straight into a single blob
+    async fn import_archive(
+        &self,
+        name: String,
+        paths: Vec<PathBuf>,
+        report: &mut ImportReport,
+    ) -> Result<(TempTag, u64, Collection, String)> {
+        let archive_name = format!("{}{}", name, archive::ARCHIVE_EXT);
+        let options = self.options.clone();
+        // Skipped entries and escaped member names, filled in by the producer
+        let noted = Arc::new(Mutex::new((Vec::new(), BTreeSet::new())));
+        let producer_noted = noted.clone();
+        let (archive_tag, total_size) = self
+            .import_blocking(move |writer| {
+                let (mut skipped, mut escaped) = (Vec::new(), BTreeSet::new());
+                let result =
+                    archive::write_archive(&paths, &options, &mut skipped, &mut escaped, writer);
+                if let Ok(mut shared) = producer_noted.lock() {
+                    *shared = (skipped, escaped);
+                }
+                result
+            })
+            .await?;
+        let mut share_meta = ShareMeta::default();
+        if let Ok(mut noted) = noted.lock() {
+            let (skipped, escaped) = &mut *noted;
+            report.skipped.append(skipped);
+            share_meta.escaped.append(escaped);
+        }
+        share_meta.archives.insert(archive_name.clone());
+        self.add_labels(&mut share_meta);
+        let meta_tag = self
+            .store
+            .add_bytes(share_meta.to_bytes()?)
+            .temp_tag()
+            .await?;
+
+        let (temp_tag, collection) = self
+            .store_collection(vec![
+                (archive_name, archive_tag),
+                (META_NAME.to_string(), meta_tag),
+            ])
+            .await?;
+        Ok((temp_tag, total_size, collection, name))
+    }
+
+    /// Reads stdin to EOF; its length is unknown until then
+    async fn import_stdin(&self, name: &str) -> Result<(TempTag, u64, Collection, String)> {
+        let name = canonicalized_path_to_string(name, true)?;
+        anyhow::ensure!(!name.is_empty(), "a name is required for stdin sends");
+
+        let (tag, total_size) = self
+            .import_blocking(|mut writer| {
+                std::io::copy(&mut std::io::stdin().lock(), &mut writer)?;
+                Ok(writer)
+            })
+            .await?;
+
+        let mut share_meta = ShareMeta::default();
+        let entry_name = reserve_meta_name(name.clone(), &mut share_meta);
+        let mut entries = vec![(entry_name, tag)];
+        self.add_labels(&mut share_meta);
+        if !share_meta.is_empty() {
+            let meta_tag = self
+                .store
+                .add_bytes(share_meta.to_bytes()?)
+                .temp_tag()
+                .await?;
+            entries.push((META_NAME.to_string(), meta_tag));
+        }
+
+        let (temp_tag, collection) = self.store_collection(entries).await?;
+        Ok((temp_tag, total_size, collection, name))
+    }
+
+    async fn import_text(&self, text: &str) -> Result<(TempTag, u64, Collection, String)> {
+        let text_tag = self
+            .store
+            .add_bytes(text.as_bytes().to_vec())
+            .temp_tag()
+            .await?;
+
+        let mut share_meta = ShareMeta {
+            text: true,
+            ..Default::default()
+        };
+        self.add_labels(&mut share_meta);
+        let meta_tag = self
+            .store
+            .add_bytes(share_meta.to_bytes()?)
+            .temp_tag()
+            .await?;
+
+        let (temp_tag, collection) = self
+            .store_collection(vec![
+                (TEXT_NAME.to_string(), text_tag),
+                (META_NAME.to_string(), meta_tag),
+            ])
+            .await?;
+        Ok((
+            temp_tag,
+            text.len() as u64,
+            collection,
+            "Text snippet".to_string(),
+        ))
+    }
+
+    fn title(&self) -> Option<String> {
+        let title = self.options.title.as_deref()?;
+        meta::clean_label(title, meta::MAX_TITLE_LEN)
+    }
+
+    /// Carries the sender's title and note in the sidecar
+    fn add_labels(&self, share_meta: &mut ShareMeta) {
+        share_meta.title = self.title();
+        share_meta.note = self
+            .options
+            .note
+            .as_deref()
+            .and_then(|note| meta::clean_label(note, meta::MAX_NOTE_LEN));
+    }
+
+    async fn report_escaped(&self, escaped: Vec<EntryRename>) {
+        if escaped.is_empty() {
+            return;
+        }
+        let _ = self
+            .progress_tx
+            .send(TransferProgress::Renamed {
+                id: self.id.clone(),
+                renames: escaped,
+            })
+            .await;
+    }
+
+    async fn report_hashes(&self, root: Hash, collection: &Collection) {
+        let entries = collection
+            .iter()
+            .filter(|(name, _)| name != META_NAME)
+            .map(|(name, hash)| (name.clone(), *hash))
+            .collect();
+        let _ = self
+            .progress_tx
+            .send(TransferProgress::Hashes {
+                id: self.id.clone(),
+                root,
+                entries,
+            })
+            .await;
+    }
 
-        let (collection, tags): (Collection, Vec<_>) = names_and_tags
+    /// Runs a blocking producer whose output becomes one blob
+    async fn import_blocking(
+        &self,
+        produce: impl FnOnce(ChannelWriter) -> Result<ChannelWriter> + Send + 'static,
+    ) -> Result<(TempTag, u64)> {
+        let (tx, rx) = mpsc::channel::<std::io::Result<Bytes>>(16);
+        // The writer is dropped on return, which ends the stream
+        let producer = tokio::task::spawn_blocking(move || {
+            produce(ChannelWriter { tx, written: 0 }).map(|writer| writer.written)
+        });
+
+        let chunks = stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|chunk| (chunk, rx))
+        });
+        let tag = self.store.add_stream(chunks).await.temp_tag().await?;
+        // A failed producer closes the stream early, so its error takes precedence
+        let written = producer.await??;
+        Ok((tag, written))
+    }
+
+    async fn store_collection(
+        &self,
+        mut entries: Vec<(String, TempTag)>,
+    ) -> Result<(TempTag, Collection)> {
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let (collection, tags): (Collection, Vec<_>) = entries
             .into_iter()
-            .map(|(name, tag, _)| ((name, tag.hash()), tag))
+            .map(|(name, tag)| ((name, tag.hash()), tag))
             .unzip();
 
-        let temp_tag = collection.clone().store(&store).await?;
+        let temp_tag = collection.clone().store(&self.store).await?;
         drop(tags); // Data now protected by collection
-
-        Ok((temp_tag, total_size, collection, name))
+        Ok((temp_tag, collection))
     }
 
     async fn import_single_file(
         store: &FsStore,
         file_name: String,
         file_path: PathBuf,
+        mode: ImportMode,
+        counters: &ImportCounters,
     ) -> Result<(String, TempTag, u64)> {
+        if let Ok(mut current) = counters.current.lock() {
+            current.clone_from(&file_name);
+        }
         let import = store.add_path_with_opts(AddPathOptions {
             path: file_path.clone(),
             format: iroh_blobs::BlobFormat::Raw,
-            mode: ImportMode::Copy,
+            mode,
         });
 
         let mut item_size = 0u64;
+        let mut hashed = 0u64;
         let mut temp_tag = None;
 
         let mut stream = std::pin::pin!(import.stream().await);
@@ -377,12 +1081,22 @@ impl SendTask {
                 AddProgressItem::Size(size) => {
                     item_size = size;
                 }
+                AddProgressItem::OutboardProgress(offset) => {
+                    let offset = offset.min(item_size);
+                    counters
+                        .hashed_bytes
+                        .fetch_add(offset.saturating_sub(hashed), Ordering::Relaxed);
+                    hashed = hashed.max(offset);
+                }
                 AddProgressItem::Done(tag) => {
+                    // Small files are stored inline without outboard progress
+                    counters
+                        .hashed_bytes
+                        .fetch_add(item_size.saturating_sub(hashed), Ordering::Relaxed);
+                    counters.files_done.fetch_add(1, Ordering::Relaxed);
                     temp_tag = Some(tag);
                 }
-                AddProgressItem::CopyProgress(_)
-                | AddProgressItem::OutboardProgress(_)
-                | AddProgressItem::CopyDone => {}
+                AddProgressItem::CopyProgress(_) | AddProgressItem::CopyDone => {}
                 AddProgressItem::Error(e) => {
                     anyhow::bail!("import error for {}: {}", file_path.display(), e);
                 }
@@ -394,6 +1108,49 @@ impl SendTask {
     }
 }
 
+/// Entry names a share currently holds, without the sidecar
+fn share_entries(collection: &Collection) -> Vec<String> {
+    collection
+        .iter()
+        .map(|(name, _)| name.clone())
+        .filter(|name| name != META_NAME)
+        .collect()
+}
+
+/// Non-UTF-8 names are sent escaped rather than failing the send. Each
+/// escaped entry is reported once, not again for everything beneath it.
+fn note_escaped(name: &str, path: &Path, share_meta: &mut ShareMeta, report: &mut ImportReport) {
+    let prefixes = rawname::escaped_prefixes(name, path);
+    if prefixes.last().is_some_and(|last| last == name) {
+        tracing::warn!("Sending non-UTF-8 name {} as {}", path.display(), name);
+        report.escaped.push(EntryRename {
+            from: path.display().to_string(),
+            to: name.to_string(),
+        });
+    }
+    share_meta.escaped.extend(prefixes);
+}
+
+/// A file named like the sidecar is sent escaped, so the receiver neither
+/// drops it nor reads it as share metadata
+fn reserve_meta_name(name: String, share_meta: &mut ShareMeta) -> String {
+    match rawname::escape_reserved(&name) {
+        Some(escaped) => {
+            share_meta.escaped.insert(escaped.clone());
+            escaped
+        }
+        None => name,
+    }
+}
+
+/// The collection and every blob in it
+fn share_hashes<'a>(
+    temp_tag: &TempTag,
+    collection: &'a Collection,
+) -> impl Iterator<Item = Hash> + 'a {
+    std::iter::once(temp_tag.hash()).chain(collection.iter().map(|(_, hash)| *hash))
+}
+
 /// Path to string with forward slashes.
 /// Security: Rejects `.`, `..`, path separators in components, root dirs (if relative required).
 fn canonicalized_path_to_string(path: impl AsRef<Path>, must_be_relative: bool) -> Result<String> {
@@ -428,3 +1185,350 @@ fn canonicalized_path_to_string(path: impl AsRef<Path>, must_be_relative: bool)
     path_str.push_str(&parts.join("/"));
     Ok(path_str)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::naming::EntryLayout;
+
+    #[test]
+    fn test_file_snapshot_detects_modification() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.img");
+        std::fs::write(&path, "original").unwrap();
+
+        let snapshot = FileSnapshot::new(path.clone(), &std::fs::metadata(&path).unwrap());
+        assert!(snapshot.check().is_ok());
+
+        std::fs::write(&path, "changed!").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        assert!(snapshot.check().is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(snapshot.check().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_single_file_counts_hashed_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsStore::load(dir.path().join("store")).await.unwrap();
+        let path = dir.path().join("data.bin");
+        std::fs::write(&path, vec![7u8; 100_000]).unwrap();
+
+        let counters = ImportCounters::default();
+        let (name, _tag, size) = SendTask::import_single_file(
+            &store,
+            "data.bin".to_string(),
+            path,
+            ImportMode::Copy,
+            &counters,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(name, "data.bin");
+        assert_eq!(size, 100_000);
+        assert_eq!(counters.hashed_bytes.load(Ordering::Relaxed), 100_000);
+        assert_eq!(counters.files_done.load(Ordering::Relaxed), 1);
+        assert_eq!(*counters.current.lock().unwrap(), "data.bin");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_import_skips_special_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let share = dir.path().join("share");
+        std::fs::create_dir_all(&share).unwrap();
+        std::fs::write(share.join("a.txt"), "a").unwrap();
+        let _socket = std::os::unix::net::UnixListener::bind(share.join("app.sock")).unwrap();
+
+        let store = Arc::new(FsStore::load(dir.path().join("store")).await.unwrap());
+        let (progress_tx, _progress_rx) = mpsc::channel(64);
+        let (task, _updater) = SendTask::new(
+            "test",
+            SendSource::Paths(vec![share]),
+            store,
+            progress_tx,
+            SendOptions::default(),
+            CancellationToken::new(),
+        );
+
+        let mut report = ImportReport::default();
+        let (_tag, size, collection, _name) = task.import_files(&mut report).await.unwrap();
+
+        assert_eq!(size, 1);
+        assert!(collection.iter().any(|(name, _)| name == "share/a.txt"));
+        assert_eq!(report.skipped.len(), 1);
+        assert!(report.skipped[0].path.ends_with("app.sock"));
+        assert_eq!(report.skipped[0].reason, "special file (socket)");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_non_utf8_symlink_targets_are_escaped() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let share = dir.path().join("share");
+        std::fs::create_dir_all(&share).unwrap();
+        let target = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        std::os::unix::fs::symlink(target, share.join("link")).unwrap();
+
+        let store = Arc::new(FsStore::load(dir.path().join("store")).await.unwrap());
+        let (progress_tx, _progress_rx) = mpsc::channel(64);
+        let options = SendOptions {
+            symlinks: SymlinkMode::Preserve,
+            ..Default::default()
+        };
+        let (task, _updater) = SendTask::new(
+            "test",
+            SendSource::Paths(vec![share]),
+            store,
+            progress_tx,
+            options,
+            CancellationToken::new(),
+        );
+
+        let mut report = ImportReport::default();
+        let (_tag, _size, collection, _name) = task.import_files(&mut report).await.unwrap();
+        let (_, meta_hash) = collection.iter().find(|(n, _)| n == META_NAME).unwrap();
+        let meta = ShareMeta::from_bytes(&task.store.get_bytes(*meta_hash).await.unwrap()).unwrap();
+        assert_eq!(meta.symlinks["share/link"], "caf%E9.txt");
+        assert!(meta.escaped_targets.contains("share/link"));
+    }
+
+    #[tokio::test]
+    async fn test_file_named_like_sidecar_survives() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(META_NAME);
+        std::fs::write(&path, "{\"title\":\"not metadata\"}").unwrap();
+
+        let store = Arc::new(FsStore::load(dir.path().join("store")).await.unwrap());
+        let (progress_tx, _progress_rx) = mpsc::channel(64);
+        let (task, _updater) = SendTask::new(
+            "test",
+            SendSource::Paths(vec![path]),
+            store,
+            progress_tx,
+            SendOptions::default(),
+            CancellationToken::new(),
+        );
+
+        let mut report = ImportReport::default();
+        let (_tag, _size, collection, _name) = task.import_files(&mut report).await.unwrap();
+        assert_eq!(collection.len(), 2);
+
+        // What a receiver does: split off the sidecar, then restore the name
+        let (entries, _sizes, meta_hash) = meta::split_collection(collection, &[0, 0]);
+        let meta = ShareMeta::from_bytes(&task.store.get_bytes(meta_hash.unwrap()).await.unwrap())
+            .unwrap();
+        assert_eq!(meta.title, None);
+        let (name, hash) = entries.iter().next().unwrap();
+        assert!(meta.escaped.contains(name));
+        assert_eq!(rawname::decode_os(name).unwrap(), META_NAME);
+        assert_eq!(
+            task.store.get_bytes(*hash).await.unwrap(),
+            "{\"title\":\"not metadata\"}".as_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_title_and_note_go_into_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        std::fs::write(&path, "log").unwrap();
+
+        let store = Arc::new(FsStore::load(dir.path().join("store")).await.unwrap());
+        let (progress_tx, _progress_rx) = mpsc::channel(64);
+        let options = SendOptions {
+            title: Some("  Q3 logs ".to_string()),
+            note: Some("ignore the tmp folder".to_string()),
+            ..Default::default()
+        };
+        let (task, _updater) = SendTask::new(
+            "test",
+            SendSource::Paths(vec![path]),
+            store,
+            progress_tx,
+            options,
+            CancellationToken::new(),
+        );
+
+        let mut report = ImportReport::default();
+        let (_tag, _size, collection, _name) = task.import_files(&mut report).await.unwrap();
+        let (_, meta_hash) = collection.iter().find(|(n, _)| n == META_NAME).unwrap();
+        let meta = ShareMeta::from_bytes(&task.store.get_bytes(*meta_hash).await.unwrap()).unwrap();
+        assert_eq!(meta.title.as_deref(), Some("Q3 logs"));
+        assert_eq!(meta.note.as_deref(), Some("ignore the tmp folder"));
+        assert_eq!(task.title().as_deref(), Some("Q3 logs"));
+    }
+
+    #[tokio::test]
+    async fn test_flattened_names_must_be_unique() {
+        let dir = tempfile::tempdir().unwrap();
+        let share = dir.path().join("share");
+        std::fs::create_dir_all(share.join("a")).unwrap();
+        std::fs::create_dir_all(share.join("b")).unwrap();
+        std::fs::write(share.join("a/notes.txt"), "a").unwrap();
+        std::fs::write(share.join("b/notes.txt"), "b").unwrap();
+
+        let store = Arc::new(FsStore::load(dir.path().join("store")).await.unwrap());
+        let mut naming = EntryNaming {
+            layout: EntryLayout::Flatten,
+            prefix: Some("q3".to_string()),
+            ..Default::default()
+        };
+        let task = |naming: EntryNaming| {
+            let (progress_tx, _progress_rx) = mpsc::channel(64);
+            let options = SendOptions {
+                naming,
+                ..Default::default()
+            };
+            SendTask::new(
+                "test",
+                SendSource::Paths(vec![share.clone()]),
+                store.clone(),
+                progress_tx,
+                options,
+                CancellationToken::new(),
+            )
+            .0
+        };
+
+        let mut report = ImportReport::default();
+        let err = task(naming.clone())
+            .import_files(&mut report)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("would both be sent as q3/notes.txt"),
+            "{}",
+            err
+        );
+
+        let renamed = share.join("b/notes.txt").canonicalize().unwrap();
+        naming.renames.insert(renamed, "notes-b.txt".to_string());
+        let (_tag, _size, collection, _name) =
+            task(naming).import_files(&mut report).await.unwrap();
+        let mut names = share_entries(&collection);
+        names.sort();
+        assert_eq!(names, vec!["q3/notes-b.txt", "q3/notes.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_share_adds_and_removes_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let share = dir.path().join("share");
+        std::fs::create_dir_all(share.join("old")).unwrap();
+        std::fs::write(share.join("a.txt"), "aaaa").unwrap();
+        std::fs::write(share.join("old/b.txt"), "bb").unwrap();
+        let extra = dir.path().join("extra.txt");
+        std::fs::write(&extra, "extra").unwrap();
+
+        let store = Arc::new(FsStore::load(dir.path().join("store")).await.unwrap());
+        let (progress_tx, _progress_rx) = mpsc::channel(64);
+        let (task, _updater) = SendTask::new(
+            "test",
+            SendSource::Paths(vec![share]),
+            store,
+            progress_tx,
+            SendOptions::default(),
+            CancellationToken::new(),
+        );
+
+        let mut report = ImportReport::default();
+        let (tag, size, collection, _name) = task.import_files(&mut report).await.unwrap();
+        assert_eq!(size, 6);
+
+        let update = ShareUpdate {
+            add: vec![extra],
+            remove: vec!["share/old".to_string()],
+        };
+        let (new_tag, updated, new_size) = task
+            .update_share(&collection, update, &mut report)
+            .await
+            .unwrap();
+
+        assert_ne!(new_tag.hash(), tag.hash());
+        assert_eq!(share_entries(&updated), vec!["extra.txt", "share/a.txt"]);
+        assert_eq!(new_size, 9);
+        let (_, meta_hash) = updated.iter().find(|(n, _)| n == META_NAME).unwrap();
+        let meta = ShareMeta::from_bytes(&task.store.get_bytes(*meta_hash).await.unwrap()).unwrap();
+        assert!(meta.file("extra.txt").is_some());
+        assert!(!meta.dirs.contains("share/old"));
+
+        // An archive entry taken out of the share takes its flag with it
+        let mut archived = meta.clone();
+        archived.archives.insert("bundle.tar.zst".to_string());
+        let archived_tag = task
+            .store
+            .add_bytes(archived.to_bytes().unwrap())
+            .temp_tag()
+            .await
+            .unwrap();
+        let bundle = task
+            .store
+            .add_bytes(b"zst".to_vec())
+            .temp_tag()
+            .await
+            .unwrap();
+        let with_archive: Collection = updated
+            .iter()
+            .filter(|(name, _)| name != META_NAME)
+            .map(|(name, hash)| (name.clone(), *hash))
+            .chain([
+                ("bundle.tar.zst".to_string(), bundle.hash()),
+                (META_NAME.to_string(), archived_tag.hash()),
+            ])
+            .collect();
+        let update = ShareUpdate {
+            remove: vec!["bundle.tar.zst".to_string()],
+            ..Default::default()
+        };
+        let (_tag, pruned, _size) = task
+            .update_share(&with_archive, update, &mut report)
+            .await
+            .unwrap();
+        let (_, meta_hash) = pruned.iter().find(|(n, _)| n == META_NAME).unwrap();
+        let meta = ShareMeta::from_bytes(&task.store.get_bytes(*meta_hash).await.unwrap()).unwrap();
+        assert!(meta.archives.is_empty());
+
+        let empty = ShareUpdate {
+            remove: vec!["share".to_string(), "extra.txt".to_string()],
+            ..Default::default()
+        };
+        assert!(task
+            .update_share(&updated, empty, &mut report)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_blocking_finishes_when_producer_returns() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(FsStore::load(dir.path()).await.unwrap());
+        let (progress_tx, _progress_rx) = mpsc::channel(8);
+        let (task, _updater) = SendTask::new(
+            "test",
+            SendSource::Paths(vec![]),
+            store,
+            progress_tx,
+            SendOptions::default(),
+            CancellationToken::new(),
+        );
+
+        let import = task.import_blocking(|mut writer| {
+            writer.write_all(b"hello")?;
+            Ok(writer)
+        });
+        let (tag, written) = tokio::time::timeout(std::time::Duration::from_secs(10), import)
+            .await
+            .expect("import should not hang")
+            .unwrap();
+
+        assert_eq!(written, 5);
+        assert_eq!(tag.hash(), iroh_blobs::Hash::new(b"hello"));
+    }
+}
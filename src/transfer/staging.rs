@@ -0,0 +1,491 @@
+//! Staging area for receives
+//!
+//! Files are exported into a hidden directory inside the output directory, so
+//! they are on the same filesystem, and renamed into place only once the whole
+//! collection is there. A failed or cancelled receive leaves nothing behind
+//! but the staging directory, which is removed when the `Staging` is dropped
+//! or, after a crash, by `remove_stale` on the next start. Receives into a
+//! subfolder stage in the directory above it, so that scan finds them too.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use walkdir::WalkDir;
+
+const STAGING_PREFIX: &str = ".tuit-staging-";
+/// Held locked while the staging directory is in use
+const LOCK_NAME: &str = ".lock";
+/// Archive entries are unpacked below this, mirroring the output directory
+const TREE_NAME: &str = "tree";
+/// Added to a file being copied across volumes until it is complete
+const PARTIAL_SUFFIX: &str = ".tuit-partial";
+/// Added, with a unique part, to a file being overwritten until the commit
+/// has gone through
+const REPLACED_SUFFIX: &str = ".tuit-replaced-";
+/// A staging directory is created a moment before its lock file; one without
+/// a lock is only stale once it is older than this
+const LOCKLESS_GRACE: Duration = Duration::from_secs(60);
+
+/// What to do when the final path was taken while the receive ran
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnExisting {
+    Overwrite,
+    Rename,
+    Skip,
+}
+
+#[derive(Debug)]
+struct StagedEntry {
+    staged: PathBuf,
+    target: PathBuf,
+    on_existing: OnExisting,
+    expected_size: Option<u64>,
+}
+
+/// Where a committed file ended up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placed {
+    /// Path the file was staged for
+    pub target: PathBuf,
+    /// Final path, which differs when the target was taken
+    pub path: PathBuf,
+}
+
+#[derive(Debug)]
+pub struct Staging {
+    dir: PathBuf,
+    lock: Option<File>,
+    entries: Vec<StagedEntry>,
+    /// Directories to create in the output, for empty archive directories
+    dirs: Vec<PathBuf>,
+}
+
+impl Staging {
+    pub fn create(output_dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(output_dir)
+            .with_context(|| format!("failed to create {}", output_dir.display()))?;
+        let dir = output_dir.join(format!(
+            "{}{}",
+            STAGING_PREFIX,
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::create_dir(&dir)
+            .with_context(|| format!("failed to create staging area {}", dir.display()))?;
+        let lock = File::create(dir.join(LOCK_NAME))?;
+        lock.try_lock_exclusive()?;
+        Ok(Self {
+            dir,
+            lock: Some(lock),
+            entries: Vec::new(),
+            dirs: Vec::new(),
+        })
+    }
+
+    /// A fresh path in the staging area for the file that will end up at `target`
+    pub fn stage(
+        &mut self,
+        target: PathBuf,
+        on_existing: OnExisting,
+        expected_size: Option<u64>,
+    ) -> PathBuf {
+        let staged = self.dir.join(self.entries.len().to_string());
+        self.entries.push(StagedEntry {
+            staged: staged.clone(),
+            target,
+            on_existing,
+            expected_size,
+        });
+        staged
+    }
+
+    /// Where archive entries are unpacked; see `stage_tree`
+    pub fn tree(&self) -> PathBuf {
+        self.dir.join(TREE_NAME)
+    }
+
+    /// Stages everything unpacked below `tree()` for the same relative path
+    /// under `output_dir`. Existing files are kept and the new one renamed,
+    /// existing symlinks win.
+    pub fn stage_tree(&mut self, output_dir: &Path) -> Result<()> {
+        let tree = self.tree();
+        if !tree.exists() {
+            return Ok(());
+        }
+        for entry in WalkDir::new(&tree).min_depth(1) {
+            let entry = entry?;
+            let target = output_dir.join(entry.path().strip_prefix(&tree)?);
+            let file_type = entry.file_type();
+            if file_type.is_dir() {
+                self.dirs.push(target);
+                continue;
+            }
+            let on_existing = if file_type.is_symlink() {
+                OnExisting::Skip
+            } else {
+                OnExisting::Rename
+            };
+            self.entries.push(StagedEntry {
+                staged: entry.into_path(),
+                target,
+                on_existing,
+                expected_size: None,
+            });
+        }
+        Ok(())
+    }
+
+    /// Syncs and checks every staged file, then moves them all into place.
+    /// Nothing is moved unless every file checks out, and a move that fails
+    /// rolls back the ones before it. Returns where each file went; files
+    /// skipped for an existing path are left out.
+    pub fn commit(mut self, available_path: impl Fn(&Path) -> PathBuf) -> Result<Vec<Placed>> {
+        for entry in &self.entries {
+            let metadata = std::fs::symlink_metadata(&entry.staged)
+                .with_context(|| format!("staged file missing: {}", entry.target.display()))?;
+            if metadata.is_file() {
+                if let Some(expected) = entry.expected_size {
+                    anyhow::ensure!(
+                        metadata.len() == expected,
+                        "{} is {} bytes, expected {}",
+                        entry.target.display(),
+                        metadata.len(),
+                        expected
+                    );
+                }
+                File::open(&entry.staged)?.sync_all()?;
+            }
+        }
+
+        for dir in &self.dirs {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut placed = Vec::with_capacity(self.entries.len());
+        let mut replaced = Vec::new();
+        let moved = self.place_all(&available_path, &mut placed, &mut replaced);
+        if let Err(e) = moved {
+            roll_back(&placed, &replaced);
+            return Err(e);
+        }
+        for (backup, _) in &replaced {
+            if let Err(e) = std::fs::remove_file(backup) {
+                tracing::warn!("Failed to remove {}: {}", backup.display(), e);
+            }
+        }
+
+        sync_parents(placed.iter().map(|placed| placed.path.as_path()));
+        Ok(placed)
+    }
+
+    /// Moves the staged files into place, recording each one moved and each
+    /// overwritten file set aside, so a failure part way can be rolled back
+    fn place_all(
+        &mut self,
+        available_path: impl Fn(&Path) -> PathBuf,
+        placed: &mut Vec<Placed>,
+        replaced: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<()> {
+        for entry in std::mem::take(&mut self.entries) {
+            if let Some(parent) = entry.target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let existing = std::fs::symlink_metadata(&entry.target).ok();
+            let target = match (&existing, entry.on_existing) {
+                (None, _) | (Some(_), OnExisting::Overwrite) => entry.target.clone(),
+                (Some(_), OnExisting::Rename) => available_path(&entry.target),
+                (Some(_), OnExisting::Skip) => {
+                    tracing::warn!("Skipping, path exists: {}", entry.target.display());
+                    continue;
+                }
+            };
+            // Directories aren't set aside, the move onto them fails as before
+            if existing.is_some_and(|m| !m.is_dir()) && target == entry.target {
+                let mut backup = target.as_os_str().to_os_string();
+                backup.push(format!(
+                    "{}{}",
+                    REPLACED_SUFFIX,
+                    uuid::Uuid::new_v4().simple()
+                ));
+                let backup = PathBuf::from(backup);
+                std::fs::rename(&target, &backup).with_context(|| {
+                    format!("failed to set aside {} to replace it", target.display())
+                })?;
+                replaced.push((backup, target.clone()));
+            }
+            move_file(&entry.staged, &target)
+                .with_context(|| format!("failed to move {} into place", target.display()))?;
+            placed.push(Placed {
+                target: entry.target,
+                path: target,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Undoes a commit that failed part way: the files moved so far are removed
+/// and the ones they replaced put back, so the output is as before
+fn roll_back(placed: &[Placed], replaced: &[(PathBuf, PathBuf)]) {
+    for placed in placed.iter().rev() {
+        if let Err(e) = std::fs::remove_file(&placed.path) {
+            tracing::warn!("Failed to roll back {}: {}", placed.path.display(), e);
+        }
+    }
+    for (backup, target) in replaced.iter().rev() {
+        if let Err(e) = std::fs::rename(backup, target) {
+            tracing::warn!(
+                "Failed to restore {} from {}: {}",
+                target.display(),
+                backup.display(),
+                e
+            );
+        }
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        // Unlock before removing; Windows won't delete an open file
+        drop(self.lock.take());
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("Failed to remove {}: {}", self.dir.display(), e);
+            }
+        }
+    }
+}
+
+/// Removes staging directories in `output_dir` left by receives that crashed.
+/// Ones still locked by a running receive, possibly in another instance, stay,
+/// as do fresh ones not locked yet.
+pub fn remove_stale(output_dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(output_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let is_staging = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.starts_with(STAGING_PREFIX));
+        if !is_staging || !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+
+        let path = entry.path();
+        let in_use = match File::open(path.join(LOCK_NAME)) {
+            Ok(lock) => lock.try_lock_exclusive().is_err(),
+            // Possibly another receive between creating the directory and
+            // locking it
+            Err(_) => entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|modified| modified.elapsed().unwrap_or_default() < LOCKLESS_GRACE),
+        };
+        if in_use {
+            continue;
+        }
+        tracing::info!("Removing stale staging area {}", path.display());
+        if let Err(e) = std::fs::remove_dir_all(&path) {
+            tracing::warn!("Failed to remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Renames, or copies for targets on another volume such as a routed
+/// folder; the copy goes to a temporary name first so it appears whole
+fn move_file(staged: &Path, target: &Path) -> std::io::Result<()> {
+    match std::fs::rename(staged, target) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            let mut partial = target.as_os_str().to_os_string();
+            partial.push(PARTIAL_SUFFIX);
+            let partial = PathBuf::from(partial);
+            let copied = std::fs::copy(staged, &partial)
+                .and_then(|_| {
+                    let copy = File::open(&partial)?;
+                    // Copies don't keep the modification time restored on staging
+                    if let Ok(modified) = std::fs::metadata(staged).and_then(|m| m.modified()) {
+                        let _ = copy.set_modified(modified);
+                    }
+                    copy.sync_all()
+                })
+                .and_then(|_| std::fs::rename(&partial, target));
+            if copied.is_err() {
+                let _ = std::fs::remove_file(&partial);
+            }
+            copied?;
+            std::fs::remove_file(staged)
+        }
+        result => result,
+    }
+}
+
+/// Makes the renames durable; best effort, as not every platform supports it
+fn sync_parents<'a>(paths: impl Iterator<Item = &'a Path>) {
+    #[cfg(unix)]
+    {
+        let mut parents: Vec<&Path> = paths.filter_map(|p| p.parent()).collect();
+        parents.sort();
+        parents.dedup();
+        for parent in parents {
+            if let Err(e) = File::open(parent).and_then(|dir| dir.sync_all()) {
+                tracing::debug!("Failed to sync {}: {}", parent.display(), e);
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = paths;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn staging_dirs(dir: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.to_string_lossy().contains(STAGING_PREFIX))
+            .collect()
+    }
+
+    #[test]
+    fn test_commit_moves_files_into_place() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "old").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "old").unwrap();
+
+        let mut staging = Staging::create(dir.path()).unwrap();
+        let a = staging.stage(dir.path().join("a.txt"), OnExisting::Overwrite, Some(3));
+        let b = staging.stage(dir.path().join("b.txt"), OnExisting::Rename, Some(3));
+        let c = staging.stage(dir.path().join("sub/c.txt"), OnExisting::Rename, Some(3));
+        for path in [&a, &b, &c] {
+            std::fs::write(path, "new").unwrap();
+        }
+        // Nothing is visible in the output until the commit
+        assert!(!dir.path().join("sub").exists());
+
+        let placed = staging.commit(|p| p.with_file_name("b (1).txt")).unwrap();
+        assert_eq!(placed.len(), 3);
+        assert_eq!(placed[1].target, dir.path().join("b.txt"));
+        assert_eq!(placed[1].path, dir.path().join("b (1).txt"));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("b.txt")).unwrap(),
+            "old"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("b (1).txt")).unwrap(),
+            "new"
+        );
+        assert!(dir.path().join("sub/c.txt").exists());
+        assert!(staging_dirs(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_failed_receive_leaves_output_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut staging = Staging::create(dir.path()).unwrap();
+        let a = staging.stage(dir.path().join("a.txt"), OnExisting::Rename, Some(10));
+        std::fs::write(&a, "short").unwrap();
+
+        let err = staging.commit(Path::to_path_buf).unwrap_err();
+        assert!(err.to_string().contains("expected 10"));
+        assert!(!dir.path().join("a.txt").exists());
+        assert!(staging_dirs(dir.path()).is_empty());
+
+        // Dropping without committing, as on cancel or an early error
+        let mut staging = Staging::create(dir.path()).unwrap();
+        std::fs::write(
+            staging.stage(dir.path().join("b.txt"), OnExisting::Rename, None),
+            "b",
+        )
+        .unwrap();
+        drop(staging);
+        assert!(std::fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_failed_commit_rolls_back_moved_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "old").unwrap();
+        // A file where the last entry needs a directory
+        std::fs::write(dir.path().join("sub"), "file").unwrap();
+
+        let mut staging = Staging::create(dir.path()).unwrap();
+        let a = staging.stage(dir.path().join("a.txt"), OnExisting::Overwrite, Some(3));
+        let b = staging.stage(dir.path().join("b.txt"), OnExisting::Rename, Some(3));
+        let c = staging.stage(dir.path().join("sub/c.txt"), OnExisting::Rename, Some(3));
+        for path in [&a, &b, &c] {
+            std::fs::write(path, "new").unwrap();
+        }
+
+        assert!(staging.commit(Path::to_path_buf).is_err());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "old"
+        );
+        assert!(!dir.path().join("b.txt").exists());
+        let mut left: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["a.txt", "sub"]);
+    }
+
+    #[test]
+    fn test_stage_tree_mirrors_archive_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out");
+        let mut staging = Staging::create(&out).unwrap();
+        std::fs::create_dir_all(staging.tree().join("proj/empty")).unwrap();
+        std::fs::write(staging.tree().join("proj/main.rs"), "fn main() {}").unwrap();
+
+        staging.stage_tree(&out).unwrap();
+        staging.commit(Path::to_path_buf).unwrap();
+        assert!(out.join("proj/main.rs").is_file());
+        assert!(out.join("proj/empty").is_dir());
+    }
+
+    #[test]
+    fn test_remove_stale_skips_locked_staging() {
+        let dir = tempfile::tempdir().unwrap();
+        let live = Staging::create(dir.path()).unwrap();
+        let stale = dir.path().join(format!("{}crashed", STAGING_PREFIX));
+        std::fs::create_dir_all(&stale).unwrap();
+        std::fs::write(stale.join("0"), "partial").unwrap();
+        // Left unlocked by the crash
+        std::fs::write(stale.join(LOCK_NAME), "").unwrap();
+
+        remove_stale(dir.path());
+        assert!(!stale.exists());
+        assert!(live.dir.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_remove_stale_spares_staging_being_created() {
+        let dir = tempfile::tempdir().unwrap();
+        // Another receive has created its directory but not yet its lock
+        let creating = dir.path().join(format!("{}creating", STAGING_PREFIX));
+        std::fs::create_dir_all(&creating).unwrap();
+        remove_stale(dir.path());
+        assert!(creating.exists());
+
+        // One that never got its lock is removed once it is old
+        File::open(&creating)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - 2 * LOCKLESS_GRACE)
+            .unwrap();
+        remove_stale(dir.path());
+        assert!(!creating.exists());
+    }
+}
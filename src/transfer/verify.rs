@@ -0,0 +1,379 @@
+//! Checking received files against the hashes they were sent with
+//!
+//! Once a receive is in place, every exported file is hashed again and
+//! compared with its collection hash. That catches files changed on disk
+//! after the export, which store-referencing exports and busy folders make
+//! possible. Files that pass can be listed in checksum manifests that
+//! `b3sum --check` and `sha256sum --check` understand. Archive contents have
+//! no hash of their own in the collection and are not checked.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Which checksum manifests a receive writes next to its files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ManifestMode {
+    #[default]
+    Off,
+    Blake3,
+    /// SHA-256 as well as BLAKE3
+    Sha256,
+}
+
+impl ManifestMode {
+    /// Config name; unknown names fall back to `Off`
+    pub fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "blake3" => Self::Blake3,
+            "sha256" => Self::Sha256,
+            _ => Self::Off,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Off => Self::Blake3,
+            Self::Blake3 => Self::Sha256,
+            Self::Sha256 => Self::Off,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Off => "no manifest",
+            Self::Blake3 => "BLAKE3 manifest",
+            Self::Sha256 => "BLAKE3 + SHA-256 manifests",
+        }
+    }
+}
+
+/// A file a receive saved, with the hash it was sent under
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedFile {
+    pub name: String,
+    pub hash: Hash,
+    pub path: PathBuf,
+}
+
+/// Outcome of re-hashing a receive's files
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub checked: usize,
+    /// Entry names that didn't match, with the reason for each
+    pub failed: Vec<(String, String)>,
+    /// Manifests written next to the files
+    pub manifests: Vec<PathBuf>,
+    /// Why the manifests couldn't be written
+    pub manifest_error: Option<String>,
+}
+
+impl VerifyReport {
+    pub fn passed(&self) -> usize {
+        self.checked - self.failed.len()
+    }
+
+    /// Whether the entry of that full name failed
+    pub fn file_failed(&self, entry_name: &str) -> bool {
+        self.failed.iter().any(|(name, _)| name == entry_name)
+    }
+}
+
+struct Checked<'a> {
+    file: &'a ExportedFile,
+    sha256: Option<String>,
+}
+
+/// Hashes every file again and writes the manifests `mode` asks for into
+/// `dir`, named `<stem>.b3` and `<stem>.sha256` or, when taken, whatever
+/// `available_path` picks instead. Blocking; `keep_going` is asked before
+/// each file, and unchecked files are left out of the report.
+pub fn verify(
+    files: &[ExportedFile],
+    mode: ManifestMode,
+    dir: &Path,
+    stem: &str,
+    available_path: impl Fn(&Path) -> PathBuf,
+    keep_going: impl Fn() -> bool,
+) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    let mut passed = Vec::with_capacity(files.len());
+    for file in files {
+        if !keep_going() {
+            break;
+        }
+        report.checked += 1;
+        match hash_file(&file.path, mode == ManifestMode::Sha256) {
+            Ok((hash, sha256)) if hash == file.hash => passed.push(Checked { file, sha256 }),
+            Ok(_) => report
+                .failed
+                .push((file.name.clone(), "content changed".to_string())),
+            Err(e) => report.failed.push((file.name.clone(), e.to_string())),
+        }
+    }
+    for (name, reason) in &report.failed {
+        tracing::warn!("Verification failed for {}: {}", name, reason);
+    }
+
+    if mode != ManifestMode::Off && !passed.is_empty() {
+        match write_manifests(&passed, mode, dir, stem, available_path) {
+            Ok(manifests) => report.manifests = manifests,
+            Err(e) => {
+                tracing::warn!("Failed to write checksum manifest: {:#}", e);
+                report.manifest_error = Some(format!("{:#}", e));
+            }
+        }
+    }
+    report
+}
+
+fn write_manifests(
+    passed: &[Checked],
+    mode: ManifestMode,
+    dir: &Path,
+    stem: &str,
+    available_path: impl Fn(&Path) -> PathBuf,
+) -> Result<Vec<PathBuf>> {
+    let mut manifests = Vec::new();
+
+    let b3 = passed
+        .iter()
+        .map(|checked| manifest_line(&checked.file.hash.to_hex(), dir, &checked.file.path));
+    let path = available_path(&dir.join(format!("{}.b3", stem)));
+    manifests.push(write_manifest(path, b3)?);
+
+    if mode == ManifestMode::Sha256 {
+        let sha256 = passed.iter().filter_map(|checked| {
+            let hex = checked.sha256.as_deref()?;
+            Some(manifest_line(hex, dir, &checked.file.path))
+        });
+        let path = available_path(&dir.join(format!("{}.sha256", stem)));
+        manifests.push(write_manifest(path, sha256)?);
+    }
+    Ok(manifests)
+}
+
+/// Never replaces a file; one that appeared since the name was picked fails
+fn write_manifest(path: PathBuf, lines: impl Iterator<Item = Vec<u8>>) -> Result<PathBuf> {
+    let mut file =
+        File::create_new(&path).with_context(|| format!("failed to create {}", path.display()))?;
+    for line in lines {
+        file.write_all(&line)?;
+        file.write_all(b"\n")?;
+    }
+    file.sync_all()?;
+    Ok(path)
+}
+
+/// `<hex>  <path>`, with the path relative to the manifest where possible.
+/// Names with a backslash or line break are escaped and the line starts with
+/// a backslash, as the coreutils tools expect. Names go in as raw bytes, so
+/// non-UTF-8 ones still match the file on `--check`.
+fn manifest_line(hex: &str, dir: &Path, path: &Path) -> Vec<u8> {
+    let name = name_bytes(path.strip_prefix(dir).unwrap_or(path));
+    let needs_escape = name.iter().any(|b| matches!(b, b'\\' | b'\n' | b'\r'));
+    let mut line = Vec::with_capacity(hex.len() + name.len() + 3);
+    if needs_escape {
+        line.push(b'\\');
+    }
+    line.extend_from_slice(hex.as_bytes());
+    line.extend_from_slice(b"  ");
+    for &byte in name.iter() {
+        match byte {
+            b'\\' if needs_escape => line.extend_from_slice(b"\\\\"),
+            b'\n' => line.extend_from_slice(b"\\n"),
+            b'\r' => line.extend_from_slice(b"\\r"),
+            _ => line.push(byte),
+        }
+    }
+    line
+}
+
+fn name_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        std::borrow::Cow::Borrowed(path.as_os_str().as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        std::borrow::Cow::Owned(path.to_string_lossy().into_owned().into_bytes())
+    }
+}
+
+/// BLAKE3 of a file, as iroh-blobs hashes it when the file is a single blob.
+/// Blocking.
+pub fn blake3_file(path: &Path) -> std::io::Result<Hash> {
+    hash_file(path, false).map(|(hash, _)| hash)
+}
+
+/// BLAKE3 and, when asked, SHA-256 of a file in one read
+fn hash_file(path: &Path, sha256: bool) -> std::io::Result<(Hash, Option<String>)> {
+    let mut file = File::open(path)?;
+    let mut blake3 = blake3::Hasher::new();
+    let mut sha = sha256.then(Sha256::new);
+    let mut buf = vec![0u8; 256 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        blake3.update(&buf[..read]);
+        if let Some(sha) = sha.as_mut() {
+            sha.update(&buf[..read]);
+        }
+    }
+    Ok((
+        blake3.finalize().into(),
+        sha.map(|sha| format!("{:x}", sha.finalize())),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exported(dir: &Path, name: &str, sent: &[u8], on_disk: &[u8]) -> ExportedFile {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, on_disk).unwrap();
+        ExportedFile {
+            name: name.to_string(),
+            hash: Hash::new(sent),
+            path,
+        }
+    }
+
+    #[test]
+    fn test_verify_reports_changed_and_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = exported(dir.path(), "docs/good.txt", b"hello", b"hello");
+        let changed = exported(dir.path(), "changed.txt", b"hello", b"HELLO");
+        let mut missing = exported(dir.path(), "missing.txt", b"x", b"x");
+        missing.path = dir.path().join("gone.txt");
+
+        let report = verify(
+            &[good, changed, missing],
+            ManifestMode::Off,
+            dir.path(),
+            "checksums",
+            Path::to_path_buf,
+            || true,
+        );
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.passed(), 1);
+        assert_eq!(report.failed[0].0, "changed.txt");
+        assert_eq!(report.failed[0].1, "content changed");
+        assert_eq!(report.failed[1].0, "missing.txt");
+        assert!(report.file_failed("changed.txt"));
+        assert!(!report.file_failed("docs/good.txt"));
+        // Full names only, not another entry with the same base name
+        assert!(!report.file_failed("docs/changed.txt"));
+        assert!(report.manifests.is_empty());
+    }
+
+    #[test]
+    fn test_manifests_list_passing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let good = exported(dir.path(), "docs/good.txt", b"hello", b"hello");
+        let changed = exported(dir.path(), "changed.txt", b"hello", b"HELLO");
+        let odd = exported(dir.path(), "back\\slash", b"odd", b"odd");
+        // A user file by the manifest's name is left alone
+        std::fs::write(dir.path().join("checksums.b3"), "mine").unwrap();
+
+        let report = verify(
+            &[good.clone(), changed, odd.clone()],
+            ManifestMode::Sha256,
+            dir.path(),
+            "checksums",
+            |path| {
+                if path.exists() {
+                    path.with_file_name("checksums (1).b3")
+                } else {
+                    path.to_path_buf()
+                }
+            },
+            || true,
+        );
+        assert_eq!(report.manifests.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("checksums.b3")).unwrap(),
+            "mine"
+        );
+
+        let b3 = std::fs::read_to_string(dir.path().join("checksums (1).b3")).unwrap();
+        assert_eq!(
+            b3,
+            format!(
+                "{}  docs/good.txt\n\\{}  back\\\\slash\n",
+                good.hash.to_hex(),
+                odd.hash.to_hex()
+            )
+        );
+        let sha256 = std::fs::read_to_string(dir.path().join("checksums.sha256")).unwrap();
+        assert!(sha256.starts_with(
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  docs/good.txt\n"
+        ));
+        assert!(!sha256.contains("changed.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_manifest_keeps_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let raw = std::ffi::OsStr::from_bytes(b"caf\xe9.txt");
+        std::fs::write(dir.path().join(raw), "menu").unwrap();
+        let file = ExportedFile {
+            name: "caf%E9.txt".to_string(),
+            hash: Hash::new(b"menu"),
+            path: dir.path().join(raw),
+        };
+
+        verify(
+            std::slice::from_ref(&file),
+            ManifestMode::Blake3,
+            dir.path(),
+            "checksums",
+            Path::to_path_buf,
+            || true,
+        );
+        let b3 = std::fs::read(dir.path().join("checksums.b3")).unwrap();
+        assert_eq!(
+            b3,
+            [file.hash.to_hex().as_bytes(), b"  caf\xe9.txt\n"].concat()
+        );
+    }
+
+    #[test]
+    fn test_manifest_never_overwrites() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checksums.b3");
+        std::fs::write(&path, "mine").unwrap();
+        assert!(write_manifest(path.clone(), std::iter::once(b"line".to_vec())).is_err());
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "mine");
+    }
+
+    #[test]
+    fn test_blake3_file_matches_blob_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        let data = vec![7u8; 300_000];
+        std::fs::write(&path, &data).unwrap();
+
+        assert_eq!(blake3_file(&path).unwrap(), Hash::new(&data));
+    }
+
+    #[test]
+    fn test_manifest_mode_names() {
+        assert_eq!(ManifestMode::from_name("SHA256"), ManifestMode::Sha256);
+        assert_eq!(ManifestMode::from_name("blake3"), ManifestMode::Blake3);
+        assert_eq!(ManifestMode::from_name("md5"), ManifestMode::Off);
+    }
+}
@@ -28,6 +28,8 @@ pub struct FileNode {
     pub name: String,
     pub children: Vec<FileNode>,
     pub expanded: bool,
+    /// Only directories are loaded as children, all the way down
+    pub dirs_only: bool,
 }
 
 impl FileNode {
@@ -44,9 +46,16 @@ impl FileNode {
             name,
             children: Vec::new(),
             expanded: false,
+            dirs_only: false,
         }
     }
 
+    /// Builder method: Only load directories below this node
+    pub fn with_dirs_only(mut self, dirs_only: bool) -> Self {
+        self.dirs_only = dirs_only;
+        self
+    }
+
     /// Load immediate children (lazy loading)
     pub fn load_children(&mut self) {
         if !self.is_dir || !self.children.is_empty() {
@@ -56,7 +65,8 @@ impl FileNode {
         if let Ok(entries) = std::fs::read_dir(&self.path) {
             let mut children: Vec<FileNode> = entries
                 .filter_map(|e| e.ok())
-                .map(|e| FileNode::new(e.path()))
+                .map(|e| FileNode::new(e.path()).with_dirs_only(self.dirs_only))
+                .filter(|node| node.is_dir || !self.dirs_only)
                 .collect();
 
             // Sort: directories first, then alphabetically
@@ -114,6 +124,8 @@ pub struct TreeBrowser {
     pub search_active: bool,
     pub search_results: Vec<PathBuf>,
     pub search_nodes: Vec<FileNode>,
+    /// Directory picker mode: files are neither listed nor searched
+    pub dirs_only: bool,
     matcher: Matcher,
 }
 
@@ -126,7 +138,16 @@ impl TreeBrowser {
     }
 
     pub fn with_root(root_dir: PathBuf) -> Self {
-        let mut root = FileNode::new(root_dir.clone());
+        Self::build(root_dir, false)
+    }
+
+    /// Browser that lists only directories, for picking a destination
+    pub fn dirs_only(root_dir: PathBuf) -> Self {
+        Self::build(root_dir, true)
+    }
+
+    fn build(root_dir: PathBuf, dirs_only: bool) -> Self {
+        let mut root = FileNode::new(root_dir.clone()).with_dirs_only(dirs_only);
         root.load_children();
         root.expanded = true;
 
@@ -147,6 +168,7 @@ impl TreeBrowser {
             search_active: false,
             search_results: Vec::new(),
             search_nodes: Vec::new(),
+            dirs_only,
             matcher: Matcher::new(Config::DEFAULT),
         }
     }
@@ -246,7 +268,7 @@ impl TreeBrowser {
         if let Some(parent) = self.root_dir.parent() {
             let parent = parent.to_path_buf();
             self.root_dir = parent.clone();
-            self.root = FileNode::new(parent);
+            self.root = FileNode::new(parent).with_dirs_only(self.dirs_only);
             self.root.load_children();
             self.root.expanded = true;
             self.state = TreeState::default();
@@ -339,11 +361,11 @@ impl TreeBrowser {
             })
             .collect();
 
-        matches.sort_by(|a, b| b.0.cmp(&a.0));
+        matches.sort_by_key(|m| std::cmp::Reverse(m.0));
 
         let paths: Vec<PathBuf> = matches.into_iter().take(100).map(|(_, p)| p).collect();
         self.search_results = paths.clone();
-        self.search_nodes = Self::build_merged_tree(&paths);
+        self.search_nodes = Self::build_merged_tree(&paths, self.dirs_only);
 
         // Reset state and open all merged nodes so children are visible
         self.state = TreeState::default();
@@ -369,7 +391,7 @@ impl TreeBrowser {
     }
 
     /// Build a merged tree from search results, preserving parent-child relationships
-    fn build_merged_tree(paths: &[PathBuf]) -> Vec<FileNode> {
+    fn build_merged_tree(paths: &[PathBuf], dirs_only: bool) -> Vec<FileNode> {
         if paths.is_empty() {
             return Vec::new();
         }
@@ -390,7 +412,7 @@ impl TreeBrowser {
             for root in &mut roots {
                 if let Some(parent_node) = Self::find_parent_for_path(root, &path) {
                     // Insert as child of the found parent
-                    let node = FileNode::new(path.clone());
+                    let node = FileNode::new(path.clone()).with_dirs_only(dirs_only);
                     parent_node.children.push(node);
                     // Re-sort children
                     parent_node
@@ -407,7 +429,7 @@ impl TreeBrowser {
 
             if !inserted {
                 // This is a new root-level result
-                let node = FileNode::new(path);
+                let node = FileNode::new(path).with_dirs_only(dirs_only);
                 roots.push(node);
             }
         }
@@ -471,7 +493,9 @@ impl TreeBrowser {
                 // Recursively collect, but limit depth for performance
                 if let Ok(entries) = std::fs::read_dir(&child.path) {
                     for entry in entries.filter_map(|e| e.ok()).take(100) {
-                        paths.push(entry.path());
+                        if !self.dirs_only || entry.path().is_dir() {
+                            paths.push(entry.path());
+                        }
                     }
                 }
             }
@@ -709,6 +733,24 @@ mod tests {
         assert_eq!(browser.search_query, "aa");
     }
 
+    #[test]
+    fn test_dirs_only_hides_files() {
+        let (_temp_dir, root) = create_test_tree();
+        let mut browser = TreeBrowser::dirs_only(root.clone());
+        let names: Vec<_> = browser
+            .root
+            .children
+            .iter()
+            .map(|c| c.name.clone())
+            .collect();
+        assert_eq!(names, vec!["ccc_dir"]);
+
+        browser.update_search("nested");
+        assert!(browser.search_results.is_empty());
+        browser.update_search("ccc");
+        assert_eq!(browser.search_results, vec![root.join("ccc_dir")]);
+    }
+
     #[test]
     fn test_node_id_display() {
         let node_id = NodeId(PathBuf::from("/test/path"));
@@ -4,6 +4,8 @@ pub mod layout;
 pub mod screens;
 pub mod widgets;
 
+use std::path::PathBuf;
+
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     symbols,
@@ -11,10 +13,16 @@ use ratatui::{
     widgets::{Block, Borders, Clear, Paragraph, Tabs, Wrap},
     Frame,
 };
+use tui_tree_widget::Tree;
 
-use crate::app::{App, ConflictPopup, ConnectionStatus, KeyPresetPopup, Mode, ThemePopup};
+use crate::app::{
+    App, ApprovalPopup, ConflictPopup, ConnectionStatus, DestinationPopup, ExcludePopup, FileCheck,
+    HashPopup, KeyPresetPopup, Mode, ReviewField, SendReviewPopup, ShareEditPopup, ShareLabelPopup,
+    TextComposePopup, TextViewPopup, ThemePopup, VerifyFilePopup,
+};
 use crate::input::KeyPreset;
 use crate::theme::{ThemeColors, ThemeKind};
+use crate::transfer::ConflictResolution;
 
 /// Main draw function
 pub fn draw(frame: &mut Frame, app: &mut App) {
@@ -55,6 +63,52 @@ pub fn draw(frame: &mut Frame, app: &mut App) {
         draw_conflict_popup(frame, theme, popup, size);
     }
 
+    // Where the next receive saves to
+    if let Some(ref mut popup) = app.destination_popup {
+        draw_destination_popup(frame, theme, popup, &app.recent_receive_dirs, size);
+    }
+
+    // Large receive waiting for confirmation
+    if let Some(ref popup) = app.approval_popup {
+        draw_approval_popup(frame, theme, popup, size);
+    }
+
+    // Text snippet compose/view popups
+    if let Some(ref popup) = app.text_compose {
+        draw_text_compose_popup(frame, theme, popup, size);
+    }
+    if let Some(ref popup) = app.text_view {
+        draw_text_view_popup(frame, theme, popup, size);
+    }
+
+    // Content hashes of a transfer, and checking a file against one
+    if let Some(ref popup) = app.hash_popup {
+        draw_hash_popup(frame, theme, popup, size);
+    }
+    if let Some(ref popup) = app.verify_file_popup {
+        draw_verify_file_popup(frame, theme, popup, size);
+    }
+
+    // Exclude pattern editor
+    if let Some(ref popup) = app.exclude_popup {
+        draw_exclude_popup(frame, theme, popup, size);
+    }
+
+    // Title and note for the next send
+    if let Some(ref popup) = app.share_label_popup {
+        draw_share_label_popup(frame, theme, popup, size);
+    }
+
+    // Entries to take out of a running share
+    if let Some(ref popup) = app.share_edit_popup {
+        draw_share_edit_popup(frame, theme, popup, size);
+    }
+
+    // Entry names of the next send
+    if let Some(ref popup) = app.send_review {
+        draw_send_review_popup(frame, theme, popup, size);
+    }
+
     // Theme picker popup
     if let Some(ref popup) = app.theme_popup {
         draw_theme_popup(frame, theme, popup, size);
@@ -153,21 +207,63 @@ fn draw_content(frame: &mut Frame, app: &mut App, theme: &ThemeColors, area: Rec
 }
 
 fn draw_footer(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
-    let symlink_status = if app.follow_symlinks {
-        "S:symlinks[ON]"
+    let symlink_status = format!("S:symlinks[{}]", app.symlink_mode.label());
+    let archive_status = if app.archive_sends {
+        "Z:archive[ON]"
+    } else {
+        "Z:archive"
+    };
+    let in_place_status = if app.in_place_sends {
+        "R:in-place[ON]"
     } else {
-        "S:symlinks"
+        "R:in-place"
+    };
+    let hidden_status = if app.exclude.include_hidden {
+        "H:hidden"
+    } else {
+        "H:hidden[OFF]"
+    };
+    let gitignore_status = if app.exclude.respect_gitignore {
+        "I:gitignore[ON]"
+    } else {
+        "I:gitignore"
+    };
+    let extract_status = if app.auto_extract {
+        "x:extract[ON]"
+    } else {
+        "x:extract"
+    };
+    let footprint_status = if app.low_footprint_receive {
+        "L:low-disk[ON]"
+    } else {
+        "L:low-disk"
+    };
+    let verify_status = if app.verify_receives {
+        "v:verify[ON]"
+    } else {
+        "v:verify"
     };
     let hints = match app.mode {
+        Mode::Send if app.adding_to_share.is_some() => format!(
+            "Space:sel  a:all  c:clr  /:search  s:add to share  Esc:back  {}  {}  {}  E:exclude  ?:help",
+            symlink_status, hidden_status, gitignore_status
+        ),
         Mode::Send => format!(
-            "Space:sel  a:all  c:clr  /:search  g/G:jump  s:send  {}  ?:help",
-            symlink_status
+            "Space:sel  a:all  c:clr  /:search  s:send  V:review  T:text  N:name  {}  {}  {}  {}  {}  E:exclude  ?:help",
+            symlink_status, archive_status, in_place_status, hidden_status, gitignore_status
+        ),
+        Mode::Receive => format!(
+            "Enter:input  Ctrl+V:paste  d:destination  f:subfolder[{}]  {}  {}  {}  m:manifest  #:verify file  ?:help  t:theme  B:keys  q:quit",
+            app.receive_subfolder.label(),
+            extract_status,
+            footprint_status,
+            verify_status
         ),
-        Mode::Receive => "Enter:input  Ctrl+V:paste  ?:help  t:theme  B:keys  q:quit".to_string(),
         Mode::Active => {
-            "c:copy  p:pause  x:cancel  r:retry  ?:help  t:theme  B:keys  q:quit".to_string()
+            "c:copy  v:text  #:hashes  +/-:add/remove  p:pause  x:cancel  r:retry  ?:help  t:theme  B:keys  q:quit"
+                .to_string()
         }
-        Mode::History => "r:resend  c:copy  d:delete  ?:help  t:theme  B:keys  q:quit".to_string(),
+        Mode::History => "r:resend  c:copy  #:hashes  d:delete  ?:help  t:theme  B:keys  q:quit".to_string(),
     };
 
     let footer = Paragraph::new(Line::from(vec![
@@ -356,9 +452,150 @@ fn draw_ticket_popup(frame: &mut Frame, theme: &ThemeColors, ticket: &str, area:
 }
 
 /// Draw the conflict resolution popup
+fn draw_destination_popup(
+    frame: &mut Frame,
+    theme: &ThemeColors,
+    popup: &mut DestinationPopup,
+    recent: &[PathBuf],
+    area: Rect,
+) {
+    let width = 70.min(area.width.saturating_sub(4));
+    let height = 24.min(area.height.saturating_sub(4));
+    let popup_area = centered_rect(width, height, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(Span::styled(" Save To ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused())
+        .border_set(symbols::border::DOUBLE)
+        .style(theme.panel());
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let width = inner.width as usize;
+    let mut top: Vec<Line> = vec![];
+    if !recent.is_empty() {
+        top.push(Line::from(Span::styled("Recent:", theme.text_dimmed())));
+        for (i, dir) in recent.iter().enumerate() {
+            top.push(Line::from(vec![
+                Span::styled(format!(" {} ", i + 1), theme.key()),
+                Span::styled(
+                    truncate_start(&dir.display().to_string(), width.saturating_sub(3)),
+                    theme.text_muted(),
+                ),
+            ]));
+        }
+        top.push(Line::from(""));
+    }
+    top.push(Line::from(Span::styled(
+        truncate_start(&popup.browser.root_dir.display().to_string(), width),
+        theme.text_highlight(),
+    )));
+
+    let footer = Line::from(vec![
+        Span::styled("Space", theme.key()),
+        Span::styled(" pick  ", theme.text()),
+        Span::styled(".", theme.key()),
+        Span::styled(" this folder  ", theme.text()),
+        Span::styled("Enter", theme.key()),
+        Span::styled(" open  ", theme.text()),
+        Span::styled("Bksp", theme.key()),
+        Span::styled(" parent  ", theme.text()),
+        Span::styled("Esc", theme.key()),
+        Span::styled(" close", theme.text()),
+    ]);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(top.len() as u16),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    frame.render_widget(Paragraph::new(top), chunks[0]);
+    let items = popup.browser.tree_items();
+    let tree = Tree::new(&items)
+        .expect("valid tree")
+        .highlight_style(theme.selected())
+        .highlight_symbol("> ");
+    frame.render_stateful_widget(tree, chunks[1], &mut popup.browser.state);
+    frame.render_widget(Paragraph::new(footer), chunks[2]);
+}
+
+fn draw_approval_popup(frame: &mut Frame, theme: &ThemeColors, popup: &ApprovalPopup, area: Rect) {
+    let width = 64.min(area.width.saturating_sub(4));
+    let height = (popup.largest.len() as u16 + 10).min(area.height.saturating_sub(4));
+    let popup_area = centered_rect(width, height, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(Span::styled(" Large Share ", theme.warning()))
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused())
+        .border_set(symbols::border::DOUBLE)
+        .style(theme.panel());
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let mut lines: Vec<Line> = vec![];
+    if let Some(ref title) = popup.title {
+        lines.push(Line::from(Span::styled(
+            title.as_str(),
+            theme.text_highlight(),
+        )));
+    }
+    if let Some(ref note) = popup.note {
+        lines.push(Line::from(Span::styled(note.as_str(), theme.text_muted())));
+    }
+    lines.push(Line::from(vec![
+        Span::styled(format_bytes(popup.total_bytes), theme.warning()),
+        Span::styled(
+            format!(" in {} file(s). Download it?", popup.files),
+            theme.text(),
+        ),
+    ]));
+    lines.push(Line::from(""));
+
+    if !popup.largest.is_empty() {
+        lines.push(Line::from(Span::styled("Largest:", theme.text_dimmed())));
+        let size_width = 11;
+        let name_width = (inner.width as usize).saturating_sub(size_width + 2);
+        for (name, size) in &popup.largest {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  {:>w$} ", format_bytes(*size), w = size_width - 1),
+                    theme.text_dimmed(),
+                ),
+                Span::styled(truncate_start(name, name_width), theme.text_muted()),
+            ]));
+        }
+        lines.push(Line::from(""));
+    }
+
+    lines.push(Line::from(vec![
+        Span::styled("y", theme.key()),
+        Span::styled("/", theme.text_dimmed()),
+        Span::styled("Enter", theme.key()),
+        Span::styled(" download  ", theme.text()),
+        Span::styled("n", theme.key()),
+        Span::styled("/", theme.text_dimmed()),
+        Span::styled("Esc", theme.key()),
+        Span::styled(" decline", theme.text()),
+    ]));
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
 fn draw_conflict_popup(frame: &mut Frame, theme: &ThemeColors, popup: &ConflictPopup, area: Rect) {
-    let width = 60.min(area.width.saturating_sub(4));
-    let height = 16.min(area.height.saturating_sub(4));
+    let width = 76.min(area.width.saturating_sub(4));
+    let height = 23.min(area.height.saturating_sub(4));
     let popup_area = centered_rect(width, height, area);
 
     frame.render_widget(Clear, popup_area);
@@ -376,6 +613,22 @@ fn draw_conflict_popup(frame: &mut Frame, theme: &ThemeColors, popup: &ConflictP
     // Build content
     let mut lines: Vec<Line> = vec![];
 
+    // Who sent this and why, when the sender said
+    let mut label = Vec::new();
+    if let Some(ref title) = popup.title {
+        label.push(Span::styled(title.as_str(), theme.text_highlight()));
+    }
+    if let Some(ref note) = popup.note {
+        if !label.is_empty() {
+            label.push(Span::styled(" - ", theme.text_dimmed()));
+        }
+        label.push(Span::styled(note.as_str(), theme.text_muted()));
+    }
+    let has_label = !label.is_empty();
+    if has_label {
+        lines.push(Line::from(label));
+    }
+
     // Show conflict count and size
     let size_str = format_bytes(popup.total_bytes);
     lines.push(Line::from(vec![
@@ -385,55 +638,113 @@ fn draw_conflict_popup(frame: &mut Frame, theme: &ThemeColors, popup: &ConflictP
         ),
         Span::styled(format!(" ({})", size_str), theme.text_dimmed()),
     ]));
-    lines.push(Line::from(""));
-
-    // Show first few conflicting files
-    let max_show = 3;
-    for (name, _path) in popup.conflicts.iter().take(max_show) {
-        lines.push(Line::from(Span::styled(
-            format!("  {} {}", "•", name),
-            theme.text_muted(),
-        )));
-    }
-    if popup.conflicts.len() > max_show {
+    if !popup.identical.is_empty() {
         lines.push(Line::from(Span::styled(
-            format!("  ... and {} more", popup.conflicts.len() - max_show),
-            theme.text_dimmed(),
+            format!(
+                "{} identical file(s) will be skipped",
+                popup.identical.len()
+            ),
+            theme.success(),
         )));
     }
-
-    lines.push(Line::from(""));
-    lines.push(Line::from(Span::styled("Choose action:", theme.text())));
     lines.push(Line::from(""));
 
-    // Options
-    let options = [
-        ("1", "Rename", "Add (1), (2), etc. to new files"),
-        ("2", "Overwrite", "Replace existing files"),
-        ("3", "Skip", "Don't download existing files"),
-        ("4", "Cancel", "Abort the transfer"),
-    ];
+    // Scrolling window of conflicts around the cursor
+    let reserved = 11 + usize::from(!popup.identical.is_empty()) + usize::from(has_label);
+    let max_show = (inner.height as usize).saturating_sub(reserved).max(1);
+    let offset = popup.selected.saturating_sub(max_show - 1);
+    let label_width = 13;
+    let age_width = 6;
+    let name_width = (inner.width as usize).saturating_sub(label_width + age_width + 4);
 
-    for (i, (key, label, desc)) in options.iter().enumerate() {
+    for (i, (conflict, choice)) in popup
+        .conflicts
+        .iter()
+        .zip(popup.choices.iter())
+        .enumerate()
+        .skip(offset)
+        .take(max_show)
+    {
         let is_selected = i == popup.selected;
         let prefix = if is_selected { "▸ " } else { "  " };
-        let style = if is_selected {
+        let name_style = if is_selected {
             theme.text_highlight()
         } else {
-            theme.text()
+            theme.text_muted()
+        };
+        let (label, style) = match choice {
+            ConflictResolution::Rename => ("Rename", theme.info()),
+            ConflictResolution::Overwrite => ("Overwrite", theme.warning()),
+            ConflictResolution::Skip => ("Skip", theme.text_dimmed()),
+            ConflictResolution::Cancel => ("Cancel", theme.error()),
+            ConflictResolution::SkipIdentical => ("Identical", theme.success()),
+            ConflictResolution::KeepNewer => ("Keep newer", theme.info()),
+        };
+        // Whether the incoming copy is newer, when both times are known
+        let age = match conflict.incoming_is_newer() {
+            Some(true) => "newer",
+            Some(false) => "older",
+            None => "",
         };
 
         lines.push(Line::from(vec![
-            Span::styled(prefix, style),
-            Span::styled(format!("[{}] ", key), theme.key()),
-            Span::styled(*label, style),
-            Span::styled(format!(" - {}", desc), theme.text_dimmed()),
+            Span::styled(prefix, name_style),
+            Span::styled(format!("[{:<w$}] ", label, w = label_width - 3), style),
+            Span::styled(format!("{:<w$}", age, w = age_width), theme.text_dimmed()),
+            Span::styled(truncate_start(&conflict.name, name_width), name_style),
+        ]));
+    }
+    let hidden = popup.conflicts.len().saturating_sub(offset + max_show);
+    if hidden > 0 {
+        lines.push(Line::from(Span::styled(
+            format!("  ... and {} more", hidden),
+            theme.text_dimmed(),
+        )));
+    }
+
+    // Details for the file under the cursor
+    if let Some(conflict) = popup.conflicts.get(popup.selected) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("Existing ", theme.text_dimmed()),
+            Span::styled(format_bytes(conflict.existing_size), theme.text()),
+            Span::styled("  Incoming ", theme.text_dimmed()),
+            Span::styled(format_bytes(conflict.incoming_size), theme.text()),
         ]));
+        lines.push(Line::from(Span::styled(
+            truncate_start(&conflict.path.display().to_string(), inner.width as usize),
+            theme.text_dimmed(),
+        )));
     }
 
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled("This file: ", theme.text_dimmed()),
+        Span::styled("r", theme.key()),
+        Span::styled(" rename  ", theme.text()),
+        Span::styled("o", theme.key()),
+        Span::styled(" overwrite  ", theme.text()),
+        Span::styled("s", theme.key()),
+        Span::styled(" skip  ", theme.text()),
+        Span::styled("n", theme.key()),
+        Span::styled(" newer  ", theme.text()),
+        Span::styled("Space", theme.key()),
+        Span::styled(" cycle", theme.text()),
+    ]));
+    lines.push(Line::from(vec![
+        Span::styled("All files: ", theme.text_dimmed()),
+        Span::styled("R", theme.key()),
+        Span::styled(" rename  ", theme.text()),
+        Span::styled("O", theme.key()),
+        Span::styled(" overwrite  ", theme.text()),
+        Span::styled("S", theme.key()),
+        Span::styled(" skip  ", theme.text()),
+        Span::styled("N", theme.key()),
+        Span::styled(" newer", theme.text()),
+    ]));
     lines.push(Line::from(""));
     lines.push(Line::from(Span::styled(
-        "↑/↓ to select, Enter to confirm, Esc to cancel",
+        "↑/↓ to select, Enter to confirm, Esc to cancel transfer",
         theme.text_dimmed(),
     )));
 
@@ -441,6 +752,17 @@ fn draw_conflict_popup(frame: &mut Frame, theme: &ThemeColors, popup: &ConflictP
     frame.render_widget(content, inner);
 }
 
+/// Truncate from the left so the file name at the end of a path stays visible
+fn truncate_start(s: &str, max: usize) -> String {
+    let len = s.chars().count();
+    if len <= max {
+        s.to_string()
+    } else {
+        let tail: String = s.chars().skip(len - max.saturating_sub(1)).collect();
+        format!("…{}", tail)
+    }
+}
+
 /// Format bytes as human-readable string
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -504,6 +826,552 @@ fn draw_theme_popup(frame: &mut Frame, theme: &ThemeColors, popup: &ThemePopup,
     frame.render_widget(content, inner);
 }
 
+/// Shared frame for the text popups: body above a one-line hint
+fn text_popup_areas(
+    frame: &mut Frame,
+    theme: &ThemeColors,
+    title: &str,
+    area: Rect,
+) -> (Rect, Rect) {
+    let width = 72.min(area.width.saturating_sub(4));
+    let height = 16.min(area.height.saturating_sub(4));
+    let popup_area = centered_rect(width, height, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(Span::styled(title, theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+    (chunks[0], chunks[1])
+}
+
+/// Draw the text snippet compose popup
+fn draw_text_compose_popup(
+    frame: &mut Frame,
+    theme: &ThemeColors,
+    popup: &TextComposePopup,
+    area: Rect,
+) {
+    let (body, hints) = text_popup_areas(frame, theme, " Share Text ", area);
+
+    let mut lines: Vec<Line> = popup
+        .text
+        .split('\n')
+        .map(|line| Line::from(Span::styled(line.to_string(), theme.text())))
+        .collect();
+    if let Some(last) = lines.last_mut() {
+        last.push_span(Span::styled("█", theme.text_highlight()));
+    }
+
+    // Keep the cursor line in view
+    let visible = body.height as usize;
+    let scroll = lines.len().saturating_sub(visible) as u16;
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0)),
+        body,
+    );
+
+    frame.render_widget(
+        Paragraph::new(Line::from(Span::styled(
+            "Enter send, Ctrl+V paste, Ctrl+J newline, Ctrl+U clear, Esc cancel",
+            theme.text_dimmed(),
+        ))),
+        hints,
+    );
+}
+
+/// Draw the received text snippet popup
+fn draw_text_view_popup(frame: &mut Frame, theme: &ThemeColors, popup: &TextViewPopup, area: Rect) {
+    let (body, hints) = text_popup_areas(frame, theme, " Received Text ", area);
+
+    frame.render_widget(
+        Paragraph::new(popup.text.as_str())
+            .style(theme.text())
+            .wrap(Wrap { trim: false })
+            .scroll((popup.scroll, 0)),
+        body,
+    );
+
+    let hint = if popup.copied {
+        "Copied to clipboard. ↑/↓ scroll, Esc close"
+    } else {
+        "c copy, ↑/↓ scroll, Esc close"
+    };
+    frame.render_widget(
+        Paragraph::new(Line::from(Span::styled(hint, theme.text_dimmed()))),
+        hints,
+    );
+}
+
+/// Draw the exclude pattern editor
+fn draw_exclude_popup(frame: &mut Frame, theme: &ThemeColors, popup: &ExcludePopup, area: Rect) {
+    let width = 64.min(area.width.saturating_sub(4));
+    let height = 8.min(area.height.saturating_sub(4));
+    let popup_area = centered_rect(width, height, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(Span::styled(" Exclude Patterns ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let lines = vec![
+        Line::from(Span::styled(
+            " Comma-separated, gitignore syntax (e.g. target/, *.log)",
+            theme.text_dimmed(),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(" > ", theme.key()),
+            Span::styled(popup.input.as_str(), theme.text()),
+            Span::styled("█", theme.text_highlight()),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            " Enter save, Ctrl+U clear, Esc cancel",
+            theme.text_dimmed(),
+        )),
+    ];
+
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
+}
+
+fn draw_share_label_popup(
+    frame: &mut Frame,
+    theme: &ThemeColors,
+    popup: &ShareLabelPopup,
+    area: Rect,
+) {
+    let width = 64.min(area.width.saturating_sub(4));
+    let height = 10.min(area.height.saturating_sub(4));
+    let popup_area = centered_rect(width, height, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(Span::styled(" Share Title & Note ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let field = |label: &'static str, value: &str, active: bool| {
+        let mut spans = vec![
+            Span::styled(
+                label,
+                if active {
+                    theme.key()
+                } else {
+                    theme.text_dimmed()
+                },
+            ),
+            Span::styled(value.to_string(), theme.text()),
+        ];
+        if active {
+            spans.push(Span::styled("█", theme.text_highlight()));
+        }
+        Line::from(spans)
+    };
+
+    let lines = vec![
+        Line::from(Span::styled(
+            " Shown to the receiver instead of the file names",
+            theme.text_dimmed(),
+        )),
+        Line::from(""),
+        field(" Title > ", &popup.title, !popup.editing_note),
+        field(" Note  > ", &popup.note, popup.editing_note),
+        Line::from(""),
+        Line::from(Span::styled(
+            " Tab switch field, Enter save, Ctrl+U clear, Esc cancel",
+            theme.text_dimmed(),
+        )),
+    ];
+
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
+}
+
+fn draw_hash_popup(frame: &mut Frame, theme: &ThemeColors, popup: &HashPopup, area: Rect) {
+    let width = 100.min(area.width.saturating_sub(4));
+    let height = 22.min(area.height.saturating_sub(4));
+    let popup_area = centered_rect(width, height, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" Hashes: {} ", popup.name),
+            theme.title(),
+        ))
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let mut lines: Vec<Line> = vec![Line::from(Span::styled(
+        " BLAKE3, as iroh-blobs verifies them; the collection hash is the one in the ticket",
+        theme.text_dimmed(),
+    ))];
+
+    // Scrolling window of rows around the cursor
+    let max_show = (inner.height as usize).saturating_sub(5).max(1);
+    let offset = popup.selected.saturating_sub(max_show - 1);
+    let name_width = (inner.width as usize).saturating_sub(68);
+
+    let rows = std::iter::once(("collection", popup.hashes.root.as_str())).chain(
+        popup
+            .hashes
+            .entries
+            .iter()
+            .map(|(name, hash)| (name.as_str(), hash.as_str())),
+    );
+    for (i, (name, hash)) in rows.enumerate().skip(offset).take(max_show) {
+        let is_selected = i == popup.selected;
+        let prefix = if is_selected { "▸ " } else { "  " };
+        let (hash_style, name_style) = if is_selected {
+            (theme.text_highlight(), theme.text_highlight())
+        } else if i == 0 {
+            (theme.text(), theme.key())
+        } else {
+            (theme.text(), theme.text_muted())
+        };
+        lines.push(Line::from(vec![
+            Span::styled(prefix, name_style),
+            Span::styled(hash.to_string(), hash_style),
+            Span::styled("  ", theme.text()),
+            Span::styled(truncate_start(name, name_width), name_style),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    if popup.hashes.omitted > 0 {
+        lines.push(Line::from(Span::styled(
+            format!(" {} more entries not kept", popup.hashes.omitted),
+            theme.text_dimmed(),
+        )));
+    }
+    let mut footer = vec![];
+    if popup.copied {
+        footer.push(Span::styled(" Copied. ", theme.success()));
+    }
+    footer.extend([
+        Span::styled(" c", theme.key()),
+        Span::styled(" copy  ", theme.text()),
+        Span::styled("a", theme.key()),
+        Span::styled(" copy all  ", theme.text()),
+    ]);
+    // The collection hash belongs to no single file
+    if popup.selected > 0 {
+        footer.extend([
+            Span::styled("v", theme.key()),
+            Span::styled(" verify a file  ", theme.text()),
+        ]);
+    }
+    footer.extend([
+        Span::styled("Esc", theme.key()),
+        Span::styled(" close", theme.text()),
+    ]);
+    lines.push(Line::from(footer));
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+fn draw_verify_file_popup(
+    frame: &mut Frame,
+    theme: &ThemeColors,
+    popup: &VerifyFilePopup,
+    area: Rect,
+) {
+    let width = 84.min(area.width.saturating_sub(4));
+    let height = 10.min(area.height.saturating_sub(4));
+    let popup_area = centered_rect(width, height, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(Span::styled(" Verify File ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let value_width = (inner.width as usize).saturating_sub(11);
+    let field = |label: &'static str, value: &str, active: bool| {
+        let mut spans = vec![
+            Span::styled(
+                label,
+                if active {
+                    theme.key()
+                } else {
+                    theme.text_dimmed()
+                },
+            ),
+            Span::styled(truncate_start(value, value_width), theme.text()),
+        ];
+        if active {
+            spans.push(Span::styled("█", theme.text_highlight()));
+        }
+        Line::from(spans)
+    };
+
+    let result = match &popup.check {
+        None => Line::from(""),
+        Some(FileCheck::Hashing { .. }) => Line::from(Span::styled(" Hashing...", theme.info())),
+        Some(FileCheck::Matches) => Line::from(Span::styled(
+            " ✓ Matches: this is exactly the file that was sent",
+            theme.success(),
+        )),
+        Some(FileCheck::Differs(actual)) => Line::from(vec![
+            Span::styled(" ✗ Differs, file hashes to ", theme.error()),
+            Span::styled(actual.as_str(), theme.text()),
+        ]),
+        Some(FileCheck::Failed(error)) => {
+            Line::from(Span::styled(format!(" ✗ {}", error), theme.error()))
+        }
+    };
+
+    let lines = vec![
+        Line::from(Span::styled(
+            " Hash a local file and compare it with a BLAKE3 hash",
+            theme.text_dimmed(),
+        )),
+        Line::from(""),
+        field(" File   > ", &popup.path, !popup.editing_hash),
+        field(" BLAKE3 > ", &popup.expected, popup.editing_hash),
+        Line::from(""),
+        result,
+        Line::from(""),
+        Line::from(Span::styled(
+            " Tab switch field, Enter check, Ctrl+V paste, Ctrl+U clear, Esc close",
+            theme.text_dimmed(),
+        )),
+    ];
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+fn draw_share_edit_popup(
+    frame: &mut Frame,
+    theme: &ThemeColors,
+    popup: &ShareEditPopup,
+    area: Rect,
+) {
+    let width = 64.min(area.width.saturating_sub(4));
+    let height = 20.min(area.height.saturating_sub(4));
+    let popup_area = centered_rect(width, height, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(Span::styled(" Remove From Share ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let mut lines: Vec<Line> = vec![Line::from("")];
+
+    // Scrolling window of entries around the cursor
+    let max_show = (inner.height as usize).saturating_sub(4).max(1);
+    let offset = popup.selected.saturating_sub(max_show - 1);
+    let name_width = (inner.width as usize).saturating_sub(7);
+
+    for (i, (name, remove)) in popup
+        .entries
+        .iter()
+        .zip(popup.remove.iter())
+        .enumerate()
+        .skip(offset)
+        .take(max_show)
+    {
+        let is_selected = i == popup.selected;
+        let prefix = if is_selected { "▸ " } else { "  " };
+        let (check, style) = if *remove {
+            ("[x] ", theme.warning())
+        } else {
+            ("[ ] ", theme.text_muted())
+        };
+        let name_style = if is_selected {
+            theme.text_highlight()
+        } else {
+            style
+        };
+        lines.push(Line::from(vec![
+            Span::styled(prefix, name_style),
+            Span::styled(check, style),
+            Span::styled(truncate_start(name, name_width), name_style),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(vec![
+        Span::styled(" Space", theme.key()),
+        Span::styled(" mark  ", theme.text()),
+        Span::styled("Enter", theme.key()),
+        Span::styled(" remove marked  ", theme.text()),
+        Span::styled("Esc", theme.key()),
+        Span::styled(" cancel", theme.text()),
+    ]));
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+fn draw_send_review_popup(
+    frame: &mut Frame,
+    theme: &ThemeColors,
+    popup: &SendReviewPopup,
+    area: Rect,
+) {
+    let width = 72.min(area.width.saturating_sub(4));
+    let height = 24.min(area.height.saturating_sub(4));
+    let popup_area = centered_rect(width, height, area);
+
+    frame.render_widget(Clear, popup_area);
+
+    let block = Block::default()
+        .title(Span::styled(" Review Entry Names ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(popup_area);
+    frame.render_widget(block, popup_area);
+
+    let prefix = popup.naming.prefix.as_deref().unwrap_or("none");
+    let mut lines: Vec<Line> = vec![Line::from(vec![
+        Span::styled(" Layout: ", theme.text_dimmed()),
+        Span::styled(popup.naming.layout.label(), theme.text()),
+        Span::styled("   Prefix: ", theme.text_dimmed()),
+        Span::styled(prefix, theme.text()),
+        Span::styled(
+            format!("   Renamed: {}", popup.naming.renames.len()),
+            theme.text_dimmed(),
+        ),
+    ])];
+
+    let status = if !popup.duplicates.is_empty() {
+        Span::styled(
+            format!(
+                " {} names collide; rename or change the layout",
+                popup.duplicates.len()
+            ),
+            theme.error(),
+        )
+    } else if popup.names.iter().any(|n| n.is_err()) {
+        Span::styled(" Some names are invalid", theme.error())
+    } else if popup.truncated {
+        Span::styled(
+            format!(" Showing the first {} files", popup.files.len()),
+            theme.warning(),
+        )
+    } else {
+        Span::styled(format!(" {} files", popup.files.len()), theme.text_dimmed())
+    };
+    lines.push(Line::from(status));
+    lines.push(Line::from(""));
+
+    // Scrolling window of entries around the cursor
+    let max_show = (inner.height as usize).saturating_sub(7).max(1);
+    let offset = popup.selected.saturating_sub(max_show - 1);
+    let name_width = (inner.width as usize).saturating_sub(4);
+
+    for (i, (file, name)) in popup
+        .files
+        .iter()
+        .zip(popup.names.iter())
+        .enumerate()
+        .skip(offset)
+        .take(max_show)
+    {
+        let is_selected = i == popup.selected;
+        let marker = if is_selected { "▸ " } else { "  " };
+        let (text, style) = match name {
+            Err(e) => (format!("{} ({})", file.path.display(), e), theme.error()),
+            Ok(name) if popup.duplicates.contains(name) => (name.clone(), theme.error()),
+            Ok(name) if popup.naming.renames.contains_key(&file.path) => {
+                (name.clone(), theme.info())
+            }
+            Ok(name) => (name.clone(), theme.text()),
+        };
+        let style = if is_selected {
+            style.patch(theme.text_highlight())
+        } else {
+            style
+        };
+        lines.push(Line::from(vec![
+            Span::styled(marker, style),
+            Span::styled(truncate_start(&text, name_width), style),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    match &popup.editing {
+        Some((field, input)) => {
+            let label = match field {
+                ReviewField::Prefix => " Prefix > ",
+                ReviewField::Rename => " Rename > ",
+            };
+            lines.push(Line::from(vec![
+                Span::styled(label, theme.key()),
+                Span::styled(input.as_str(), theme.text()),
+                Span::styled("█", theme.text_highlight()),
+            ]));
+            lines.push(Line::from(Span::styled(
+                " Enter apply, Ctrl+U clear, Esc cancel",
+                theme.text_dimmed(),
+            )));
+        }
+        None => {
+            lines.push(Line::from(vec![
+                Span::styled(" f", theme.key()),
+                Span::styled(" layout  ", theme.text()),
+                Span::styled("p", theme.key()),
+                Span::styled(" prefix  ", theme.text()),
+                Span::styled("r", theme.key()),
+                Span::styled(" rename  ", theme.text()),
+                Span::styled("Enter", theme.key()),
+                Span::styled(" send  ", theme.text()),
+                Span::styled("Esc", theme.key()),
+                Span::styled(" back", theme.text()),
+            ]));
+        }
+    }
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
 /// Draw the key preset picker popup
 fn draw_key_preset_popup(
     frame: &mut Frame,
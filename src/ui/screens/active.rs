@@ -8,8 +8,9 @@ use ratatui::{
     Frame,
 };
 
-use crate::app::{App, TransferDirection, TransferStatus};
+use crate::app::{App, Transfer, TransferDirection, TransferStatus};
 use crate::theme::ThemeColors;
+use crate::transfer::HookStatus;
 use crate::ui::widgets::transfer_item::TransferItem;
 
 pub fn draw(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
@@ -20,7 +21,136 @@ pub fn draw(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
         .split(area);
 
     draw_transfers(frame, app, theme, chunks[0]);
-    draw_recent(frame, app, theme, chunks[1]);
+
+    // Entries the selected send left out, or the selected receive renamed,
+    // and hook output share the side column with Recent
+    let details = app
+        .transfers
+        .get(app.transfer_cursor)
+        .filter(|t| !t.skipped.is_empty() || !t.renamed.is_empty());
+    let show_hooks = !app.hook_runs.is_empty();
+    let constraints = match (details.is_some(), show_hooks) {
+        (true, true) => vec![
+            Constraint::Percentage(30),
+            Constraint::Percentage(35),
+            Constraint::Percentage(35),
+        ],
+        (true, false) | (false, true) => {
+            vec![Constraint::Percentage(40), Constraint::Percentage(60)]
+        }
+        (false, false) => vec![Constraint::Percentage(100)],
+    };
+    let side = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(chunks[1]);
+
+    draw_recent(frame, app, theme, side[0]);
+    if let Some(transfer) = details {
+        if transfer.skipped.is_empty() {
+            draw_renamed(frame, transfer, theme, side[1]);
+        } else {
+            draw_skipped(frame, transfer, theme, side[1]);
+        }
+    }
+    if show_hooks {
+        draw_hooks(frame, app, theme, side[side.len() - 1]);
+    }
+}
+
+/// Hook runs, newest first, with their captured output
+fn draw_hooks(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
+    let block = Block::default()
+        .title(Span::styled(" Hooks ", theme.title()))
+        .borders(Borders::ALL)
+        .border_style(theme.border())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut lines = Vec::new();
+    for (_, run) in app.hook_runs.iter().rev() {
+        let (status, style) = match &run.status {
+            HookStatus::Running => ("running".to_string(), theme.info()),
+            HookStatus::Succeeded => ("ok".to_string(), theme.success()),
+            HookStatus::Failed(reason) => (reason.clone(), theme.error()),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!(" {} ", run.event.label()), theme.text_muted()),
+            Span::styled(&run.transfer, theme.text()),
+            Span::styled(format!("  {}", status), style),
+        ]));
+        for output in run.output.lines() {
+            lines.push(Line::from(Span::styled(
+                format!("   {}", output),
+                theme.text_dimmed(),
+            )));
+        }
+    }
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+/// List the entries a receive saved under cleaned-up names
+fn draw_renamed(frame: &mut Frame, transfer: &Transfer, theme: &ThemeColors, area: Rect) {
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" Renamed ({}) ", transfer.renamed.len()),
+            theme.title(),
+        ))
+        .borders(Borders::ALL)
+        .border_style(theme.border())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut lines = Vec::new();
+    for rename in &transfer.renamed {
+        lines.push(Line::from(Span::styled(
+            format!(" {}", rename.from),
+            theme.text_muted(),
+        )));
+        lines.push(Line::from(Span::styled(
+            format!("   → {}", rename.to),
+            theme.info(),
+        )));
+    }
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+/// List the entries a send left out, with the reason for each
+fn draw_skipped(frame: &mut Frame, transfer: &Transfer, theme: &ThemeColors, area: Rect) {
+    let block = Block::default()
+        .title(Span::styled(
+            format!(" Skipped ({}) ", transfer.skipped.len()),
+            theme.title(),
+        ))
+        .borders(Borders::ALL)
+        .border_style(theme.border())
+        .border_set(symbols::border::ROUNDED)
+        .style(theme.panel());
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut lines = Vec::new();
+    for entry in &transfer.skipped {
+        lines.push(Line::from(Span::styled(
+            format!(" {}", entry.path),
+            theme.text(),
+        )));
+        lines.push(Line::from(Span::styled(
+            format!("   {}", entry.reason),
+            theme.warning(),
+        )));
+    }
+
+    frame.render_widget(Paragraph::new(lines), inner);
 }
 
 fn draw_transfers(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
@@ -99,7 +229,7 @@ fn draw_transfers(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect)
                     x: inner.x,
                     y,
                     width: inner.width,
-                    height: 2,
+                    height: 3, // The spacer row holds the note, if any
                 },
             );
             y += 3;
@@ -153,7 +283,7 @@ fn draw_transfers(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect)
                     x: inner.x,
                     y,
                     width: inner.width,
-                    height: 2,
+                    height: 3, // The spacer row holds the note, if any
                 },
             );
             y += 3;
@@ -133,12 +133,37 @@ fn draw_details(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
     let size = humansize::format_size(transfer.total_bytes, humansize::BINARY);
 
     // Conflict resolution info
-    let conflict_info = transfer.conflict_resolution.as_ref().map(|r| match r {
-        ConflictResolution::Rename => ("Renamed (added suffix)", theme.info()),
-        ConflictResolution::Overwrite => ("Overwrote existing", theme.warning()),
-        ConflictResolution::Skip => ("Skipped existing", theme.text_dimmed()),
-        ConflictResolution::Cancel => ("Cancelled", theme.error()),
-    });
+    let conflict_info = match transfer.conflict_resolution.as_ref() {
+        Some(r) => Some(match r {
+            ConflictResolution::Rename => ("Renamed (added suffix)".to_string(), theme.info()),
+            ConflictResolution::Overwrite => ("Overwrote existing".to_string(), theme.warning()),
+            ConflictResolution::Skip => ("Skipped existing".to_string(), theme.text_dimmed()),
+            ConflictResolution::Cancel => ("Cancelled".to_string(), theme.error()),
+            ConflictResolution::SkipIdentical => {
+                ("Skipped identical files".to_string(), theme.success())
+            }
+            ConflictResolution::KeepNewer => ("Kept newer version".to_string(), theme.info()),
+        }),
+        None if !transfer.conflict_resolutions.is_empty() => {
+            let parts: Vec<String> = transfer
+                .conflict_summary()
+                .into_iter()
+                .map(|(r, count)| {
+                    let label = match r {
+                        ConflictResolution::Rename => "renamed",
+                        ConflictResolution::Overwrite => "overwritten",
+                        ConflictResolution::Skip => "skipped",
+                        ConflictResolution::Cancel => "cancelled",
+                        ConflictResolution::SkipIdentical => "identical",
+                        ConflictResolution::KeepNewer => "kept newer",
+                    };
+                    format!("{} {}", count, label)
+                })
+                .collect();
+            Some((parts.join(", "), theme.info()))
+        }
+        None => None,
+    };
 
     let mut lines = vec![
         Line::from(""),
@@ -191,6 +216,68 @@ fn draw_details(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
         ]));
     }
 
+    if let Some(ref hashes) = transfer.hashes {
+        lines.push(Line::from(vec![
+            Span::styled("  Hash: ", theme.text_dimmed()),
+            Span::styled(hashes.root.as_str(), theme.text()),
+            Span::styled("  (# for entry hashes)", theme.text_muted()),
+        ]));
+    }
+
+    if let Some(ref dir) = transfer.saved_to {
+        lines.push(Line::from(vec![
+            Span::styled("  Saved to: ", theme.text_dimmed()),
+            Span::styled(dir.display().to_string(), theme.text_muted()),
+        ]));
+    }
+    for (dir, files) in &transfer.routed {
+        lines.push(Line::from(vec![
+            Span::styled("  Routed: ", theme.text_dimmed()),
+            Span::styled(format!("{} file(s) to ", files), theme.text()),
+            Span::styled(dir.display().to_string(), theme.text_muted()),
+        ]));
+    }
+
+    if let Some(ref report) = transfer.verify {
+        let (label, style) = if report.failed.is_empty() {
+            (format!("{} files OK", report.checked), theme.success())
+        } else {
+            (
+                format!("{} of {} files failed", report.failed.len(), report.checked),
+                theme.error(),
+            )
+        };
+        lines.push(Line::from(vec![
+            Span::styled("  Verified: ", theme.text_dimmed()),
+            Span::styled(label, style),
+        ]));
+        for (name, reason) in &report.failed {
+            lines.push(Line::from(vec![
+                Span::styled(format!("    ✗ {}", name), theme.error()),
+                Span::styled(format!(": {}", reason), theme.text_muted()),
+            ]));
+        }
+        for manifest in &report.manifests {
+            lines.push(Line::from(vec![
+                Span::styled("  Manifest: ", theme.text_dimmed()),
+                Span::styled(manifest.display().to_string(), theme.text_muted()),
+            ]));
+        }
+        if let Some(ref error) = report.manifest_error {
+            lines.push(Line::from(vec![
+                Span::styled("  Manifest: ", theme.text_dimmed()),
+                Span::styled(format!("not written: {}", error), theme.warning()),
+            ]));
+        }
+    }
+
+    if let Some(ref note) = transfer.note {
+        lines.push(Line::from(vec![
+            Span::styled("  Note: ", theme.text_dimmed()),
+            Span::styled(note.as_str(), theme.text_muted()),
+        ]));
+    }
+
     // Add conflict resolution line if present
     if let Some((label, style)) = conflict_info {
         lines.push(Line::from(vec![
@@ -199,6 +286,20 @@ fn draw_details(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
         ]));
     }
 
+    if !transfer.renamed.is_empty() {
+        lines.push(Line::from(Span::styled(
+            format!("  Renamed ({}):", transfer.renamed.len()),
+            theme.text_dimmed(),
+        )));
+        for rename in &transfer.renamed {
+            lines.push(Line::from(vec![
+                Span::styled(format!("    {}", rename.from), theme.text_muted()),
+                Span::styled(" → ", theme.text_dimmed()),
+                Span::styled(&rename.to, theme.info()),
+            ]));
+        }
+    }
+
     // Add file list if multiple files
     let total_files = transfer.total_file_count();
     if total_files > 1 {
@@ -211,9 +312,19 @@ fn draw_details(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
         let max_display = (inner.height as usize).saturating_sub(lines.len() + 6);
         for file in transfer.files.iter().take(max_display) {
             let size = humansize::format_size(file.size, humansize::BINARY);
+            // Pass or fail per file once the receive has been verified
+            let mark = match transfer.verify {
+                Some(ref report) if report.file_failed(&file.name) => {
+                    Span::styled("  ✗ ", theme.error())
+                }
+                Some(_) => Span::styled("  ✓ ", theme.success()),
+                None => Span::styled("    ", theme.text()),
+            };
+            // Received files are listed by entry name, shown by file name
+            let name = file.name.rsplit('/').next().unwrap_or(&file.name);
             lines.push(Line::from(vec![
-                Span::styled("    ", theme.text()),
-                Span::styled(&file.name, theme.text()),
+                mark,
+                Span::styled(name, theme.text()),
                 Span::styled(format!(" ({})", size), theme.text_muted()),
             ]));
         }
@@ -71,17 +71,51 @@ pub fn draw(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
     frame.render_widget(content, inner);
 
     // Save directory info below
+    let mut info = vec![
+        Line::from(vec![
+            Span::styled("  Save to: ", theme.text_dimmed()),
+            Span::styled(app.receive_dir.display().to_string(), theme.text()),
+        ]),
+        Line::from(vec![
+            Span::styled("  Names: ", theme.text_dimmed()),
+            Span::styled(app.name_strictness.label(), theme.text_muted()),
+            Span::styled("  Subfolder: ", theme.text_dimmed()),
+            Span::styled(app.receive_subfolder.label(), theme.text_muted()),
+        ]),
+        Line::from(vec![
+            Span::styled("  Verify: ", theme.text_dimmed()),
+            Span::styled(
+                if app.verify_receives {
+                    format!("on, {}", app.checksum_manifest.label())
+                } else {
+                    "off".to_string()
+                },
+                theme.text_muted(),
+            ),
+        ]),
+    ];
+    if !app.receive_routes.is_empty() {
+        info.push(Line::from(vec![
+            Span::styled("  Routes: ", theme.text_dimmed()),
+            Span::styled(
+                format!("{} rule(s) from config", app.receive_routes.len()),
+                theme.text_muted(),
+            ),
+        ]));
+    }
+    if !app.receive_policy.is_empty() {
+        info.push(Line::from(vec![
+            Span::styled("  Limits: ", theme.text_dimmed()),
+            Span::styled(app.receive_policy.summary(), theme.text_muted()),
+        ]));
+    }
     let save_area = Rect {
         y: popup.y + height + 1,
         x: popup.x,
         width,
-        height: 2,
+        height: info.len() as u16,
     };
-
-    let save_info = Paragraph::new(vec![Line::from(vec![
-        Span::styled("  Save to: ", theme.text_dimmed()),
-        Span::styled(app.receive_dir.display().to_string(), theme.text()),
-    ])]);
+    let save_info = Paragraph::new(info);
 
     if save_area.y + save_area.height <= area.y + area.height {
         frame.render_widget(save_info, save_area);
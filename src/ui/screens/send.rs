@@ -11,6 +11,7 @@ use tui_tree_widget::Tree;
 
 use crate::app::App;
 use crate::theme::ThemeColors;
+use crate::transfer::SelectionSummary;
 
 pub fn draw(frame: &mut Frame, app: &mut App, theme: &ThemeColors, area: Rect) {
     let chunks = Layout::default()
@@ -19,7 +20,8 @@ pub fn draw(frame: &mut Frame, app: &mut App, theme: &ThemeColors, area: Rect) {
         .split(area);
 
     draw_tree_browser(frame, app, theme, chunks[0]);
-    draw_selection(frame, app, theme, chunks[1]);
+    let summary = app.send_summary();
+    draw_selection(frame, app, summary, theme, chunks[1]);
 }
 
 fn draw_tree_browser(frame: &mut Frame, app: &mut App, theme: &ThemeColors, area: Rect) {
@@ -66,7 +68,13 @@ fn draw_tree_browser(frame: &mut Frame, app: &mut App, theme: &ThemeColors, area
     frame.render_stateful_widget(tree, tree_area, &mut app.tree_browser.state);
 }
 
-fn draw_selection(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect) {
+fn draw_selection(
+    frame: &mut Frame,
+    app: &App,
+    summary: Option<SelectionSummary>,
+    theme: &ThemeColors,
+    area: Rect,
+) {
     let count = app.tree_browser.selected.len();
     let title = format!(" Selected ({}) ", count);
 
@@ -95,16 +103,80 @@ fn draw_selection(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect)
         return;
     }
 
-    let total_size: u64 = app
-        .tree_browser
-        .selected
-        .iter()
-        .filter_map(|p| p.metadata().ok())
-        .map(|m| m.len())
-        .sum();
-    let size_str = humansize::format_size(total_size, humansize::BINARY);
+    let summary = summary.unwrap_or_default();
+    let at_least = if summary.truncated { "≥ " } else { "" };
+    let mut bottom_lines = vec![Line::from(Span::styled(
+        format!(
+            "  Total: {}{} in {}{} files",
+            at_least,
+            humansize::format_size(summary.bytes, humansize::BINARY),
+            at_least,
+            summary.files
+        ),
+        theme.text_muted(),
+    ))];
+
+    // Say what the exclude rules left out, so nothing goes missing silently
+    if summary.excluded.entries > 0 {
+        bottom_lines.push(Line::from(Span::styled(
+            format!(
+                "  Excluded: {} ({})  E:edit",
+                summary.excluded.entries,
+                humansize::format_size(summary.excluded.bytes, humansize::BINARY)
+            ),
+            theme.text_dimmed(),
+        )));
+    }
 
-    let list_height = inner.height.saturating_sub(4);
+    if let Some(ref title) = app.share_title {
+        bottom_lines.push(Line::from(vec![
+            Span::styled("  Title: ", theme.text_dimmed()),
+            Span::styled(title.as_str(), theme.text()),
+        ]));
+    }
+    if let Some(ref note) = app.share_note {
+        bottom_lines.push(Line::from(vec![
+            Span::styled("  Note: ", theme.text_dimmed()),
+            Span::styled(note.as_str(), theme.text_muted()),
+        ]));
+    }
+
+    // In-place sends serve straight from disk, so edits break the transfer
+    if app.in_place_sends && !app.archive_sends {
+        bottom_lines.push(Line::from(Span::styled(
+            "  In place: don't modify files until sent",
+            theme.warning(),
+        )));
+    }
+
+    // Picked entries go into a running share rather than a new send
+    let target = app
+        .adding_to_share
+        .as_ref()
+        .and_then(|id| app.transfers.iter().find(|t| &t.id == id));
+    if let Some(transfer) = target {
+        bottom_lines.push(Line::from(Span::styled(
+            format!("  Adding to: {}  Esc:back", transfer.name),
+            theme.info(),
+        )));
+    }
+
+    bottom_lines.push(Line::from(""));
+    bottom_lines.push(Line::from(vec![
+        Span::raw("  "),
+        Span::styled(
+            if target.is_some() {
+                "[ ADD TO SHARE ]"
+            } else {
+                "[ SEND ]"
+            },
+            theme.success().add_modifier(ratatui::style::Modifier::BOLD),
+        ),
+        Span::styled("  s or Enter", theme.text_dimmed()),
+    ]));
+    let bottom_height = bottom_lines.len() as u16;
+
+    let list_height = inner.height.saturating_sub(bottom_height + 1);
     let items: Vec<ListItem> = app
         .tree_browser
         .selected
@@ -135,25 +207,9 @@ fn draw_selection(frame: &mut Frame, app: &App, theme: &ThemeColors, area: Rect)
     );
 
     let bottom_area = Rect {
-        y: inner.y + inner.height.saturating_sub(3),
-        height: 3,
+        y: inner.y + inner.height.saturating_sub(bottom_height),
+        height: bottom_height.min(inner.height),
         ..inner
     };
-
-    let bottom = Paragraph::new(vec![
-        Line::from(Span::styled(
-            format!("  Total: {}", size_str),
-            theme.text_muted(),
-        )),
-        Line::from(""),
-        Line::from(vec![
-            Span::raw("  "),
-            Span::styled(
-                "[ SEND ]",
-                theme.success().add_modifier(ratatui::style::Modifier::BOLD),
-            ),
-            Span::styled("  s or Enter", theme.text_dimmed()),
-        ]),
-    ]);
-    frame.render_widget(bottom, bottom_area);
+    frame.render_widget(Paragraph::new(bottom_lines), bottom_area);
 }
@@ -121,19 +121,39 @@ impl Widget for TransferItem<'_> {
         let eta = t.eta_seconds().map(format_eta).unwrap_or_default();
 
         // Build conflict resolution indicator
-        let conflict_info = t.conflict_resolution.as_ref().map(|r| {
-            let (label, style) = match r {
-                ConflictResolution::Rename => ("renamed", theme.info()),
-                ConflictResolution::Overwrite => ("overwritten", theme.warning()),
-                ConflictResolution::Skip => ("skipped existing", theme.text_dimmed()),
-                ConflictResolution::Cancel => ("cancelled", theme.error()),
-            };
-            (label, style)
-        });
+        let conflict_info = match t.conflict_resolution.as_ref() {
+            Some(r) => {
+                let (label, style) = match r {
+                    ConflictResolution::Rename => ("renamed", theme.info()),
+                    ConflictResolution::Overwrite => ("overwritten", theme.warning()),
+                    ConflictResolution::Skip => ("skipped existing", theme.text_dimmed()),
+                    ConflictResolution::Cancel => ("cancelled", theme.error()),
+                    ConflictResolution::SkipIdentical => ("identical skipped", theme.success()),
+                    ConflictResolution::KeepNewer => ("kept newer", theme.info()),
+                };
+                Some((label, style))
+            }
+            None if !t.conflict_resolutions.is_empty() => Some(("mixed conflicts", theme.info())),
+            None => None,
+        };
 
         // For uploads with a ticket, show the ticket prominently
         let line2 = if t.direction == TransferDirection::Upload {
-            if let Some(ref ticket) = t.ticket {
+            if let (Some(import), TransferStatus::Preparing) = (&t.import, &t.status) {
+                Line::from(vec![
+                    Span::styled(
+                        format!(
+                            "    Hashing {}/{} files  {} / {}  ",
+                            import.files_done,
+                            import.files_total,
+                            humansize::format_size(import.hashed_bytes, humansize::BINARY),
+                            humansize::format_size(import.total_bytes, humansize::BINARY),
+                        ),
+                        theme.text_muted(),
+                    ),
+                    Span::styled(import.current.clone(), theme.text_dimmed()),
+                ])
+            } else if let Some(ref ticket) = t.ticket {
                 // Show ticket (truncated to fit)
                 let max_ticket_len = area.width.saturating_sub(10) as usize;
                 let display_ticket = if ticket.len() > max_ticket_len {
@@ -141,11 +161,21 @@ impl Widget for TransferItem<'_> {
                 } else {
                     ticket.clone()
                 };
-                Line::from(vec![
+                let mut spans = vec![
                     Span::styled("    Ticket: ", theme.text_dimmed()),
                     Span::styled(display_ticket, theme.text_highlight()),
                     Span::styled(" (copied)", theme.success()),
-                ])
+                ];
+                if !t.skipped.is_empty() {
+                    spans.push(Span::styled(
+                        format!("  [{} skipped]", t.skipped.len()),
+                        theme.warning(),
+                    ));
+                }
+                if let Some(ref err) = t.error_message {
+                    spans.push(Span::styled(format!("  {}", err), theme.error()));
+                }
+                Line::from(spans)
             } else {
                 Line::from(Span::styled(
                     "    Generating ticket...",
@@ -191,6 +221,19 @@ impl Widget for TransferItem<'_> {
             },
             buf,
         );
+
+        // Line 3: the sender's note, when there is one and room for it
+        if let (Some(note), true) = (&t.note, area.height >= 3) {
+            let line3 = Line::from(Span::styled(format!("    “{}”", note), theme.text_dimmed()));
+            Paragraph::new(line3).render(
+                Rect {
+                    y: area.y + 2,
+                    height: 1,
+                    ..area
+                },
+                buf,
+            );
+        }
     }
 }
 